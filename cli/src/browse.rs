@@ -0,0 +1,402 @@
+//! Interactive browser for app info files, behind the `tui` feature.
+//!
+//! The left pane lists the apps with a fuzzy search on their ID and name, the right pane
+//! shows the key-values of the selected app as an expandable tree.
+
+use std::{collections::BTreeSet, io::Write};
+
+use base64::Engine;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use vdfr::{AppInfo, KeyValues, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Apps,
+    Search,
+    Tree,
+}
+
+/// A visible line of the key-values tree.
+struct TreeRow {
+    path: Vec<String>,
+    depth: usize,
+    key: String,
+    value: String,
+    expandable: bool,
+}
+
+struct Browser {
+    app_info: AppInfo,
+    /// ID and name of every app, in the order of the file.
+    apps: Vec<(u32, String)>,
+    search: String,
+    /// Indices into `apps` matching the search, best match first.
+    filtered: Vec<usize>,
+    apps_state: ListState,
+    tree_state: ListState,
+    /// Paths of the expanded sections of the tree.
+    expanded: BTreeSet<Vec<String>>,
+    focus: Focus,
+    status: String,
+}
+
+impl Browser {
+    fn new(app_info: AppInfo) -> Self {
+        let apps = app_info
+            .apps
+            .values()
+            .map(|app| {
+                let name = app
+                    .get(&["appinfo", "common", "name"])
+                    .or_else(|| app.get(&["common", "name"]));
+                let name = match name {
+                    Some(Value::StringType(name)) => name.to_string(),
+                    Some(Value::WideStringType(name)) => name.to_string_lossy(),
+                    _ => String::new(),
+                };
+                (app.id, name)
+            })
+            .collect();
+
+        let mut browser = Browser {
+            app_info,
+            apps,
+            search: String::new(),
+            filtered: Vec::new(),
+            apps_state: ListState::default(),
+            tree_state: ListState::default(),
+            expanded: BTreeSet::new(),
+            focus: Focus::Apps,
+            status: String::from("/ search, tab switch pane, enter expand, c copy as JSON, q quit"),
+        };
+        browser.update_filter();
+        browser
+    }
+
+    fn update_filter(&mut self) {
+        if self.search.is_empty() {
+            self.filtered = (0..self.apps.len()).collect();
+        } else {
+            let matcher = SkimMatcherV2::default();
+            let mut scored = self
+                .apps
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (id, name))| {
+                    let score = matcher.fuzzy_match(&format!("{} {}", id, name), &self.search)?;
+                    Some((score, index))
+                })
+                .collect::<Vec<_>>();
+            scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            self.filtered = scored.into_iter().map(|(_, index)| index).collect();
+        }
+
+        self.apps_state
+            .select((!self.filtered.is_empty()).then_some(0));
+        self.reset_tree();
+    }
+
+    fn reset_tree(&mut self) {
+        self.expanded.clear();
+        self.tree_state.select(Some(0));
+    }
+
+    fn selected_app(&self) -> Option<&vdfr::App> {
+        let index = *self.filtered.get(self.apps_state.selected()?)?;
+        self.app_info.apps.get(&self.apps[index].0)
+    }
+
+    fn tree_rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        if let Some(app) = self.selected_app() {
+            self.push_rows(&mut rows, &app.key_values, &mut Vec::new());
+        }
+        rows
+    }
+
+    fn push_rows(&self, rows: &mut Vec<TreeRow>, key_values: &KeyValues, path: &mut Vec<String>) {
+        for (key, value) in key_values {
+            path.push(key.to_string());
+            self.push_row(rows, key, value, path);
+            path.pop();
+        }
+    }
+
+    fn push_row(&self, rows: &mut Vec<TreeRow>, key: &str, value: &Value, path: &mut Vec<String>) {
+        let expandable = matches!(value, Value::KeyValueType(_) | Value::ArrayType(_));
+        let expanded = self.expanded.contains(path);
+        rows.push(TreeRow {
+            path: path.clone(),
+            depth: path.len() - 1,
+            key: key.to_string(),
+            value: match value {
+                Value::KeyValueType(kv) => format!("{{{}}}", kv.len()),
+                Value::ArrayType(items) => format!("[{}]", items.len()),
+                _ => format!("{:?}", value),
+            },
+            expandable,
+        });
+
+        if !expanded {
+            return;
+        }
+        match value {
+            Value::KeyValueType(kv) => self.push_rows(rows, kv, path),
+            Value::ArrayType(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    let key = index.to_string();
+                    path.push(key.clone());
+                    self.push_row(rows, &key, item, path);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Find the value at a tree path in the selected app.
+    fn value_at(&self, path: &[String]) -> Option<&Value> {
+        let app = self.selected_app()?;
+        let (first, rest) = path.split_first()?;
+        rest.iter().try_fold(
+            app.key_values.get(first.as_str())?,
+            |value, key| match value {
+                Value::KeyValueType(kv) => kv.get(key.as_str()),
+                Value::ArrayType(items) => items.get(key.parse::<usize>().ok()?),
+                _ => None,
+            },
+        )
+    }
+
+    /// Copy the selected tree node, or the whole app from the app list, to the clipboard.
+    fn copy_json(&mut self) {
+        let json = match self.focus {
+            Focus::Tree => {
+                let rows = self.tree_rows();
+                self.tree_state
+                    .selected()
+                    .and_then(|index| rows.get(index))
+                    .and_then(|row| self.value_at(&row.path))
+                    .map(|value| vdfr::serde_json::to_string_pretty(value).unwrap())
+            }
+            _ => self
+                .selected_app()
+                .map(|app| vdfr::serde_json::to_string_pretty(app).unwrap()),
+        };
+
+        self.status = match json {
+            Some(json) => match copy_to_clipboard(&json) {
+                Ok(()) => format!("Copied {} bytes of JSON", json.len()),
+                Err(e) => format!("Failed to copy: {}", e),
+            },
+            None => String::from("Nothing to copy"),
+        };
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Focus::Tree => {
+                let len = self.tree_rows().len();
+                (&mut self.tree_state, len)
+            }
+            _ => (&mut self.apps_state, self.filtered.len()),
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        state.select(Some(next));
+
+        if self.focus != Focus::Tree {
+            self.reset_tree();
+        }
+    }
+
+    /// Expand or collapse the selected tree node.
+    fn set_expanded(&mut self, expand: Option<bool>) {
+        let rows = self.tree_rows();
+        let Some(row) = self.tree_state.selected().and_then(|index| rows.get(index)) else {
+            return;
+        };
+        if !row.expandable {
+            return;
+        }
+
+        let expand = expand.unwrap_or(!self.expanded.contains(&row.path));
+        if expand {
+            self.expanded.insert(row.path.clone());
+        } else {
+            self.expanded.remove(&row.path);
+        }
+    }
+
+    /// Handle a key press, returning false to quit.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if self.focus == Focus::Search {
+            match code {
+                KeyCode::Esc | KeyCode::Enter => self.focus = Focus::Apps,
+                KeyCode::Backspace => {
+                    self.search.pop();
+                    self.update_filter();
+                }
+                KeyCode::Char(c) => {
+                    self.search.push(c);
+                    self.update_filter();
+                }
+                KeyCode::Up => self.move_selection(-1),
+                KeyCode::Down => self.move_selection(1),
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Tree => Focus::Apps,
+                    _ => Focus::Tree,
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-20),
+            KeyCode::PageDown => self.move_selection(20),
+            KeyCode::Enter | KeyCode::Char(' ') if self.focus == Focus::Tree => {
+                self.set_expanded(None)
+            }
+            KeyCode::Right | KeyCode::Char('l') if self.focus == Focus::Tree => {
+                self.set_expanded(Some(true))
+            }
+            KeyCode::Left | KeyCode::Char('h') if self.focus == Focus::Tree => {
+                self.set_expanded(Some(false))
+            }
+            KeyCode::Enter | KeyCode::Right => self.focus = Focus::Tree,
+            KeyCode::Char('c') => self.copy_json(),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let [search, apps] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(left);
+
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+        let border = |focus: Focus| {
+            if self.focus == focus {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            }
+        };
+
+        let search_widget = Paragraph::new(self.search.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border(Focus::Search))
+                .title("Search"),
+        );
+        frame.render_widget(search_widget, search);
+
+        let items = self
+            .filtered
+            .iter()
+            .map(|&index| {
+                let (id, name) = &self.apps[index];
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:>8} ", id), Style::default().fg(Color::Cyan)),
+                    Span::raw(name.as_str()),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let apps_widget = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border(Focus::Apps))
+                    .title(format!(
+                        "Apps ({}/{})",
+                        self.filtered.len(),
+                        self.apps.len()
+                    )),
+            )
+            .highlight_style(highlight);
+        frame.render_stateful_widget(apps_widget, apps, &mut self.apps_state);
+
+        let rows = self
+            .tree_rows()
+            .into_iter()
+            .map(|row| {
+                let marker = match (row.expandable, self.expanded.contains(&row.path)) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    _ => "  ",
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{}{}", "  ".repeat(row.depth), marker)),
+                    Span::styled(row.key, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" "),
+                    Span::styled(row.value, Style::default().fg(Color::Green)),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let title = match self.selected_app() {
+            Some(app) => format!("App {} (change {})", app.id, app.change_number),
+            None => String::from("No app selected"),
+        };
+        let tree_widget = List::new(rows)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border(Focus::Tree))
+                    .title(title),
+            )
+            .highlight_style(highlight);
+        frame.render_stateful_widget(tree_widget, right, &mut self.tree_state);
+
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+    }
+}
+
+/// Copy text to the clipboard with the OSC 52 escape sequence, supported by most terminals.
+fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
+
+fn run(terminal: &mut DefaultTerminal, browser: &mut Browser) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !browser.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub fn browse(app_info: AppInfo) -> std::io::Result<()> {
+    let mut browser = Browser::new(app_info);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut browser);
+    ratatui::restore();
+    result
+}
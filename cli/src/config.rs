@@ -0,0 +1,74 @@
+//! Defaults of the commands, read from `~/.config/vdfr/config.toml` or the `--config` file.
+//!
+//! ```toml
+//! backend = "legacy"
+//! format = "json-pretty"
+//! strip = ["depots/*/encryptedmanifests", "extended/developer_*"]
+//! steam_dir = "/mnt/games/Steam"
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::format::DumpFormat;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Parser used unless `--legacy` is given.
+    pub backend: Backend,
+    /// Format of the dumps when none of `--format`, `--json-only` or `--vdf-only` is given.
+    pub format: Option<DumpFormat>,
+    /// Paths removed from every app before dumping, on top of the `--strip` ones.
+    pub strip: Vec<String>,
+    /// Steam installation used instead of looking for it.
+    pub steam_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Nom,
+    Legacy,
+}
+
+/// Path of the default config file, in the config directory of the platform.
+fn default_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        PathBuf::from(dir)
+    } else {
+        PathBuf::from(std::env::var_os("HOME")?).join(".config")
+    };
+    Some(config_dir.join("vdfr").join("config.toml"))
+}
+
+/// Read the config file, the given one has to exist while the default one is optional.
+pub fn load(path: Option<&Path>) -> Result<(), String> {
+    let (path, required) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(()),
+        },
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(format!("{}: {}", path.display(), err)),
+    };
+    let config = toml::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+    CONFIG.set(config).expect("the config is only loaded once");
+    Ok(())
+}
+
+/// The loaded config, or the defaults without a config file.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
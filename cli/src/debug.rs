@@ -0,0 +1,319 @@
+//! Structural dump of binary VDF files, printing the offset of every node as it's read.
+//!
+//! This walks the bytes on its own instead of using the parser, so it can keep printing up to
+//! the exact byte where a corrupt file stops making sense.
+
+use vdfr::{BinType, ParseLimits};
+
+use crate::FileKind;
+
+/// Longest string value printed, longer values are truncated.
+const VALUE_WIDTH: usize = 48;
+
+struct Walker<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// String pool of v29 app info, keys are indices into it.
+    pool: Option<Vec<String>>,
+}
+
+type WalkResult<T> = Result<T, String>;
+
+impl Walker<'_> {
+    fn take(&mut self, len: usize) -> WalkResult<&[u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| {
+                format!(
+                    "need {} bytes at offset {:#010x}, only {} left",
+                    len,
+                    self.pos,
+                    self.data.len().saturating_sub(self.pos)
+                )
+            })?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> WalkResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> WalkResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> WalkResult<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn cstring(&mut self) -> WalkResult<String> {
+        let start = self.pos;
+        let len = self.data[start..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| format!("unterminated string at offset {:#010x}", start))?;
+        let bytes = self.take(len + 1)?;
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    fn wide_string(&mut self) -> WalkResult<String> {
+        let start = self.pos;
+        let mut units = Vec::new();
+        loop {
+            let bytes = self
+                .take(2)
+                .map_err(|_| format!("unterminated wide string at offset {:#010x}", start))?;
+            let unit = u16::from_le_bytes([bytes[0], bytes[1]]);
+            if unit == 0 {
+                return Ok(String::from_utf16_lossy(&units));
+            }
+            units.push(unit);
+        }
+    }
+
+    fn key(&mut self) -> WalkResult<String> {
+        if self.pool.is_none() {
+            return self.cstring();
+        }
+
+        let start = self.pos;
+        let index = self.u32()? as usize;
+        let pool = self.pool.as_ref().unwrap();
+        pool.get(index).cloned().ok_or_else(|| {
+            format!(
+                "string pool index {} out of range ({} strings) at offset {:#010x}",
+                index,
+                pool.len(),
+                start
+            )
+        })
+    }
+
+    /// Walk the nodes of a key-values section until its end byte.
+    ///
+    /// The end is printed with the length of the whole section, from its start offset.
+    fn walk_nodes(&mut self, depth: usize, start: usize) -> WalkResult<()> {
+        loop {
+            let offset = self.pos;
+            let kind = self.u8()?;
+            let bin = BinType::from_u8(kind);
+            if matches!(bin, Some(BinType::End | BinType::EndAlt)) {
+                print_line(offset, kind, depth, "", "", self.pos - start);
+                return Ok(());
+            }
+
+            let key = self.key()?;
+            let value_start = self.pos;
+            let value = match bin {
+                Some(BinType::Map) => {
+                    print_line(offset, kind, depth, &key, "", value_start - offset);
+                    // Same limit as the parser, deeper sections would overflow the stack
+                    if depth >= ParseLimits::DEFAULT_MAX_DEPTH {
+                        return Err(format!(
+                            "section nested deeper than {} at offset {:#010x}",
+                            ParseLimits::DEFAULT_MAX_DEPTH,
+                            offset
+                        ));
+                    }
+                    self.walk_nodes(depth + 1, offset)?;
+                    continue;
+                }
+                Some(BinType::String) => truncate(&format!("{:?}", self.cstring()?)),
+                Some(BinType::WideString) => truncate(&format!("{:?}", self.wide_string()?)),
+                Some(BinType::Int32 | BinType::Pointer | BinType::Color) => {
+                    i32::from_le_bytes(self.take(4)?.try_into().unwrap()).to_string()
+                }
+                Some(BinType::Float32) => {
+                    f32::from_le_bytes(self.take(4)?.try_into().unwrap()).to_string()
+                }
+                Some(BinType::UInt64) => self.u64()?.to_string(),
+                Some(BinType::Int64) => (self.u64()? as i64).to_string(),
+                _ => {
+                    return Err(format!(
+                        "invalid type byte {:#04x} at offset {:#010x}",
+                        kind, offset
+                    ))
+                }
+            };
+            print_line(offset, kind, depth, &key, &value, self.pos - offset);
+        }
+    }
+
+    fn walk_app_info(&mut self) -> WalkResult<()> {
+        let magic = self.u32()?;
+        let universe = self.u32()?;
+        println!("{:#010x}  magic {:#010x}, universe {}", 0, magic, universe);
+
+        let version = magic & 0xff;
+        if version >= 0x29 {
+            let offset = self.u64()? as usize;
+            println!("{:#010x}  string pool offset {:#010x}", 8, offset);
+            self.read_pool(offset)?;
+        }
+
+        loop {
+            let offset = self.pos;
+            let app_id = self.u32()?;
+            if app_id == 0 {
+                println!("{:#010x}  end of apps", offset);
+                break;
+            }
+            let size = self.u32()?;
+            // state + last_update (+ access_token + checksum_txt)
+            let skip = match version {
+                0x24 => 8,
+                0x26 => 16,
+                _ => 36,
+            };
+            self.take(skip)?;
+            let change_number = self.u32()?;
+            if version >= 0x28 {
+                self.take(20)?;
+            }
+            println!(
+                "{:#010x}  app {}, size {}, change {}, ends at {:#010x}",
+                offset,
+                app_id,
+                size,
+                change_number,
+                offset + 8 + size as usize
+            );
+
+            if version == 0x24 {
+                loop {
+                    let section_offset = self.pos;
+                    let section = self.u8()?;
+                    if section == 0 {
+                        break;
+                    }
+                    println!("{:#010x}  section {}", section_offset, section);
+                    self.walk_nodes(1, self.pos)?;
+                }
+            } else {
+                self.walk_nodes(1, self.pos)?;
+            }
+        }
+        self.print_trailing();
+        Ok(())
+    }
+
+    fn read_pool(&mut self, offset: usize) -> WalkResult<()> {
+        let mut pool_walker = Walker {
+            data: self.data,
+            pos: offset,
+            pool: None,
+        };
+        let count = pool_walker.u32()?;
+        let strings = (0..count)
+            .map(|_| pool_walker.cstring())
+            .collect::<WalkResult<Vec<_>>>()?;
+        println!(
+            "{:#010x}  string pool of {} strings, ends at {:#010x}",
+            offset, count, pool_walker.pos
+        );
+
+        self.pool = Some(strings);
+        // The apps end where the pool starts
+        self.data = &self.data[..offset];
+        Ok(())
+    }
+
+    fn walk_package_info(&mut self) -> WalkResult<()> {
+        let magic = self.u32()?;
+        let universe = self.u32()?;
+        println!("{:#010x}  magic {:#010x}, universe {}", 0, magic, universe);
+
+        while self.pos < self.data.len() {
+            let offset = self.pos;
+            let package_id = self.u32()?;
+            if package_id == 0xffffffff {
+                println!("{:#010x}  end of packages", offset);
+                break;
+            }
+            self.take(20)?;
+            let change_number = self.u32()?;
+            if magic & 0xff >= 0x28 {
+                self.take(8)?;
+            }
+            println!(
+                "{:#010x}  package {}, change {}",
+                offset, package_id, change_number
+            );
+            self.walk_nodes(1, self.pos)?;
+        }
+        self.print_trailing();
+        Ok(())
+    }
+
+    fn print_trailing(&self) {
+        if self.pos < self.data.len() {
+            println!(
+                "{:#010x}  {} trailing bytes",
+                self.pos,
+                self.data.len() - self.pos
+            );
+        }
+    }
+}
+
+fn truncate(value: &str) -> String {
+    if value.chars().count() > VALUE_WIDTH {
+        let truncated: String = value.chars().take(VALUE_WIDTH - 1).collect();
+        format!("{}…", truncated)
+    } else {
+        value.to_string()
+    }
+}
+
+fn type_name(kind: u8) -> &'static str {
+    match BinType::from_u8(kind) {
+        Some(BinType::Map) => "kv",
+        Some(BinType::String) => "string",
+        Some(BinType::Int32) => "int32",
+        Some(BinType::Float32) => "float32",
+        Some(BinType::Pointer) => "pointer",
+        Some(BinType::WideString) => "wstring",
+        Some(BinType::Color) => "color",
+        Some(BinType::UInt64) => "uint64",
+        Some(BinType::End | BinType::EndAlt) => "end",
+        Some(BinType::Int64) => "int64",
+        None => "unknown",
+    }
+}
+
+fn print_line(offset: usize, kind: u8, depth: usize, key: &str, value: &str, len: usize) {
+    println!(
+        "{:#010x}  {:02x} {:<7} {:>6}  {}{} {}",
+        offset,
+        kind,
+        type_name(kind),
+        len,
+        "  ".repeat(depth),
+        key,
+        value
+    );
+}
+
+/// Print every node of the data with its offset, type byte and encoded length.
+///
+/// Stops at the first byte that can't be decoded, and returns the reason.
+pub fn dump(kind: FileKind, data: &[u8]) -> Result<(), String> {
+    let mut walker = Walker {
+        data,
+        pos: 0,
+        pool: None,
+    };
+    match kind {
+        FileKind::AppInfo => walker.walk_app_info(),
+        FileKind::PackageInfo => walker.walk_package_info(),
+        FileKind::KeyValues => {
+            walker.walk_nodes(0, 0)?;
+            walker.print_trailing();
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,569 @@
+//! Small jq-like expression language, to shape the JSON dumps without piping them into jq.
+//!
+//! Supported are paths (`.`, `.key`, `."key"`, `.[]`, `.[0]`, `.["key"]`), pipes `|`,
+//! `select(...)`, the comparisons `==`, `!=`, `<`, `<=`, `>` and `>=`, `and`, `or`, `not`,
+//! `length`, `keys`, parentheses and string, number, `true`, `false` and `null` literals.
+
+use std::cmp::Ordering;
+
+use vdfr::serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// `.` not followed by a key.
+    Dot,
+    /// `.key` or `."key"`.
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Pipe,
+    Cmp(CmpOp),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Identity,
+    Literal(Value),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, i64),
+    Iterate(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Select(Box<Expr>),
+    Compare(Box<Expr>, CmpOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not,
+    Length,
+    Keys,
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn lex(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '.' => match chars.peek() {
+                Some(&(_, '"')) => {
+                    chars.next();
+                    Token::Field(lex_string(&mut chars)?)
+                }
+                Some(&(_, c)) if is_ident_char(c) => {
+                    let mut name = String::new();
+                    while let Some(&(_, c)) = chars.peek().filter(|(_, c)| is_ident_char(*c)) {
+                        name.push(c);
+                        chars.next();
+                    }
+                    Token::Field(name)
+                }
+                _ => Token::Dot,
+            },
+            '"' => Token::Str(lex_string(&mut chars)?),
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '|' => Token::Pipe,
+            '=' | '!' | '<' | '>' => {
+                let equals = chars.next_if(|&(_, c)| c == '=').is_some();
+                Token::Cmp(match (c, equals) {
+                    ('=', true) => CmpOp::Eq,
+                    ('!', true) => CmpOp::Ne,
+                    ('<', false) => CmpOp::Lt,
+                    ('<', true) => CmpOp::Le,
+                    ('>', false) => CmpOp::Gt,
+                    ('>', true) => CmpOp::Ge,
+                    _ => return Err(format!("unexpected `{}` at {}", c, start)),
+                })
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.') {
+                    number.push(c);
+                }
+                Token::Num(
+                    number
+                        .parse()
+                        .map_err(|_| format!("invalid number `{}` at {}", number, start))?,
+                )
+            }
+            c if is_ident_char(c) => {
+                let mut name = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| is_ident_char(c)) {
+                    name.push(c);
+                }
+                Token::Ident(name)
+            }
+            c => return Err(format!("unexpected `{}` at {}", c, start)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Read a string after its opening quote, with `\"` and `\\` escapes.
+fn lex_string(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<String, String> {
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '"')) => return Ok(string),
+            Some((_, '\\')) => match chars.next() {
+                Some((_, 'n')) => string.push('\n'),
+                Some((_, 't')) => string.push('\t'),
+                Some((_, c)) => string.push(c),
+                None => break,
+            },
+            Some((_, c)) => string.push(c),
+            None => break,
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("expected {:?}, found the end", expected)),
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, String> {
+        let mut expr = self.or()?;
+        while self.peek() == Some(&Token::Pipe) {
+            self.next();
+            expr = Expr::Pipe(Box::new(expr), Box::new(self.or()?));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Ident("or".to_string())) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.compare()?;
+        while self.peek() == Some(&Token::Ident("and".to_string())) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.compare()?));
+        }
+        Ok(expr)
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let expr = self.postfix()?;
+        if let Some(&Token::Cmp(op)) = self.peek() {
+            self.next();
+            return Ok(Expr::Compare(Box::new(expr), op, Box::new(self.postfix()?)));
+        }
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Field(_)) => {
+                    let Some(Token::Field(name)) = self.next() else {
+                        unreachable!()
+                    };
+                    expr = Expr::Field(Box::new(expr), name);
+                }
+                Some(Token::LBracket) => {
+                    self.next();
+                    expr = match self.next() {
+                        Some(Token::RBracket) => Expr::Iterate(Box::new(expr)),
+                        Some(Token::Num(index)) if index.fract() == 0.0 => {
+                            self.expect(Token::RBracket)?;
+                            Expr::Index(Box::new(expr), index as i64)
+                        }
+                        Some(Token::Str(name)) => {
+                            self.expect(Token::RBracket)?;
+                            Expr::Field(Box::new(expr), name)
+                        }
+                        token => return Err(format!("unexpected {:?} in brackets", token)),
+                    };
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let expr = match self.next() {
+            Some(Token::Dot) => Expr::Identity,
+            Some(Token::Field(name)) => Expr::Field(Box::new(Expr::Identity), name),
+            Some(Token::Str(string)) => Expr::Literal(Value::String(string)),
+            Some(Token::Num(number)) => Expr::Literal(number_value(number)),
+            Some(Token::LParen) => {
+                let expr = self.pipe()?;
+                self.expect(Token::RParen)?;
+                expr
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                "not" => Expr::Not,
+                "length" => Expr::Length,
+                "keys" => Expr::Keys,
+                "select" => {
+                    self.expect(Token::LParen)?;
+                    let condition = self.pipe()?;
+                    self.expect(Token::RParen)?;
+                    Expr::Select(Box::new(condition))
+                }
+                _ => return Err(format!("unknown function `{}`", name)),
+            },
+            Some(token) => return Err(format!("unexpected {:?}", token)),
+            None => return Err("unexpected end of the filter".to_string()),
+        };
+        Ok(expr)
+    }
+}
+
+fn number_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Value::from(number as i64)
+    } else {
+        Value::from(number)
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn compare(a: &Value, op: CmpOp, b: &Value) -> Result<bool, String> {
+    let ordering = match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if matches!(op, CmpOp::Eq | CmpOp::Ne) => None,
+        _ => return Err(format!("cannot compare {} and {}", a, b)),
+    };
+    Ok(match op {
+        CmpOp::Eq => ordering.map_or(a == b, |ordering| ordering == Ordering::Equal),
+        CmpOp::Ne => ordering.map_or(a != b, |ordering| ordering != Ordering::Equal),
+        CmpOp::Lt => ordering == Some(Ordering::Less),
+        CmpOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        CmpOp::Gt => ordering == Some(Ordering::Greater),
+        CmpOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    })
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: lex(source)?,
+            pos: 0,
+        };
+        let expr = parser.pipe()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {:?} after the filter", token)),
+        }
+    }
+
+    /// Every output of the expression for the input.
+    pub fn eval(&self, input: &Value) -> Result<Vec<Value>, String> {
+        Ok(match self {
+            Expr::Identity => vec![input.clone()],
+            Expr::Literal(value) => vec![value.clone()],
+            Expr::Field(expr, name) => expr
+                .eval(input)?
+                .iter()
+                .map(|value| match value {
+                    Value::Object(map) => Ok(map.get(name).cloned().unwrap_or(Value::Null)),
+                    Value::Null => Ok(Value::Null),
+                    _ => Err(format!("cannot get the key `{}` of {}", name, value)),
+                })
+                .collect::<Result<_, _>>()?,
+            Expr::Index(expr, index) => expr
+                .eval(input)?
+                .iter()
+                .map(|value| match value {
+                    Value::Array(items) => {
+                        let index = if *index < 0 {
+                            items.len() as i64 + index
+                        } else {
+                            *index
+                        };
+                        Ok(usize::try_from(index)
+                            .ok()
+                            .and_then(|index| items.get(index))
+                            .cloned()
+                            .unwrap_or(Value::Null))
+                    }
+                    Value::Null => Ok(Value::Null),
+                    _ => Err(format!("cannot index {}", value)),
+                })
+                .collect::<Result<_, _>>()?,
+            Expr::Iterate(expr) => {
+                let mut outputs = Vec::new();
+                for value in expr.eval(input)? {
+                    match value {
+                        Value::Array(items) => outputs.extend(items),
+                        Value::Object(map) => outputs.extend(map.into_iter().map(|(_, v)| v)),
+                        value => return Err(format!("cannot iterate over {}", value)),
+                    }
+                }
+                outputs
+            }
+            Expr::Pipe(left, right) => {
+                let mut outputs = Vec::new();
+                for value in left.eval(input)? {
+                    outputs.extend(right.eval(&value)?);
+                }
+                outputs
+            }
+            Expr::Select(condition) => {
+                let keep = condition.eval(input)?.iter().any(truthy);
+                if keep {
+                    vec![input.clone()]
+                } else {
+                    Vec::new()
+                }
+            }
+            Expr::Compare(left, op, right) => {
+                let mut outputs = Vec::new();
+                for a in left.eval(input)? {
+                    for b in right.eval(input)? {
+                        outputs.push(Value::Bool(compare(&a, *op, &b)?));
+                    }
+                }
+                outputs
+            }
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let is_and = matches!(self, Expr::And(..));
+                let mut outputs = Vec::new();
+                for a in left.eval(input)? {
+                    if truthy(&a) != is_and {
+                        outputs.push(Value::Bool(!is_and));
+                        continue;
+                    }
+                    for b in right.eval(input)? {
+                        outputs.push(Value::Bool(truthy(&b)));
+                    }
+                }
+                outputs
+            }
+            Expr::Not => vec![Value::Bool(!truthy(input))],
+            Expr::Length => vec![match input {
+                Value::Null => Value::from(0),
+                Value::Bool(_) => return Err("a boolean has no length".to_string()),
+                Value::Number(number) => Value::from(number.as_f64().unwrap_or_default().abs()),
+                Value::String(string) => Value::from(string.chars().count()),
+                Value::Array(items) => Value::from(items.len()),
+                Value::Object(map) => Value::from(map.len()),
+            }],
+            Expr::Keys => match input {
+                Value::Object(map) => vec![Value::from(
+                    map.keys().cloned().map(Value::from).collect::<Vec<_>>(),
+                )],
+                Value::Array(items) => vec![Value::from((0..items.len()).collect::<Vec<_>>())],
+                value => return Err(format!("{} has no keys", value)),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vdfr::serde_json::json;
+
+    use super::*;
+
+    fn run(filter: &str, input: &Value) -> Result<Vec<Value>, String> {
+        Expr::parse(filter)?.eval(input)
+    }
+
+    #[test]
+    fn test_lex() {
+        assert_eq!(
+            lex(r#"."a b" .["c\"d"] -1.5 42"#).unwrap(),
+            [
+                Token::Field("a b".to_string()),
+                Token::Dot,
+                Token::LBracket,
+                Token::Str("c\"d".to_string()),
+                Token::RBracket,
+                Token::Num(-1.5),
+                Token::Num(42.0),
+            ]
+        );
+        assert_eq!(
+            lex("<= != >").unwrap(),
+            [
+                Token::Cmp(CmpOp::Le),
+                Token::Cmp(CmpOp::Ne),
+                Token::Cmp(CmpOp::Gt),
+            ]
+        );
+        assert_eq!(lex(r#""open"#).unwrap_err(), "unterminated string");
+        assert_eq!(lex("1.2.3").unwrap_err(), "invalid number `1.2.3` at 0");
+    }
+
+    #[test]
+    fn test_precedence() {
+        // `|` is looser than `and` and `or`, and `and` is tighter than `or`
+        assert!(matches!(
+            Expr::parse(".a | .b and .c").unwrap(),
+            Expr::Pipe(_, right) if matches!(*right, Expr::And(..))
+        ));
+        assert!(matches!(
+            Expr::parse(".a or .b and .c").unwrap(),
+            Expr::Or(_, right) if matches!(*right, Expr::And(..))
+        ));
+
+        let input = json!({ "a": true, "b": false, "c": false, "d": { "e": 1 } });
+        assert_eq!(run(".a or .b and .c", &input).unwrap(), [json!(true)]);
+        assert_eq!(run("(.a or .b) and .c", &input).unwrap(), [json!(false)]);
+        assert_eq!(
+            run(".d | .e == 1 and .e < 2", &input).unwrap(),
+            [json!(true)]
+        );
+    }
+
+    #[test]
+    fn test_select_and_compare() {
+        let input = json!([
+            { "type": "Game", "id": 10 },
+            { "type": "DLC", "id": 20 },
+            { "type": "Game", "id": 30 },
+        ]);
+        assert_eq!(
+            run(r#".[] | select(.type == "Game") | .id"#, &input).unwrap(),
+            [json!(10), json!(30)]
+        );
+        assert_eq!(
+            run(
+                r#".[] | select(.type != "Game" or .id >= 30) | .id"#,
+                &input
+            )
+            .unwrap(),
+            [json!(20), json!(30)]
+        );
+        assert_eq!(
+            run(".[] | select(.id > 10 and .id <= 20) | .id", &input).unwrap(),
+            [json!(20)]
+        );
+        assert_eq!(
+            run(".[] | select(.id < 20) | .id", &input).unwrap(),
+            [json!(10)]
+        );
+        assert_eq!(
+            run(".[] | select(.id == 10 | not) | .id", &input).unwrap(),
+            [json!(20), json!(30)]
+        );
+        // Equality works across types, ordering doesn't
+        assert_eq!(run(r#".[0].id == "10""#, &input).unwrap(), [json!(false)]);
+        assert_eq!(
+            run(r#".[0].id < "10""#, &input).unwrap_err(),
+            r#"cannot compare 10 and "10""#
+        );
+    }
+
+    #[test]
+    fn test_iterate_and_index() {
+        let array = json!([1, 2, 3]);
+        assert_eq!(run(".[]", &array).unwrap(), [json!(1), json!(2), json!(3)]);
+        assert_eq!(run(".[1]", &array).unwrap(), [json!(2)]);
+        assert_eq!(run(".[-1]", &array).unwrap(), [json!(3)]);
+        assert_eq!(run(".[5]", &array).unwrap(), [Value::Null]);
+
+        let object = json!({ "a": 1, "b": 2 });
+        assert_eq!(run(".[]", &object).unwrap(), [json!(1), json!(2)]);
+        assert_eq!(run(r#".["b"]"#, &object).unwrap(), [json!(2)]);
+        assert_eq!(
+            run(".[0]", &object).unwrap_err(),
+            r#"cannot index {"a":1,"b":2}"#
+        );
+    }
+
+    #[test]
+    fn test_keys_and_length() {
+        let input = json!({ "b": [1, 2, 3], "a": "héllo", "c": null });
+        assert_eq!(run("keys", &input).unwrap(), [json!(["a", "b", "c"])]);
+        assert_eq!(run(".b | keys", &input).unwrap(), [json!([0, 1, 2])]);
+        assert_eq!(run("length", &input).unwrap(), [json!(3)]);
+        assert_eq!(run(".b | length", &input).unwrap(), [json!(3)]);
+        assert_eq!(run(".a | length", &input).unwrap(), [json!(5)]);
+        assert_eq!(run(".c | length", &input).unwrap(), [json!(0)]);
+        assert_eq!(
+            run(".a | keys", &input).unwrap_err(),
+            r#""héllo" has no keys"#
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Expr::parse(".a |").unwrap_err(),
+            "unexpected end of the filter"
+        );
+        assert_eq!(
+            Expr::parse(".a )").unwrap_err(),
+            "unexpected RParen after the filter"
+        );
+        assert_eq!(
+            Expr::parse("frobnicate").unwrap_err(),
+            "unknown function `frobnicate`"
+        );
+        assert_eq!(
+            Expr::parse("select(.a").unwrap_err(),
+            "expected RParen, found the end"
+        );
+        assert_eq!(
+            Expr::parse(".[.a]").unwrap_err(),
+            r#"unexpected Some(Field("a")) in brackets"#
+        );
+
+        let input = json!({ "a": 1, "s": "text" });
+        assert_eq!(
+            run(".a.b", &input).unwrap_err(),
+            "cannot get the key `b` of 1"
+        );
+        assert_eq!(run(".s[0]", &input).unwrap_err(), r#"cannot index "text""#);
+        assert_eq!(run(".a[]", &input).unwrap_err(), "cannot iterate over 1");
+    }
+}
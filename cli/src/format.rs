@@ -0,0 +1,234 @@
+//! Formats of the dumps, from `--format` or the config.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use vdfr::{App, AppInfo, KeyValues, PackageInfo, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DumpFormat {
+    /// Compact JSON
+    Json,
+    /// Indented JSON
+    JsonPretty,
+    /// A line of compact JSON per app, package or top-level key, streamed while parsing
+    Ndjson,
+    Yaml,
+    /// MessagePack, with structs as maps
+    Msgpack,
+    /// Text key-values, with apps and packages as sections keyed by their ID
+    VdfText,
+    /// Binary VDF, like the parsed file
+    #[serde(alias = "vdf")]
+    VdfBin,
+}
+
+impl DumpFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            DumpFormat::Json => "json",
+            DumpFormat::JsonPretty => "json-pretty",
+            DumpFormat::Ndjson => "ndjson",
+            DumpFormat::Yaml => "yaml",
+            DumpFormat::Msgpack => "msgpack",
+            DumpFormat::VdfText => "vdf-text",
+            DumpFormat::VdfBin => "vdf-bin",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            DumpFormat::Json | DumpFormat::JsonPretty => "json",
+            DumpFormat::Ndjson => "ndjson",
+            DumpFormat::Yaml => "yaml",
+            DumpFormat::Msgpack => "msgpack",
+            DumpFormat::VdfText | DumpFormat::VdfBin => "vdf",
+        }
+    }
+
+    /// The format goes through serde, so it can hold the outputs of a `--filter`.
+    pub fn is_serde(self) -> bool {
+        !matches!(self, DumpFormat::VdfText | DumpFormat::VdfBin)
+    }
+
+    /// Write the data in this format.
+    pub fn write<D: DumpData + ?Sized>(self, writer: &mut dyn Write, data: &D) -> io::Result<()> {
+        match self {
+            DumpFormat::Ndjson => data.write_ndjson(writer),
+            DumpFormat::VdfBin => data.write_vdf(writer),
+            DumpFormat::VdfText => {
+                writer.write_all(vdfr::text::write_text(&data.text_keyvalues()).as_bytes())
+            }
+            _ => self.serialize(writer, data),
+        }
+    }
+
+    /// Write the outputs of a filter one after another, JSON ones on their own lines and
+    /// YAML ones as separate documents.
+    pub fn write_all<T: serde::Serialize>(
+        self,
+        writer: &mut dyn Write,
+        values: &[T],
+    ) -> io::Result<()> {
+        for value in values {
+            if self == DumpFormat::Yaml {
+                writer.write_all(b"---\n")?;
+            }
+            self.serialize(writer, value)?;
+            if matches!(
+                self,
+                DumpFormat::Json | DumpFormat::JsonPretty | DumpFormat::Ndjson
+            ) {
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize<T: serde::Serialize + ?Sized>(
+        self,
+        writer: &mut dyn Write,
+        value: &T,
+    ) -> io::Result<()> {
+        match self {
+            DumpFormat::Json | DumpFormat::Ndjson => vdfr::serde_json::to_writer(writer, value)?,
+            DumpFormat::JsonPretty => vdfr::serde_json::to_writer_pretty(writer, value)?,
+            DumpFormat::Yaml => serde_yaml::to_writer(writer, value).map_err(io::Error::other)?,
+            DumpFormat::Msgpack => {
+                rmp_serde::encode::write_named(writer, value).map_err(io::Error::other)?
+            }
+            DumpFormat::VdfText | DumpFormat::VdfBin => {
+                unreachable!("VDF isn't written through serde")
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Data the dump commands write, in any of the formats.
+pub trait DumpData: serde::Serialize {
+    fn write_vdf(&self, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// Write every app, package or top-level key on its own line.
+    fn write_ndjson(&self, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// The key-values written as text VDF.
+    fn text_keyvalues(&self) -> Cow<'_, KeyValues>;
+}
+
+impl DumpData for AppInfo {
+    fn write_vdf(&self, mut writer: &mut dyn Write) -> io::Result<()> {
+        vdfr::writer::write_app_info(&mut writer, self)
+    }
+
+    fn write_ndjson(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.apps
+            .values()
+            .try_for_each(|app| write_line(writer, app))
+    }
+
+    fn text_keyvalues(&self) -> Cow<'_, KeyValues> {
+        Cow::Owned(
+            self.apps
+                .values()
+                .map(|app| {
+                    (
+                        app.id.to_string().into(),
+                        Value::KeyValueType(app.key_values.clone()),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl DumpData for PackageInfo {
+    fn write_vdf(&self, mut writer: &mut dyn Write) -> io::Result<()> {
+        vdfr::writer::write_package_info(&mut writer, self)
+    }
+
+    fn write_ndjson(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.packages
+            .values()
+            .try_for_each(|package| write_line(writer, package))
+    }
+
+    fn text_keyvalues(&self) -> Cow<'_, KeyValues> {
+        Cow::Owned(
+            self.packages
+                .values()
+                .map(|package| {
+                    (
+                        package.id.to_string().into(),
+                        Value::KeyValueType(package.key_values.clone()),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl DumpData for KeyValues {
+    fn write_vdf(&self, mut writer: &mut dyn Write) -> io::Result<()> {
+        vdfr::writer::write_keyvalues(&mut writer, self)
+    }
+
+    /// Each key is written as an object with only that key.
+    fn write_ndjson(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.iter()
+            .try_for_each(|(key, value)| write_line(writer, &BTreeMap::from([(key, value)])))
+    }
+
+    fn text_keyvalues(&self) -> Cow<'_, KeyValues> {
+        Cow::Borrowed(self)
+    }
+}
+
+/// An app dumped into its own file, serialized as the app but written to VDF as an app
+/// info file with only that app in it.
+pub struct SingleApp<'a> {
+    pub app_info: &'a AppInfo,
+    pub app: &'a App,
+}
+
+impl serde::Serialize for SingleApp<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.app.serialize(serializer)
+    }
+}
+
+impl DumpData for SingleApp<'_> {
+    fn write_vdf(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let single = AppInfo {
+            version: self.app_info.version,
+            universe: self.app_info.universe,
+            apps: [(self.app.id, self.app.clone())].into_iter().collect(),
+            trailing_bytes: 0,
+        };
+        single.write_vdf(writer)
+    }
+
+    fn write_ndjson(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_line(writer, self.app)
+    }
+
+    fn text_keyvalues(&self) -> Cow<'_, KeyValues> {
+        Cow::Borrowed(&self.app.key_values)
+    }
+}
+
+/// Write the value as a line of compact JSON.
+pub fn write_line<T: serde::Serialize + ?Sized>(
+    writer: &mut dyn Write,
+    value: &T,
+) -> io::Result<()> {
+    vdfr::serde_json::to_writer(&mut *writer, value)?;
+    writeln!(writer)
+}
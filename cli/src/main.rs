@@ -1,224 +1,1728 @@
-use std::fs;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    io::{IsTerminal, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use clap::Parser;
-use rand::Rng;
-use vdfr::KeyValueOptions;
+#[cfg(feature = "tui")]
+mod browse;
+mod config;
+mod debug;
+mod filter;
+mod format;
+mod serve;
+
+use anstyle::{AnsiColor, Style};
+use clap::{CommandFactory, Parser};
+use filter::Expr;
+use format::{DumpData, DumpFormat, SingleApp};
+use tracing::{error, info, warn};
+use vdfr::{
+    backend::{LegacyParser, NomParser, VdfParser},
+    compression::{CompressedWriter, Compression},
+    index::{sidecar_path, AppInfoIndex, IndexedAppInfo},
+    keyvalues_semantic_eq,
+    patch::Patch,
+    regex::Regex,
+    search::Search,
+    steam::SteamDir,
+    value_semantic_eq, KeyValues, Map, SemanticEqOptions, Value,
+};
 
 #[derive(Debug, Parser)]
+#[clap(version, about)]
 struct Args {
+    /// Config file with the defaults of the commands, instead of ~/.config/vdfr/config.toml
+    #[clap(long, global = true)]
+    config: Option<std::path::PathBuf>,
+    /// Log more, `-v` for debug logs and `-vv` for every log
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Log less, `-q` for warnings and errors only and `-qq` for errors only
+    #[clap(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+    /// Format of the logs written to stderr
+    #[clap(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
     #[clap(subcommand)]
     subcommand: Subcommand,
 }
 
+/// Where the parsed data is dumped back to, as JSON and VDF.
+#[derive(Debug, clap::Args)]
+struct DumpArgs {
+    /// Dump back the parsed data into JSON and VDF files next to the input, prefixed with the kind
+    #[clap(short, long)]
+    redump: bool,
+    /// Dump into this path instead, with its extension replaced when dumping both formats
+    #[clap(short, long)]
+    output: Option<std::path::PathBuf>,
+    /// Only dump in this format, instead of JSON and binary VDF
+    #[clap(long, value_enum, conflicts_with_all = ["json_only", "vdf_only"])]
+    format: Option<DumpFormat>,
+    /// Only dump the JSON, like `--format json-pretty`
+    #[clap(long, conflicts_with = "vdf_only")]
+    json_only: bool,
+    /// Only dump the VDF, like `--format vdf-bin`
+    #[clap(long)]
+    vdf_only: bool,
+    /// Dump into stdout instead, the JSON unless another format is given
+    #[clap(long, conflicts_with = "output")]
+    stdout: bool,
+    /// Compress the dumps, with the extension appended unless --output is the path of a single dump
+    #[clap(long, value_enum)]
+    compress: Option<DumpCompression>,
+    /// Only dump the outputs of this jq-like expression, like `.apps[] | .id`, in JSON or another serde format
+    #[clap(long, value_parser = Expr::parse, conflicts_with = "vdf_only")]
+    filter: Option<Expr>,
+    /// Amount of files (or apps with --split-dir) processed at the same time
+    #[clap(short, long, default_value_t = 1)]
+    jobs: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per line, with the fields of every event
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum DumpCompression {
+    Gzip,
+    Zstd,
+}
+
+impl From<DumpCompression> for Compression {
+    fn from(compression: DumpCompression) -> Self {
+        match compression {
+            DumpCompression::Gzip => Compression::Gzip,
+            DumpCompression::Zstd => Compression::Zstd,
+        }
+    }
+}
+
+impl DumpArgs {
+    /// Any of the options asks for a dump.
+    fn enabled(&self) -> bool {
+        self.redump
+            || self.output.is_some()
+            || self.format.is_some()
+            || self.json_only
+            || self.vdf_only
+            || self.stdout
+            || self.compress.is_some()
+            || self.filter.is_some()
+    }
+
+    /// Use the format of the config when no format flag is given, and exit when the
+    /// filter can't be written in the format.
+    ///
+    /// Only done when something is dumped, since the format flags ask for a dump.
+    fn apply_config(&mut self) {
+        if self.format.is_none() && !self.json_only && !self.vdf_only {
+            // Filters only apply to the serde formats
+            self.format = config::get()
+                .format
+                .filter(|format| self.filter.is_none() || format.is_serde());
+        }
+        if let Some(format) = self.format.filter(|format| !format.is_serde()) {
+            if self.filter.is_some() {
+                error!("--filter can't be written as {}", format.name());
+                std::process::exit(2);
+            }
+        }
+    }
+
+    fn compression(&self) -> Compression {
+        self.compress.map_or(Compression::None, Compression::from)
+    }
+
+    /// The dump can be written while parsing, which needs the nom parser and no filter.
+    fn can_stream(&self, legacy: bool) -> bool {
+        self.filter.is_none() && !uses_legacy(legacy)
+    }
+
+    /// Formats to dump in, JSON and binary VDF unless one is chosen.
+    fn formats(&self) -> Vec<DumpFormat> {
+        match self.format {
+            Some(format) => vec![format],
+            None if self.vdf_only => vec![DumpFormat::VdfBin],
+            None if self.json_only || self.stdout || self.filter.is_some() => {
+                vec![DumpFormat::JsonPretty]
+            }
+            None => vec![DumpFormat::JsonPretty, DumpFormat::VdfBin],
+        }
+    }
+
+    /// Write the data in the format, or the outputs of the filter one after another.
+    fn write_format<D: DumpData + ?Sized>(
+        &self,
+        writer: &mut dyn Write,
+        format: DumpFormat,
+        data: &D,
+    ) -> std::io::Result<()> {
+        let Some(filter) = &self.filter else {
+            return format.write(writer, data);
+        };
+        let value = vdfr::serde_json::to_value(data)?;
+        let outputs = filter.eval(&value).map_err(std::io::Error::other)?;
+        format.write_all(writer, &outputs)
+    }
+
+    /// Path of a dump, `None` for stdout.
+    fn path(
+        &self,
+        file: &std::path::Path,
+        prefix: &str,
+        extension: &str,
+    ) -> Option<std::path::PathBuf> {
+        if self.stdout {
+            return None;
+        }
+        let path = match &self.output {
+            Some(output) if self.formats().len() > 1 => output.with_extension(extension),
+            Some(output) => return Some(output.clone()),
+            None => {
+                let filename = file.file_stem().unwrap().to_str().unwrap();
+                let suffix = if extension == "vdf" { "_redump" } else { "" };
+                file.parent()
+                    .unwrap()
+                    .join(format!("{}_{}{}.{}", prefix, filename, suffix, extension))
+            }
+        };
+        Some(self.compressed_path(path))
+    }
+
+    /// Append the extension of the compression to the path.
+    fn compressed_path(&self, path: std::path::PathBuf) -> std::path::PathBuf {
+        match self.compression().extension() {
+            Some(compressed) => {
+                let mut path = path.into_os_string();
+                path.push(".");
+                path.push(compressed);
+                path.into()
+            }
+            None => path,
+        }
+    }
+
+    /// Dump the data in every format, logging the time each took.
+    fn dump<D: DumpData + ?Sized>(
+        &self,
+        file: &std::path::Path,
+        prefix: &str,
+        data: &D,
+    ) -> std::io::Result<()> {
+        for format in self.formats() {
+            let time_it = std::time::Instant::now();
+            self.write(file, prefix, format.extension(), |writer| {
+                self.write_format(writer, format, data)
+            })?;
+            info!(
+                format = format.name(),
+                elapsed_ms = elapsed_ms(time_it),
+                "Dumped"
+            );
+        }
+        Ok(())
+    }
+
+    fn write<F>(
+        &self,
+        file: &std::path::Path,
+        prefix: &str,
+        extension: &str,
+        write: F,
+    ) -> std::io::Result<()>
+    where
+        F: FnOnce(&mut dyn std::io::Write) -> std::io::Result<()>,
+    {
+        self.write_to(self.path(file, prefix, extension), write)
+    }
+
+    /// Write into the file at the path, or into stdout for `None`.
+    fn write_to<F>(&self, path: Option<std::path::PathBuf>, write: F) -> std::io::Result<()>
+    where
+        F: FnOnce(&mut dyn std::io::Write) -> std::io::Result<()>,
+    {
+        let output: Box<dyn std::io::Write> = match path {
+            Some(path) => Box::new(fs::File::create(&path)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        let mut writer = CompressedWriter::new(output, self.compression())?;
+        write(&mut writer)?;
+        writer.finish()?.flush()
+    }
+}
+
+/// Log to stderr at the level of the verbosity flags, keeping stdout for the output.
+fn init_logging(verbose: u8, quiet: u8, format: LogFormat) {
+    let level = match (verbose, quiet) {
+        (0, 0) => tracing::Level::INFO,
+        (1, _) => tracing::Level::DEBUG,
+        (_, 0) => tracing::Level::TRACE,
+        (_, 1) => tracing::Level::WARN,
+        _ => tracing::Level::ERROR,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder
+            .without_time()
+            .with_target(false)
+            .with_ansi(std::io::stderr().is_terminal())
+            .init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// Milliseconds since the instant, for the timings in the logs.
+fn elapsed_ms(since: std::time::Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
 #[derive(Debug, Parser)]
 enum Subcommand {
     /// Parse app info file
     #[clap(name = "app")]
     AppInfo {
-        /// Path to the file
-        file: std::path::PathBuf,
+        /// Paths or glob patterns of the files, defaults to the appinfo.vdf of the local Steam installation
+        files: Vec<String>,
         /// Use legacy parser
         #[clap(short, long)]
         legacy: bool,
-        /// Dump back the parsed data into JSON file, prefixed with app_
-        #[clap(short, long)]
-        redump: bool,
+        /// Dump every app into its own <appid>.json in this directory, or with the extension of --format
+        #[clap(long, conflicts_with_all = ["output", "stdout"])]
+        split_dir: Option<std::path::PathBuf>,
+        /// Only keep these comma-separated app IDs, like `570,440`, to dump a smaller file
+        #[clap(long, value_delimiter = ',')]
+        only: Vec<u32>,
+        /// Remove the values at this path from every app before dumping, like `depots/*/encryptedmanifests`
+        #[clap(long)]
+        strip: Vec<String>,
+        #[clap(flatten)]
+        dump: DumpArgs,
     },
     /// Parse package info file
     #[clap(name = "pkg")]
     PackageInfo {
-        /// Path to the file
-        file: std::path::PathBuf,
+        /// Paths or glob patterns of the files, defaults to the packageinfo.vdf of the local Steam installation
+        files: Vec<String>,
         /// Use legacy parser
         #[clap(short, long)]
         legacy: bool,
-        /// Dump back the parsed data into JSON file, prefixed with pkg_
-        #[clap(short, long)]
-        redump: bool,
+        #[clap(flatten)]
+        dump: DumpArgs,
     },
     /// Parse key-values file
     #[clap(name = "kv")]
     KV {
-        /// Path to the file
-        file: std::path::PathBuf,
+        /// Paths or glob patterns of the files
+        #[clap(required = true)]
+        files: Vec<String>,
         /// Use legacy parser
         #[clap(short, long)]
         legacy: bool,
-        /// Dump back the parsed data into JSON file, prefixed with kv_
+        #[clap(flatten)]
+        dump: DumpArgs,
+    },
+    /// Benchmark the parser backends against a file
+    #[clap(name = "bench")]
+    Bench {
+        /// Path to the file
+        file: std::path::PathBuf,
+        /// Amount of times to parse the file with each backend
+        #[clap(short, long, default_value_t = 10)]
+        iterations: usize,
+    },
+    /// List apps that changed since a change number or an older app info file
+    #[clap(name = "changed")]
+    Changed {
+        /// Path to the app info file
+        file: std::path::PathBuf,
+        /// List apps with a change number above this one
+        #[clap(long, conflicts_with = "since", required_unless_present = "since")]
+        since_change: Option<u32>,
+        /// List apps that are new or have a higher change number than in this older app info file
+        #[clap(long)]
+        since: Option<std::path::PathBuf>,
+    },
+    /// Print how many bytes the apps of an app info file take
+    #[clap(name = "stats")]
+    Stats {
+        /// Path to the file, defaults to the appinfo.vdf of the local Steam installation
+        file: Option<std::path::PathBuf>,
+        /// Amount of the largest apps to list
+        #[clap(long, default_value_t = 20)]
+        top_size: usize,
+        /// Also chart the apps by release year, change number and key-values depth, and the values by type
+        #[clap(long)]
+        histograms: bool,
+        /// Print the histograms as JSON instead, without the rest of the stats
+        #[clap(long, requires = "histograms")]
+        json: bool,
+        /// Amount of change number buckets
+        #[clap(long, default_value_t = 10)]
+        buckets: usize,
+    },
+    /// Check the apps of an app info file for inconsistencies, like checksum or size mismatches
+    #[clap(name = "lint")]
+    Lint {
+        /// Path to the file, defaults to the appinfo.vdf of the local Steam installation
+        file: Option<std::path::PathBuf>,
+    },
+    /// Check whether the app and package info caches of the local Steam installation are healthy
+    #[clap(name = "doctor")]
+    Doctor {
+        /// Path to the Steam installation, instead of locating it
+        #[clap(long)]
+        steam_dir: Option<std::path::PathBuf>,
+    },
+    /// Parse a file, write it back and check that it parses to the same data
+    #[clap(name = "verify")]
+    Verify {
+        /// Path to the file
+        file: std::path::PathBuf,
+        /// Also require the written file to be identical to the original, byte for byte
+        #[clap(long)]
+        exact: bool,
+    },
+    /// Print the keys of a file as a tree, with their types and values
+    #[clap(name = "tree")]
+    Tree {
+        /// Path to the file
+        file: std::path::PathBuf,
+        /// Only print the app with this ID, for app info files
+        #[clap(long)]
+        appid: Option<u32>,
+        /// Maximum depth of the sections to expand
+        #[clap(short, long)]
+        depth: Option<usize>,
+    },
+    /// Watch an app info file and print the apps that change when Steam updates it
+    #[clap(name = "watch")]
+    Watch {
+        /// Path to the file, defaults to the appinfo.vdf of the local Steam installation
+        file: Option<std::path::PathBuf>,
+        /// Command to run after a change, the changed app IDs are passed in VDF_CHANGED_APPS
+        #[clap(long)]
+        on_change: Option<String>,
+    },
+    /// Print the apps added, removed or changed between two app info files
+    #[clap(name = "diff")]
+    Diff {
+        /// Path to the older file
+        old: std::path::PathBuf,
+        /// Path to the newer file
+        new: std::path::PathBuf,
+    },
+    /// Write a patch of the apps that changed between two app info files, or apply one
+    #[clap(name = "patch")]
+    Patch {
+        /// Path to the older file the patch applies to
+        old: std::path::PathBuf,
+        /// Path to the newer file to create the patch from
+        #[clap(required_unless_present = "apply")]
+        new: Option<std::path::PathBuf>,
+        /// Apply this patch to the older file instead of creating one
+        #[clap(long, conflicts_with = "new")]
+        apply: Option<std::path::PathBuf>,
+        /// Path to write the patch or the patched file to
         #[clap(short, long)]
-        redump: bool,
+        output: std::path::PathBuf,
+    },
+    /// Print the paths of the values whose key matches a regex, and whose value too if given
+    #[clap(name = "search")]
+    Search {
+        /// Path to the file
+        file: std::path::PathBuf,
+        /// Regex the key has to match, like `^url$`
+        key: Regex,
+        /// Regex the string or number has to match, like `cdn\.example\.com`
+        #[clap(long)]
+        value: Option<Regex>,
+        /// Print the matches in this format instead, keyed by ID and path
+        #[clap(long, value_enum)]
+        format: Option<DumpFormat>,
+    },
+    /// Print the offset, type byte, key and encoded length of every node as it's read
+    #[clap(name = "debug")]
+    Debug {
+        /// Path to the file
+        file: std::path::PathBuf,
+    },
+    /// Serve the apps of an app info file as JSON over HTTP
+    #[clap(name = "serve")]
+    Serve {
+        /// Path to the file, defaults to the appinfo.vdf of the local Steam installation
+        file: Option<std::path::PathBuf>,
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
+    /// Browse the apps of an app info file interactively
+    #[cfg(feature = "tui")]
+    #[clap(name = "browse")]
+    Browse {
+        /// Path to the file, defaults to the appinfo.vdf of the local Steam installation
+        file: Option<std::path::PathBuf>,
+    },
+    /// Print the completion script of a shell, like `vdf completions bash > /etc/bash_completion.d/vdf`
+    #[clap(name = "completions")]
+    Completions {
+        /// Shell to complete in
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Write the man pages of the command and every subcommand, like `vdf.1` and `vdf-app.1`
+    #[clap(name = "mangen", hide = true)]
+    Mangen {
+        /// Directory to write the pages into
+        #[clap(default_value = ".")]
+        dir: std::path::PathBuf,
     },
 }
 
-fn get_random_num(total: usize) -> usize {
-    let mut rng = rand::rng();
-    rng.random_range(0..total)
+/// Longest value printed in the tree, longer values are truncated.
+const TREE_VALUE_WIDTH: usize = 60;
+
+const TREE_KEY: Style = Style::new().bold();
+const TREE_TYPE: Style = AnsiColor::BrightBlack.on_default();
+const TREE_STRING: Style = AnsiColor::Green.on_default();
+const TREE_NUMBER: Style = AnsiColor::Cyan.on_default();
+
+/// The legacy parser is used, from `--legacy` or the config.
+fn uses_legacy(legacy: bool) -> bool {
+    legacy || config::get().backend == config::Backend::Legacy
 }
 
-fn work_app_info(file: &std::path::PathBuf, legacy: bool, redump: bool) {
-    let data = if legacy {
-        let file = fs::File::open(file).unwrap();
-        let mut reader = std::io::BufReader::new(file);
-        let time_it = std::time::Instant::now();
-        let parsed = vdfr::legacy_parser::parse_app_info(&mut reader).unwrap();
-
-        println!("Version: {}", parsed.version);
-        println!("Universe: {}", parsed.universe);
-        println!("Total apps: {}", parsed.apps.len());
-        println!("Time taken to parse: {:?}", time_it.elapsed());
-        let total = parsed.apps.values().count();
-        let random_app_pos = get_random_num(total);
-        let random_app = parsed.apps.values().nth(random_app_pos).unwrap();
-        println!("Random app: {:?}", random_app);
-        parsed
+fn get_parser(legacy: bool) -> Box<dyn VdfParser> {
+    if uses_legacy(legacy) {
+        Box::new(LegacyParser::default())
     } else {
-        let data = fs::read(file).unwrap();
+        Box::new(NomParser::default())
+    }
+}
 
-        let time_it = std::time::Instant::now();
-        let parsed = vdfr::parser::parse_app_info(&data).unwrap();
-        println!("Version: {}", parsed.version);
-        println!("Universe: {}", parsed.universe);
-        println!("Total apps: {}", parsed.apps.len());
-        println!("Time taken to parse: {:?}", time_it.elapsed());
-        let total = parsed.apps.values().count();
-        let random_app_pos = get_random_num(total);
-        let random_app = parsed.apps.values().nth(random_app_pos).unwrap();
-        println!("Random app: {:?}", random_app);
-        parsed
-    };
-
-    if redump {
-        let filename = file.file_stem().unwrap().to_str().unwrap();
-        let output_path = file
-            .parent()
-            .unwrap()
-            .join(format!("app_{}.json", filename));
-        let time_it = std::time::Instant::now();
-        let output_file = fs::File::create(&output_path).unwrap();
-        vdfr::serde_json::to_writer_pretty(output_file, &data).unwrap();
-        println!("Time taken to dump JSON: {:?}", time_it.elapsed());
-
-        let output_path_redump = file
-            .parent()
-            .unwrap()
-            .join(format!("app_{}_redump.vdf", filename));
-        let time_it = std::time::Instant::now();
-        let mut output_file_redump = fs::File::create(&output_path_redump).unwrap();
-        vdfr::writer::write_app_info(&mut output_file_redump, &data).unwrap();
-        println!("Time taken to redump VDF: {:?}", time_it.elapsed());
+/// Locate the local Steam installation, when no file is given.
+fn locate_steam() -> SteamDir {
+    if let Some(path) = &config::get().steam_dir {
+        return SteamDir { path: path.clone() };
+    }
+    SteamDir::locate().expect("Steam installation not found, pass the file path instead")
+}
+
+/// Expand the glob patterns of the inputs, other paths are kept as they are.
+///
+/// Exits when a pattern is invalid or matches no file.
+fn expand_inputs(patterns: &[String]) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            files.push(std::path::PathBuf::from(pattern));
+            continue;
+        }
+        let matches = match glob::glob(pattern) {
+            Ok(paths) => paths.filter_map(Result::ok).collect::<Vec<_>>(),
+            Err(e) => {
+                error!("Invalid pattern {}: {}", pattern, e);
+                std::process::exit(2);
+            }
+        };
+        if matches.is_empty() {
+            error!("No file matches {}", pattern);
+            std::process::exit(2);
+        }
+        files.extend(matches);
+    }
+    files
+}
+
+/// Process every file on `--jobs` threads, reporting the status of each and exiting with
+/// an error if any failed.
+fn run_batch<F>(files: &[std::path::PathBuf], dump: &DumpArgs, work: F)
+where
+    F: Fn(&std::path::Path) -> Result<(), Box<dyn Error>> + Sync,
+{
+    if files.len() > 1 && dump.output.is_some() {
+        error!("--output needs a single input file");
+        std::process::exit(2);
+    }
+
+    let batch = files.len() > 1;
+    let next = AtomicUsize::new(0);
+    let failures = AtomicUsize::new(0);
+    let worker = || {
+        while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+            if batch {
+                info!(file = %file.display(), "Processing");
+            }
+            match work(file) {
+                Ok(()) if batch => info!(file = %file.display(), "Processed"),
+                Ok(()) => {}
+                Err(e) => {
+                    error!(file = %file.display(), "Failed: {}", e);
+                    failures.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..dump.jobs.clamp(1, files.len().max(1)) {
+            scope.spawn(worker);
+        }
+    });
+
+    let failed = failures.into_inner();
+    if batch {
+        info!(
+            files = files.len(),
+            succeeded = files.len() - failed,
+            failed,
+            "Processed all files"
+        );
+    }
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn work_app_info(
+    file: &std::path::Path,
+    legacy: bool,
+    split_dir: Option<&std::path::Path>,
+    only: &[u32],
+    strip: &[String],
+    dump: &DumpArgs,
+) -> Result<(), Box<dyn Error>> {
+    let parser = get_parser(legacy);
+    let contents = vdfr::compression::read_file(file)?;
+    if split_dir.is_none() && dump.can_stream(legacy) {
+        match dump.format {
+            Some(DumpFormat::Ndjson) => {
+                return stream_app_info(file, &contents, only, strip, dump);
+            }
+            // Compact JSON is the same as the parsed app info, unless apps or values are left out
+            Some(DumpFormat::Json) if only.is_empty() && strip.is_empty() => {
+                let time_it = std::time::Instant::now();
+                dump.write(file, "app", DumpFormat::Json.extension(), |mut writer| {
+                    vdfr::export::stream_json(&mut writer, &contents).map_err(std::io::Error::other)
+                })?;
+                info!(elapsed_ms = elapsed_ms(time_it), "Streamed app info");
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    let time_it = std::time::Instant::now();
+    let mut data = parser.parse_app_info(&contents)?;
+    info!(
+        version = %data.version,
+        universe = data.universe,
+        apps = data.apps.len(),
+        elapsed_ms = elapsed_ms(time_it),
+        "Parsed app info"
+    );
+
+    if !only.is_empty() {
+        if let Some(missing) = only.iter().find(|id| !data.apps.contains_key(*id)) {
+            return Err(format!("app {} not found", missing).into());
+        }
+        data.apps.retain(|id, _| only.contains(id));
     }
+    if !strip.is_empty() {
+        let paths: Vec<&str> = strip.iter().map(String::as_str).collect();
+        let removed = data.strip_paths(&paths);
+        info!(removed, "Stripped values");
+    }
+
+    if let Some(split_dir) = split_dir {
+        split_apps(split_dir, &data, dump)?;
+    } else if dump.enabled() {
+        dump.dump(file, "app", &data)?;
+    }
+    Ok(())
+}
+
+/// Write the apps as NDJSON while parsing them, one at a time instead of the whole app info.
+fn stream_app_info(
+    file: &std::path::Path,
+    contents: &[u8],
+    only: &[u32],
+    strip: &[String],
+    dump: &DumpArgs,
+) -> Result<(), Box<dyn Error>> {
+    let time_it = std::time::Instant::now();
+    let apps = vdfr::parser::iter_app_info(contents)?;
+    let (version, universe) = (apps.version(), apps.universe());
+    let paths: Vec<&str> = strip.iter().map(String::as_str).collect();
+    let mut written = Vec::new();
+    let mut removed = 0;
+
+    dump.write(file, "app", DumpFormat::Ndjson.extension(), |writer| {
+        for app in apps {
+            let mut app = app.map_err(std::io::Error::other)?;
+            if !only.is_empty() && !only.contains(&app.id) {
+                continue;
+            }
+            removed += app.strip_paths(&paths);
+            format::write_line(writer, &app)?;
+            written.push(app.id);
+        }
+        Ok(())
+    })?;
+
+    if let Some(missing) = only.iter().find(|id| !written.contains(id)) {
+        return Err(format!("app {} not found", missing).into());
+    }
+    if !strip.is_empty() {
+        info!(removed, "Stripped values");
+    }
+    info!(
+        version = %version,
+        universe,
+        apps = written.len(),
+        elapsed_ms = elapsed_ms(time_it),
+        "Streamed app info"
+    );
+    Ok(())
 }
 
-fn work_pkg_info(file: &std::path::PathBuf, legacy: bool, redump: bool) {
-    let data = if legacy {
-        let file = fs::File::open(file).unwrap();
-        let mut reader = std::io::BufReader::new(file);
+/// Write every app into its own file in the directory, on `--jobs` threads, in the first
+/// format of the dump.
+///
+/// The VDF of an app is an app info file with only that app in it.
+fn split_apps(
+    dir: &std::path::Path,
+    data: &vdfr::AppInfo,
+    dump: &DumpArgs,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let apps: Vec<&vdfr::App> = data.apps.values().collect();
+    let format = dump.formats()[0];
+
+    let time_it = std::time::Instant::now();
+    let next = AtomicUsize::new(0);
+    let write_apps = || -> std::io::Result<()> {
+        while let Some(app) = apps.get(next.fetch_add(1, Ordering::Relaxed)) {
+            let path = format!("{}.{}", app.id, format.extension());
+            let path = dump.compressed_path(dir.join(path));
+            let single = SingleApp {
+                app_info: data,
+                app,
+            };
+            dump.write_to(Some(path), |writer| {
+                dump.write_format(writer, format, &single)
+            })?;
+        }
+        Ok(())
+    };
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..dump.jobs.clamp(1, apps.len().max(1)))
+            .map(|_| scope.spawn(write_apps))
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().unwrap())
+    })?;
+
+    info!(
+        apps = apps.len(),
+        dir = %dir.display(),
+        elapsed_ms = elapsed_ms(time_it),
+        "Split apps"
+    );
+    Ok(())
+}
+
+fn work_pkg_info(
+    file: &std::path::Path,
+    legacy: bool,
+    dump: &DumpArgs,
+) -> Result<(), Box<dyn Error>> {
+    let parser = get_parser(legacy);
+    let contents = vdfr::compression::read_file(file)?;
+
+    let time_it = std::time::Instant::now();
+    if dump.can_stream(legacy) && dump.format == Some(DumpFormat::Ndjson) {
+        let packages = vdfr::parser::iter_package_info(&contents)?;
+        let version = packages.version();
+        let mut written = 0usize;
+        dump.write(file, "pkg", DumpFormat::Ndjson.extension(), |writer| {
+            for package in packages {
+                format::write_line(writer, &package.map_err(std::io::Error::other)?)?;
+                written += 1;
+            }
+            Ok(())
+        })?;
+        info!(
+            version = %version,
+            packages = written,
+            elapsed_ms = elapsed_ms(time_it),
+            "Streamed package info"
+        );
+        return Ok(());
+    }
+    let data = parser.parse_package_info(&contents)?;
+    info!(
+        version = %data.version,
+        packages = data.packages.len(),
+        elapsed_ms = elapsed_ms(time_it),
+        "Parsed package info"
+    );
+
+    if dump.enabled() {
+        dump.dump(file, "pkg", &data)?;
+    }
+    Ok(())
+}
+
+fn work_kv(file: &std::path::Path, legacy: bool, dump: &DumpArgs) -> Result<(), Box<dyn Error>> {
+    let parser = get_parser(legacy);
+    let contents = vdfr::compression::read_file(file)?;
+
+    let time_it = std::time::Instant::now();
+    let data = parser.parse_keyvalues(&contents)?;
+    info!(
+        key_values = data.len(),
+        elapsed_ms = elapsed_ms(time_it),
+        "Parsed key-values"
+    );
+
+    if dump.enabled() {
+        dump.dump(file, "kv", &data)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    AppInfo,
+    PackageInfo,
+    KeyValues,
+}
+
+/// Guess the kind of file from the magic number.
+fn detect_kind(data: &[u8]) -> FileKind {
+    let Some(magic) = data.get(..4) else {
+        return FileKind::KeyValues;
+    };
+    let magic = u32::from_le_bytes(magic.try_into().unwrap());
+
+    let app_version: Result<vdfr::AppInfoVersion, _> = magic.try_into();
+    let pkg_version: Result<vdfr::PkgInfoVersion, _> = magic.try_into();
+    match (app_version, pkg_version) {
+        (Ok(_), _) => FileKind::AppInfo,
+        (_, Ok(_)) => FileKind::PackageInfo,
+        _ => FileKind::KeyValues,
+    }
+}
+
+/// Reset the peak resident set size of the process, only supported on Linux.
+fn reset_peak_rss() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+/// Peak resident set size of the process in KiB, only supported on Linux.
+fn peak_rss_kib() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+fn bench_parser(parser: &dyn VdfParser, kind: FileKind, contents: &[u8], iterations: usize) {
+    reset_peak_rss();
+
+    let mut timings = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
         let time_it = std::time::Instant::now();
-        let parsed = vdfr::legacy_parser::parse_package_info(&mut reader).unwrap();
-
-        println!("Version: {}", parsed.version);
-        println!("Total packages: {}", parsed.packages.len());
-        println!("Time taken to parse: {:?}", time_it.elapsed());
-        let total = parsed.packages.values().count();
-        let random_pkg_pos = get_random_num(total);
-        let random_pkg = parsed.packages.values().nth(random_pkg_pos).unwrap();
-        println!("Random package: {:?}", random_pkg);
-        parsed
+        let result = match kind {
+            FileKind::AppInfo => parser.parse_app_info(contents).map(|_| ()),
+            FileKind::PackageInfo => parser.parse_package_info(contents).map(|_| ()),
+            FileKind::KeyValues => parser.parse_keyvalues(contents).map(|_| ()),
+        };
+        timings.push(time_it.elapsed());
+
+        if let Err(e) = result {
+            error!("Backend {} failed to parse: {}", parser.name(), e);
+            return;
+        }
+    }
+
+    let min = timings.iter().min().unwrap();
+    let max = timings.iter().max().unwrap();
+    let avg = timings.iter().sum::<std::time::Duration>() / iterations as u32;
+
+    println!("Backend: {}", parser.name());
+    println!("  min: {:?}, avg: {:?}, max: {:?}", min, avg, max);
+    match peak_rss_kib() {
+        Some(rss) => println!("  peak RSS: {} KiB", rss),
+        None => println!("  peak RSS: unavailable"),
+    }
+}
+
+fn work_bench(file: &std::path::PathBuf, iterations: usize) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let kind = detect_kind(&contents);
+    let iterations = iterations.max(1);
+
+    println!("File: {} ({:?})", file.display(), kind);
+    println!("Iterations: {}", iterations);
+    bench_parser(&NomParser::default(), kind, &contents, iterations);
+    bench_parser(&LegacyParser::default(), kind, &contents, iterations);
+}
+
+/// Load the app info index, using the sidecar index file when it's still valid.
+fn load_index(file: &std::path::Path, contents: &[u8]) -> AppInfoIndex {
+    let sidecar = fs::File::open(sidecar_path(file))
+        .ok()
+        .and_then(|f| AppInfoIndex::read(&mut std::io::BufReader::new(f)).ok())
+        .filter(|index| index.data_len == contents.len() as u64);
+
+    match sidecar {
+        Some(index) => index,
+        None => AppInfoIndex::build(contents).unwrap(),
+    }
+}
+
+fn work_changed(
+    file: &std::path::PathBuf,
+    since_change: Option<u32>,
+    since: Option<&std::path::PathBuf>,
+) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let index = load_index(file, &contents);
+
+    let old_index = since.map(|old_file| {
+        let old_contents = vdfr::compression::read_file(old_file).unwrap();
+        load_index(old_file, &old_contents)
+    });
+
+    for (app_id, entry) in &index.entries {
+        let changed = match &old_index {
+            Some(old_index) => old_index
+                .entries
+                .get(app_id)
+                .is_none_or(|old| entry.change_number > old.change_number),
+            None => entry.change_number > since_change.unwrap_or_default(),
+        };
+        if changed {
+            println!("{}\t{}", app_id, entry.change_number);
+        }
+    }
+}
+
+/// How `stats --histograms` prints them.
+struct HistogramOutput {
+    json: bool,
+    buckets: usize,
+}
+
+fn work_stats(file: &std::path::Path, top_size: usize, histograms: Option<HistogramOutput>) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let data = vdfr::parser::parse_app_info(&contents).unwrap();
+    if let Some(HistogramOutput {
+        json: true,
+        buckets,
+    }) = histograms
+    {
+        let histograms = vdfr::analysis::histograms(&data, buckets);
+        println!(
+            "{}",
+            vdfr::serde_json::to_string_pretty(&histograms).unwrap()
+        );
+        return;
+    }
+    let report = data.size_report();
+    let total: usize = report.iter().map(|size| size.total()).sum();
+
+    println!("Version: {}", data.version);
+    println!("Total apps: {}", report.len());
+    println!("Total size of the apps: {} bytes", total);
+    println!(
+        "Approximate heap size once parsed: {} bytes",
+        data.approx_heap_size()
+    );
+    println!();
+    println!(
+        "{:>10}  {:>10}  {:>6}  {:>10}  Name",
+        "App ID", "Size", "Share", "Key-values"
+    );
+    for size in report.iter().take(top_size) {
+        let app = &data.apps[&size.id];
+        let name = app
+            .get(&["appinfo", "common", "name"])
+            .or_else(|| app.get(&["common", "name"]));
+        let name = match name {
+            Some(Value::StringType(name)) => &**name,
+            _ => "",
+        };
+        println!(
+            "{:>10}  {:>10}  {:>5.1}%  {:>10}  {}",
+            size.id,
+            size.total(),
+            size.total() as f64 * 100.0 / total.max(1) as f64,
+            size.key_values,
+            name
+        );
+    }
+
+    let Some(output) = histograms else {
+        return;
+    };
+    let histograms = vdfr::analysis::histograms(&data, output.buckets);
+    let mut years: Vec<(String, usize)> = histograms
+        .release_years
+        .iter()
+        .map(|(year, count)| (year.to_string(), *count))
+        .collect();
+    years.push(("undated".to_string(), histograms.undated));
+    print_chart("Apps by release year", &years);
+    let change_numbers: Vec<(String, usize)> = histograms
+        .change_numbers
+        .iter()
+        .map(|bucket| (format!("{}-{}", bucket.start, bucket.end), bucket.count))
+        .collect();
+    print_chart("Apps by change number", &change_numbers);
+    let depths: Vec<(String, usize)> = histograms
+        .depths
+        .iter()
+        .map(|(depth, count)| (depth.to_string(), *count))
+        .collect();
+    print_chart("Apps by key-values depth", &depths);
+    let types: Vec<(String, usize)> = histograms
+        .value_types
+        .iter()
+        .map(|(kind, count)| (kind.to_string(), *count))
+        .collect();
+    print_chart("Values by type", &types);
+}
+
+/// Print a horizontal bar chart, the bars scaled to the largest count.
+fn print_chart(title: &str, rows: &[(String, usize)]) {
+    const WIDTH: usize = 40;
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let largest = rows.iter().map(|(_, count)| *count).max().unwrap_or(0);
+
+    println!();
+    println!("{}", title);
+    for (label, count) in rows {
+        let bar = (count * WIDTH).div_ceil(largest.max(1));
+        println!(
+            "{:>label_width$}  {:<WIDTH$}  {}",
+            label,
+            "█".repeat(bar),
+            count
+        );
+    }
+}
+
+fn work_lint(file: &std::path::Path) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let findings = vdfr::lint::check_data(&contents).unwrap();
+    for finding in &findings {
+        let path: Vec<&str> = finding.path.iter().map(|key| &**key).collect();
+        println!(
+            "{}\t{}\t{}\t{}",
+            finding.app_id,
+            finding.rule,
+            path.join("/"),
+            finding.message
+        );
+    }
+
+    if !findings.is_empty() {
+        eprintln!("{} findings", findings.len());
+        std::process::exit(1);
+    }
+}
+
+/// Keys of the entries that are missing on either side or differ.
+fn diff_entries<K, V, F>(original: &Map<K, V>, reparsed: &Map<K, V>, eq: F) -> Vec<String>
+where
+    K: Ord + std::hash::Hash + std::fmt::Display,
+    F: Fn(&V, &V) -> bool,
+{
+    let mut differing = Vec::new();
+    for (key, value) in original {
+        match reparsed.get(key) {
+            Some(other) if eq(value, other) => {}
+            _ => differing.push(key.to_string()),
+        }
+    }
+    for key in reparsed.keys() {
+        if !original.contains_key(key) {
+            differing.push(key.to_string());
+        }
+    }
+    differing
+}
+
+/// Parse the data, write it back and parse it again.
+///
+/// Returns the written data and the keys of the entries that changed in the round-trip.
+fn roundtrip(kind: FileKind, contents: &[u8]) -> Result<(Vec<u8>, Vec<String>), vdfr::VdfrError> {
+    let mut output = std::io::Cursor::new(Vec::new());
+    let differing = match kind {
+        FileKind::AppInfo => {
+            let original = vdfr::parser::parse_app_info(contents)?;
+            vdfr::writer::write_app_info(&mut output, &original)?;
+            let reparsed = vdfr::parser::parse_app_info(output.get_ref())?;
+
+            let mut differing = diff_entries(&original.apps, &reparsed.apps, |a, b| {
+                // The size and binary checksum depend on the encoding, --exact covers them
+                a.state == b.state
+                    && a.last_update == b.last_update
+                    && a.access_token == b.access_token
+                    && a.checksum_txt == b.checksum_txt
+                    && a.change_number == b.change_number
+                    && keyvalues_semantic_eq(&a.key_values, &b.key_values)
+            });
+            if original.version != reparsed.version || original.universe != reparsed.universe {
+                differing.insert(0, "header".to_string());
+            }
+            differing
+        }
+        FileKind::PackageInfo => {
+            let original = vdfr::parser::parse_package_info(contents)?;
+            vdfr::writer::write_package_info(&mut output, &original)?;
+            let reparsed = vdfr::parser::parse_package_info(output.get_ref())?;
+
+            let mut differing = diff_entries(&original.packages, &reparsed.packages, |a, b| {
+                a.checksum == b.checksum
+                    && a.change_number == b.change_number
+                    && a.token == b.token
+                    && keyvalues_semantic_eq(&a.key_values, &b.key_values)
+            });
+            if original.version != reparsed.version || original.universe != reparsed.universe {
+                differing.insert(0, "header".to_string());
+            }
+            differing
+        }
+        FileKind::KeyValues => {
+            let original = vdfr::parser::parse_keyvalues(contents)?;
+            vdfr::writer::write_keyvalues(&mut output, &original)?;
+            let reparsed = vdfr::parser::parse_keyvalues(output.get_ref())?;
+            diff_entries(&original, &reparsed, |a, b| {
+                value_semantic_eq(a, b, &SemanticEqOptions::default())
+            })
+        }
+    };
+    Ok((output.into_inner(), differing))
+}
+
+fn work_verify(file: &std::path::PathBuf, exact: bool) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let kind = detect_kind(&contents);
+
+    let (output, differing) = match roundtrip(kind, &contents) {
+        Ok(result) => result,
+        Err(e) => {
+            println!(
+                "{}: failed to round-trip ({:?}): {}",
+                file.display(),
+                kind,
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let mut ok = true;
+    if !differing.is_empty() {
+        ok = false;
+        println!(
+            "{}: {} entries differ after round-trip: {}",
+            file.display(),
+            differing.len(),
+            differing.join(", ")
+        );
+    }
+    if exact && output != contents {
+        ok = false;
+        let first_diff = output
+            .iter()
+            .zip(&contents)
+            .position(|(a, b)| a != b)
+            .unwrap_or(output.len().min(contents.len()));
+        println!(
+            "{}: written data differs from the original at byte {} ({} bytes written, {} original)",
+            file.display(),
+            first_diff,
+            output.len(),
+            contents.len()
+        );
+    }
+
+    if !ok {
+        std::process::exit(1);
+    }
+    println!("{}: OK ({:?})", file.display(), kind);
+}
+
+/// Problems of a Steam cache file, none when it's healthy.
+fn diagnose_cache(file: &std::path::Path, expected: FileKind) -> Vec<String> {
+    if !file.exists() {
+        return vec!["not found".to_string()];
+    }
+    let contents = match vdfr::compression::read_file(file) {
+        Ok(contents) => contents,
+        Err(e) => return vec![format!("failed to read: {}", e)],
+    };
+    let kind = detect_kind(&contents);
+    if kind != expected {
+        return vec![format!("expected {:?}, found {:?}", expected, kind)];
+    }
+
+    let mut problems = Vec::new();
+    match roundtrip(kind, &contents) {
+        Ok((_, differing)) if !differing.is_empty() => problems.push(format!(
+            "{} entries differ after round-trip: {}",
+            differing.len(),
+            differing.join(", ")
+        )),
+        Ok(_) => {}
+        Err(e) => return vec![format!("failed to parse: {}", e)],
+    }
+
+    if kind == FileKind::AppInfo {
+        match vdfr::lint::check_data(&contents) {
+            Ok(findings) => {
+                let mut counts: BTreeMap<vdfr::lint::Rule, usize> = BTreeMap::new();
+                for finding in &findings {
+                    *counts.entry(finding.rule).or_default() += 1;
+                }
+                for (rule, count) in counts {
+                    problems.push(format!("{} {} findings, see `vdf lint`", count, rule));
+                }
+            }
+            Err(e) => problems.push(format!("failed to lint: {}", e)),
+        }
+    }
+    problems
+}
+
+fn work_doctor(steam_dir: Option<std::path::PathBuf>) {
+    let steam = match steam_dir {
+        Some(path) => SteamDir { path },
+        None => locate_steam(),
+    };
+    println!("Steam: {}", steam.path.display());
+
+    let mut healthy = true;
+    for (file, kind) in [
+        (steam.appinfo_path(), FileKind::AppInfo),
+        (steam.packageinfo_path(), FileKind::PackageInfo),
+    ] {
+        let problems = diagnose_cache(&file, kind);
+        if problems.is_empty() {
+            println!("{}: OK", file.display());
+        }
+        for problem in &problems {
+            println!("{}: {}", file.display(), problem);
+        }
+        healthy &= problems.is_empty();
+    }
+
+    if !healthy {
+        println!("The caches are unhealthy, deleting the appcache folder while Steam is closed rebuilds them");
+        std::process::exit(1);
+    }
+    println!("The caches are healthy");
+}
+
+/// Quote a string leaf and truncate it to [`TREE_VALUE_WIDTH`].
+fn format_string_leaf(s: &str) -> String {
+    let quoted = format!("{:?}", s);
+    let quoted = if quoted.chars().count() > TREE_VALUE_WIDTH {
+        let truncated: String = quoted.chars().take(TREE_VALUE_WIDTH - 1).collect();
+        format!("{}…", truncated)
     } else {
-        let data = fs::read(file).unwrap();
+        quoted
+    };
+    format!("{TREE_STRING}{}{TREE_STRING:#}", quoted)
+}
 
-        let time_it = std::time::Instant::now();
-        let parsed = vdfr::parser::parse_package_info(&data).unwrap();
-        println!("Version: {}", parsed.version);
-        println!("Total packages: {}", parsed.packages.len());
-        println!("Time taken to parse: {:?}", time_it.elapsed());
-        let total = parsed.packages.values().count();
-        let random_pkg_pos = get_random_num(total);
-        let random_pkg = parsed.packages.values().nth(random_pkg_pos).unwrap();
-        println!("Random package: {:?}", random_pkg);
-        parsed
-    };
-
-    if redump {
-        let filename = file.file_stem().unwrap().to_str().unwrap();
-        let output_path = file
-            .parent()
-            .unwrap()
-            .join(format!("pkg_{}.json", filename));
-        let time_it = std::time::Instant::now();
-        let output_file = fs::File::create(&output_path).unwrap();
-        vdfr::serde_json::to_writer_pretty(output_file, &data).unwrap();
-        println!("Time taken to dump JSON: {:?}", time_it.elapsed());
-
-        let output_path_redump = file
-            .parent()
-            .unwrap()
-            .join(format!("pkg_{}_redump.vdf", filename));
-        let time_it = std::time::Instant::now();
-        let mut output_file_redump = fs::File::create(&output_path_redump).unwrap();
-        vdfr::writer::write_package_info(&mut output_file_redump, &data).unwrap();
-        println!("Time taken to redump VDF: {:?}", time_it.elapsed());
+/// Format a leaf value, with strings quoted and truncated to [`TREE_VALUE_WIDTH`].
+fn format_leaf(value: &Value) -> String {
+    match value {
+        Value::StringType(s) => format_string_leaf(s),
+        Value::WideStringType(s) => format_string_leaf(&s.to_string_lossy()),
+        Value::BytesType(b) => format_string_leaf(&String::from_utf8_lossy(b)),
+        Value::Int32Type(v) => format!("{TREE_NUMBER}{}{TREE_NUMBER:#}", v),
+        Value::PointerType(p) => format!("{TREE_NUMBER}{}{TREE_NUMBER:#}", p),
+        Value::ColorType(c) => format!("{TREE_NUMBER}{}{TREE_NUMBER:#}", c),
+        Value::UInt64Type(v) => format!("{TREE_NUMBER}{}{TREE_NUMBER:#}", v),
+        Value::Int64Type(v) => format!("{TREE_NUMBER}{}{TREE_NUMBER:#}", v),
+        Value::Float32Type(v) => format!("{TREE_NUMBER}{}{TREE_NUMBER:#}", v),
+        Value::KeyValueType(_) | Value::ArrayType(_) => String::new(),
+        Value::UnknownType { type_id, raw } => {
+            format!("<unknown type {:#04x}, {} bytes>", type_id, raw.len())
+        }
     }
 }
 
-fn work_kv(file: &std::path::PathBuf, legacy: bool, redump: bool) {
-    let data = if legacy {
-        let file = fs::File::open(file).unwrap();
-        let mut reader = std::io::BufReader::new(file);
-        let time_it = std::time::Instant::now();
-        let parsed =
-            vdfr::legacy_parser::parse_keyvalues(&mut reader, KeyValueOptions::default()).unwrap();
+fn print_tree_node(
+    key: &str,
+    value: &Value,
+    prefix: &str,
+    last: bool,
+    depth: usize,
+    max_depth: Option<usize>,
+) {
+    let branch = if last { "└─ " } else { "├─ " };
+    let children: Vec<(String, &Value)> = match value {
+        Value::KeyValueType(kv) => kv.iter().map(|(k, v)| (k.to_string(), v)).collect(),
+        Value::ArrayType(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => {
+            anstream::println!(
+                "{}{}{TREE_KEY}{}{TREE_KEY:#} {TREE_TYPE}{}{TREE_TYPE:#} {}",
+                prefix,
+                branch,
+                key,
+                value.kind_name(),
+                format_leaf(value)
+            );
+            return;
+        }
+    };
 
-        println!("Total key-values: {}", parsed.len());
-        println!("Time taken to parse: {:?}", time_it.elapsed());
-        parsed
+    // Sections past the maximum depth only show their amount of children
+    let collapsed = max_depth.is_some_and(|max_depth| depth >= max_depth) && !children.is_empty();
+    anstream::println!(
+        "{}{}{TREE_KEY}{}{TREE_KEY:#} {TREE_TYPE}{}[{}]{}{TREE_TYPE:#}",
+        prefix,
+        branch,
+        key,
+        value.kind_name(),
+        children.len(),
+        if collapsed { " …" } else { "" }
+    );
+    if collapsed {
+        return;
+    }
+
+    let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+    for (index, (key, child)) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        print_tree_node(key, child, &prefix, last, depth + 1, max_depth);
+    }
+}
+
+fn print_tree(key_values: &KeyValues, max_depth: Option<usize>) {
+    for (index, (key, value)) in key_values.iter().enumerate() {
+        let last = index + 1 == key_values.len();
+        print_tree_node(key, value, "", last, 0, max_depth);
+    }
+}
+
+fn work_tree(file: &std::path::PathBuf, appid: Option<u32>, depth: Option<usize>) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+
+    match detect_kind(&contents) {
+        FileKind::AppInfo => {
+            let data = match appid {
+                Some(appid) => vdfr::parser::parse_app_info_filtered(&contents, |id| id == appid),
+                None => vdfr::parser::parse_app_info(&contents),
+            }
+            .unwrap();
+            if data.apps.is_empty() {
+                println!("No app found");
+                std::process::exit(1);
+            }
+            for app in data.apps.values() {
+                anstream::println!(
+                    "{TREE_KEY}app {}{TREE_KEY:#} {TREE_TYPE}change {}{TREE_TYPE:#}",
+                    app.id,
+                    app.change_number
+                );
+                print_tree(&app.key_values, depth);
+            }
+        }
+        FileKind::PackageInfo => {
+            let data = vdfr::parser::parse_package_info(&contents).unwrap();
+            for package in data.packages.values() {
+                anstream::println!(
+                    "{TREE_KEY}package {}{TREE_KEY:#} {TREE_TYPE}change {}{TREE_TYPE:#}",
+                    package.id,
+                    package.change_number
+                );
+                print_tree(&package.key_values, depth);
+            }
+        }
+        FileKind::KeyValues => {
+            let data = vdfr::parser::parse_keyvalues(&contents).unwrap();
+            print_tree(&data, depth);
+        }
+    }
+}
+
+/// Wait for the file to settle after a change, Steam writes the cache in several steps.
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn run_hook(command: &str, changed: &[u32]) {
+    let changed = changed
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = std::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
     } else {
-        let data = fs::read(file).unwrap();
+        let mut shell = std::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    match shell.arg(command).env("VDF_CHANGED_APPS", changed).status() {
+        Ok(status) if !status.success() => warn!("Hook exited with {}", status),
+        Ok(_) => {}
+        Err(e) => error!("Failed to run hook: {}", e),
+    }
+}
 
-        let time_it = std::time::Instant::now();
-        let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
-        println!("Total key-values: {}", parsed.len());
-        println!("Time taken to parse: {:?}", time_it.elapsed());
-        parsed
+/// Print every value matching the regexes, the exit code is non-zero when none match.
+fn work_search(
+    file: &std::path::Path,
+    key: &Regex,
+    value: Option<&Regex>,
+    format: Option<DumpFormat>,
+) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let app_info;
+    let package_info;
+    let key_values;
+    let matches: Vec<(Option<u32>, String, &Value)> = match detect_kind(&contents) {
+        FileKind::AppInfo => {
+            app_info = vdfr::parser::parse_app_info(&contents).unwrap();
+            app_info
+                .search(key, value)
+                .into_iter()
+                .map(|(app_id, path, found)| (Some(app_id), path, found))
+                .collect()
+        }
+        FileKind::PackageInfo => {
+            package_info = vdfr::parser::parse_package_info(&contents).unwrap();
+            package_info
+                .packages
+                .values()
+                .flat_map(|package| {
+                    package
+                        .key_values
+                        .search(key, value)
+                        .into_iter()
+                        .map(|(path, found)| (Some(package.id), path, found))
+                })
+                .collect()
+        }
+        FileKind::KeyValues => {
+            key_values = vdfr::parser::parse_keyvalues(&contents).unwrap();
+            key_values
+                .search(key, value)
+                .into_iter()
+                .map(|(path, found)| (None, path, found))
+                .collect()
+        }
     };
+    if matches.is_empty() {
+        std::process::exit(1);
+    }
 
-    if redump {
-        let filename = file.file_stem().unwrap().to_str().unwrap();
-        let output_path = file.parent().unwrap().join(format!("kv_{}.json", filename));
-        let time_it = std::time::Instant::now();
-        let output_file = fs::File::create(&output_path).unwrap();
-        vdfr::serde_json::to_writer_pretty(output_file, &data).unwrap();
-        println!("Time taken to dump JSON: {:?}", time_it.elapsed());
-
-        let output_path_redump = file
-            .parent()
-            .unwrap()
-            .join(format!("kv_{}_redump.vdf", filename));
-        let time_it = std::time::Instant::now();
-        let mut output_file_redump = fs::File::create(&output_path_redump).unwrap();
-        vdfr::writer::write_keyvalues(&mut output_file_redump, &data).unwrap();
-        println!("Time taken to redump VDF: {:?}", time_it.elapsed());
+    let Some(format) = format else {
+        for (id, path, found) in &matches {
+            let id = id.map(|id| format!("{}\t", id)).unwrap_or_default();
+            anstream::println!(
+                "{}{TREE_KEY}{}{TREE_KEY:#}\t{}",
+                id,
+                path,
+                format_leaf(found)
+            );
+        }
+        return;
+    };
+    // Matches of a file with IDs are grouped in a section per ID
+    let mut found = KeyValues::new();
+    for (id, path, value) in matches {
+        let section = match id {
+            Some(id) => match found
+                .entry(id.to_string().into())
+                .or_insert_with(|| Value::KeyValueType(KeyValues::new()))
+            {
+                Value::KeyValueType(section) => section,
+                _ => unreachable!(),
+            },
+            None => &mut found,
+        };
+        section.insert(path.into(), value.clone());
+    }
+    let mut stdout = std::io::stdout().lock();
+    format.write(&mut stdout, &found).unwrap();
+}
+
+fn work_diff(old: &std::path::Path, new: &std::path::Path) {
+    let parse = |file: &std::path::Path| {
+        let contents = vdfr::compression::read_file(file).unwrap();
+        vdfr::parser::parse_app_info(&contents).unwrap()
+    };
+    let changes = vdfr::diff::appinfo_changes(&parse(old), &parse(new));
+
+    for app_id in &changes.added {
+        println!("added\t{}", app_id);
+    }
+    for app_id in &changes.removed {
+        println!("removed\t{}", app_id);
+    }
+    for change in &changes.changed {
+        println!(
+            "changed\t{}\t{} -> {}\t{}",
+            change.app_id,
+            change.old_change_number,
+            change.new_change_number,
+            change.sections.join(",")
+        );
+    }
+    if !changes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn work_patch(
+    old: &std::path::Path,
+    new: Option<&std::path::Path>,
+    apply: Option<&std::path::Path>,
+    output: &std::path::Path,
+) {
+    let old_contents = vdfr::compression::read_file(old).unwrap();
+    let output_contents = match (new, apply) {
+        (_, Some(patch_file)) => {
+            let patch = Patch::from_bytes(&std::fs::read(patch_file).unwrap()).unwrap();
+            patch.apply(&old_contents).unwrap()
+        }
+        (Some(new), None) => {
+            let new_contents = vdfr::compression::read_file(new).unwrap();
+            let patch = Patch::create(&old_contents, &new_contents).unwrap();
+            info!(
+                apps = patch.ops.len(),
+                bytes = patch.to_bytes().len(),
+                new_bytes = new_contents.len(),
+                "Created patch"
+            );
+            patch.to_bytes()
+        }
+        (None, None) => unreachable!("clap requires the new file or a patch"),
+    };
+    std::fs::write(output, output_contents).unwrap();
+}
+
+fn work_watch(file: &std::path::Path, on_change: Option<&str>) {
+    let contents = vdfr::compression::read_file(file).unwrap();
+    let mut data = vdfr::parser::parse_app_info(&contents).unwrap();
+    println!("Watching {} ({} apps)", file.display(), data.apps.len());
+
+    // Steam replaces the file instead of writing in place, so watch the directory
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).unwrap();
+    let directory = file.parent().unwrap_or(std::path::Path::new("."));
+    notify::Watcher::watch(&mut watcher, directory, notify::RecursiveMode::NonRecursive).unwrap();
+
+    let file_name = file.file_name();
+    let is_file_event = |event: &notify::Result<notify::Event>| {
+        event.as_ref().is_ok_and(|event| {
+            !event.kind.is_access() && event.paths.iter().any(|path| path.file_name() == file_name)
+        })
+    };
+
+    while let Ok(event) = rx.recv() {
+        if !is_file_event(&event) {
+            continue;
+        }
+        std::thread::sleep(WATCH_DEBOUNCE);
+        while rx.try_recv().is_ok() {}
+
+        let Ok(contents) = vdfr::compression::read_file(file) else {
+            continue;
+        };
+        let old_apps = data
+            .apps
+            .iter()
+            .map(|(id, app)| (*id, app.change_number))
+            .collect::<BTreeMap<_, _>>();
+        let changed = match data.update_from(&contents) {
+            Ok(changed) => changed,
+            Err(e) => {
+                warn!("Failed to parse the updated file: {}", e);
+                continue;
+            }
+        };
+
+        for app_id in &changed {
+            let change_number = data.apps[app_id].change_number;
+            match old_apps.get(app_id) {
+                Some(old) => println!("changed\t{}\t{} -> {}", app_id, old, change_number),
+                None => println!("added\t{}\t{}", app_id, change_number),
+            }
+        }
+        let removed = old_apps
+            .keys()
+            .filter(|app_id| !data.apps.contains_key(*app_id))
+            .collect::<Vec<_>>();
+        for app_id in &removed {
+            println!("removed\t{}", app_id);
+        }
+
+        if let Some(command) = on_change {
+            if !changed.is_empty() || !removed.is_empty() {
+                run_hook(command, &changed);
+            }
+        }
     }
 }
 
 fn main() {
     let args = Args::parse();
+    init_logging(args.verbose, args.quiet, args.log_format);
+    if let Err(err) = config::load(args.config.as_deref()) {
+        error!("Invalid config {}", err);
+        std::process::exit(1);
+    }
 
     match args.subcommand {
         Subcommand::AppInfo {
-            file,
+            files,
             legacy,
-            redump,
-        } => work_app_info(&file, legacy, redump),
+            split_dir,
+            only,
+            mut strip,
+            mut dump,
+        } => {
+            let files = if files.is_empty() {
+                vec![locate_steam().appinfo_path()]
+            } else {
+                expand_inputs(&files)
+            };
+            if dump.enabled() || split_dir.is_some() {
+                dump.apply_config();
+            }
+            strip.extend(config::get().strip.iter().cloned());
+            run_batch(&files, &dump, |file| {
+                work_app_info(file, legacy, split_dir.as_deref(), &only, &strip, &dump)
+            })
+        }
         Subcommand::PackageInfo {
-            file,
+            files,
             legacy,
-            redump,
-        } => work_pkg_info(&file, legacy, redump),
+            mut dump,
+        } => {
+            let files = if files.is_empty() {
+                vec![locate_steam().packageinfo_path()]
+            } else {
+                expand_inputs(&files)
+            };
+            if dump.enabled() {
+                dump.apply_config();
+            }
+            run_batch(&files, &dump, |file| work_pkg_info(file, legacy, &dump))
+        }
         Subcommand::KV {
-            file,
+            files,
             legacy,
-            redump,
-        } => work_kv(&file, legacy, redump),
+            mut dump,
+        } => {
+            if dump.enabled() {
+                dump.apply_config();
+            }
+            run_batch(&expand_inputs(&files), &dump, |file| {
+                work_kv(file, legacy, &dump)
+            })
+        }
+        Subcommand::Bench { file, iterations } => work_bench(&file, iterations),
+        Subcommand::Changed {
+            file,
+            since_change,
+            since,
+        } => work_changed(&file, since_change, since.as_ref()),
+        Subcommand::Stats {
+            file,
+            top_size,
+            histograms,
+            json,
+            buckets,
+        } => {
+            let file = file.unwrap_or_else(|| locate_steam().appinfo_path());
+            let histograms = histograms.then_some(HistogramOutput { json, buckets });
+            work_stats(&file, top_size, histograms)
+        }
+        Subcommand::Lint { file } => {
+            let file = file.unwrap_or_else(|| locate_steam().appinfo_path());
+            work_lint(&file)
+        }
+        Subcommand::Doctor { steam_dir } => work_doctor(steam_dir),
+        Subcommand::Verify { file, exact } => work_verify(&file, exact),
+        Subcommand::Tree { file, appid, depth } => work_tree(&file, appid, depth),
+        Subcommand::Debug { file } => {
+            let contents = vdfr::compression::read_file(&file).unwrap();
+            if let Err(e) = debug::dump(detect_kind(&contents), &contents) {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Subcommand::Watch { file, on_change } => {
+            let file = file.unwrap_or_else(|| locate_steam().appinfo_path());
+            work_watch(&file, on_change.as_deref())
+        }
+        Subcommand::Diff { old, new } => work_diff(&old, &new),
+        Subcommand::Search {
+            file,
+            key,
+            value,
+            format,
+        } => work_search(&file, &key, value.as_ref(), format),
+        Subcommand::Patch {
+            old,
+            new,
+            apply,
+            output,
+        } => work_patch(&old, new.as_deref(), apply.as_deref(), &output),
+        Subcommand::Serve { file, listen } => {
+            let file = file.unwrap_or_else(|| locate_steam().appinfo_path());
+            let contents = vdfr::compression::read_file(&file).unwrap();
+            let index = load_index(&file, &contents);
+            let app_info = IndexedAppInfo::open(&contents, index).unwrap();
+            println!(
+                "Serving {} ({} apps)",
+                file.display(),
+                app_info.index().entries.len()
+            );
+            serve::serve(&app_info, &listen).unwrap()
+        }
+        #[cfg(feature = "tui")]
+        Subcommand::Browse { file } => {
+            let file = file.unwrap_or_else(|| locate_steam().appinfo_path());
+            let contents = vdfr::compression::read_file(&file).unwrap();
+            let data = vdfr::parser::parse_app_info(&contents).unwrap();
+            browse::browse(data).unwrap()
+        }
+        Subcommand::Completions { shell } => {
+            let mut command = Args::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        }
+        Subcommand::Mangen { dir } => {
+            fs::create_dir_all(&dir).unwrap();
+            clap_mangen::generate_to(Args::command(), &dir).unwrap();
+        }
     }
 }
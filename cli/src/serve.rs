@@ -0,0 +1,151 @@
+//! Minimal HTTP server answering JSON queries about an app info file.
+//!
+//! Apps are decoded on demand through the index, so the server starts without parsing
+//! the whole file. Every connection is answered once and closed.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use tracing::warn;
+use vdfr::index::IndexedAppInfo;
+
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(body: String) -> Self {
+        Response {
+            status: "200 OK",
+            body,
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Response {
+            status,
+            body: vdfr::serde_json::json!({ "error": message }).to_string(),
+        }
+    }
+}
+
+/// Serve the app info on the address until the process is stopped.
+pub fn serve(app_info: &IndexedAppInfo, listen: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    println!("Listening on http://{}", listener.local_addr()?);
+
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(move || {
+                        if let Err(e) = handle_connection(app_info, stream) {
+                            warn!("Failed to answer a request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept a connection: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(app_info: &IndexedAppInfo, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not used, but have to be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => route(app_info, target),
+        (Some(_), Some(_)) => Response::error("405 Method Not Allowed", "only GET is supported"),
+        _ => Response::error("400 Bad Request", "malformed request line"),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.body.len()
+    )?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+/// Answer `/apps`, `/apps/{id}` and `/apps/{id}/kv/{path}`.
+fn route(app_info: &IndexedAppInfo, target: &str) -> Response {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match segments.as_slice() {
+        ["apps"] => {
+            let apps: Vec<_> = app_info
+                .index()
+                .entries
+                .iter()
+                .map(|(id, entry)| {
+                    vdfr::serde_json::json!({ "id": id, "change_number": entry.change_number })
+                })
+                .collect();
+            Response::json(vdfr::serde_json::Value::from(apps).to_string())
+        }
+        ["apps", id, rest @ ..] => {
+            let Ok(id) = id.parse::<u32>() else {
+                return Response::error("400 Bad Request", "the app ID is not a number");
+            };
+            let app = match app_info.get(id) {
+                Ok(Some(app)) => app,
+                Ok(None) => return Response::error("404 Not Found", "no app with this ID"),
+                Err(e) => return Response::error("500 Internal Server Error", &e.to_string()),
+            };
+            match rest {
+                [] => Response::json(vdfr::serde_json::to_string(&app).unwrap()),
+                ["kv", keys @ ..] => match app.get(keys) {
+                    Some(value) => Response::json(vdfr::serde_json::to_string(value).unwrap()),
+                    None => Response::error("404 Not Found", "no value at this path"),
+                },
+                _ => Response::error("404 Not Found", "unknown endpoint"),
+            }
+        }
+        _ => Response::error("404 Not Found", "unknown endpoint"),
+    }
+}
+
+/// Decode the `%XX` escapes of a path segment, invalid escapes are kept as they are.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,8 @@
+//! The `vdfr` Python extension module, with the bindings of `vdfr::python`.
+
+use pyo3::prelude::*;
+
+#[pymodule]
+fn vdfr(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    ::vdfr::python::register(m)
+}
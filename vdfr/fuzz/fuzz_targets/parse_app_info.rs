@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = vdfr::parser::parse_app_info(data);
+    let _ = vdfr::parser::parse_app_info_resilient(data);
+    let _ = vdfr::legacy_parser::parse_app_info(&mut std::io::Cursor::new(data));
+});
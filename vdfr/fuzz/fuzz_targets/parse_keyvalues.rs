@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = vdfr::parser::parse_keyvalues(data);
+    let _ = vdfr::legacy_parser::parse_keyvalues(
+        &mut std::io::Cursor::new(data),
+        vdfr::KeyValueOptions::default(),
+    );
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = vdfr::parser::parse_package_info(data);
+    let _ = vdfr::legacy_parser::parse_package_info(&mut std::io::Cursor::new(data));
+});
@@ -0,0 +1,231 @@
+//! Size analysis of app info files, which keys take the most space and which pool strings are unused.
+//!
+//! Also distributions over the apps, like their release years or the depth of their key-values.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec::Vec,
+};
+
+use crate::{
+    parser::{parse_app_info_header, parse_app_info_with_spans},
+    AppInfo, Key, KeyValueOptions, KeyValues, Value, VdfrError,
+};
+
+/// How often a key occurs in a file and the bytes of its nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyUsage {
+    pub key: Key,
+    /// Amount of nodes with the key.
+    pub count: usize,
+    /// Encoded size of the nodes with the key, from the type byte to the end of the value.
+    ///
+    /// Nested nodes are also counted in the size of their parents.
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyReport {
+    /// Usage of every key, the most frequent first.
+    pub keys: Vec<KeyUsage>,
+    /// Entries of the v29 string pool, empty for older versions.
+    pub pool_size: usize,
+    /// Strings of the v29 string pool that no key refers to.
+    pub unused_pool: Vec<String>,
+}
+
+impl KeyReport {
+    /// The keys taking the most space, the biggest first.
+    pub fn largest(&self, count: usize) -> Vec<&KeyUsage> {
+        let mut keys: Vec<&KeyUsage> = self.keys.iter().collect();
+        keys.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.key.cmp(&b.key)));
+        keys.truncate(count);
+        keys
+    }
+}
+
+/// Report the key usage of an app info file.
+///
+/// Keys of v24 apps are not counted, since their nodes are not recorded by
+/// [`parse_app_info_with_spans`].
+pub fn analyze_keys(data: &[u8]) -> Result<KeyReport, VdfrError> {
+    let (_, spans) = parse_app_info_with_spans(data, true)?;
+
+    let mut usage: BTreeMap<Key, (usize, usize)> = BTreeMap::new();
+    for nodes in spans.nodes.values() {
+        for (path, range) in nodes {
+            let Some(key) = path.last() else {
+                continue;
+            };
+            let (count, bytes) = usage.entry(key.clone()).or_default();
+            *count += 1;
+            *bytes += range.len();
+        }
+    }
+
+    let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+    let pool = header.options.string_pool;
+    let used: BTreeSet<&str> = usage.keys().map(|key| &**key).collect();
+    let unused_pool = pool
+        .iter()
+        .filter(|entry| !used.contains(entry.as_str()))
+        .cloned()
+        .collect();
+
+    let mut keys: Vec<KeyUsage> = usage
+        .into_iter()
+        .map(|(key, (count, bytes))| KeyUsage { key, count, bytes })
+        .collect();
+    keys.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+
+    Ok(KeyReport {
+        keys,
+        pool_size: pool.len(),
+        unused_pool,
+    })
+}
+
+/// Distributions over the apps of an app info file, see [`histograms`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Histograms {
+    /// Apps by the year of their `steam_release_date`, or `original_release_date`, in UTC.
+    pub release_years: BTreeMap<i32, usize>,
+    /// Apps without a release date.
+    pub undated: usize,
+    /// Apps by change number, in buckets of the same width from the lowest to the highest.
+    pub change_numbers: Vec<Bucket>,
+    /// Apps by the depth of their key-values, a section of leaves being 1 deep.
+    pub depths: BTreeMap<usize, usize>,
+    /// Values of all the apps by their type, like `string` or `section`.
+    pub value_types: BTreeMap<&'static str, usize>,
+}
+
+/// Amount of items from `start` to `end`, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Bucket {
+    pub start: u32,
+    pub end: u32,
+    pub count: usize,
+}
+
+/// Compute the distributions over the apps, with up to `buckets` change number buckets.
+///
+/// Dates and depths are read below the `appinfo` section of apps wrapped in one.
+pub fn histograms(app_info: &AppInfo, buckets: usize) -> Histograms {
+    let mut histograms = Histograms::default();
+    for app in app_info.apps.values() {
+        let sections = app.sections();
+        let date = ["steam_release_date", "original_release_date"]
+            .iter()
+            .filter_map(|key| match sections.get("common") {
+                Some(Value::KeyValueType(common)) => common.get(*key).and_then(timestamp),
+                _ => None,
+            })
+            .find(|&date| date > 0);
+        match date {
+            Some(date) => *histograms.release_years.entry(year_of(date)).or_default() += 1,
+            None => histograms.undated += 1,
+        }
+
+        *histograms.depths.entry(depth(sections)).or_default() += 1;
+        count_types(&app.key_values, &mut histograms.value_types);
+    }
+
+    let change_numbers: Vec<u32> = app_info
+        .apps
+        .values()
+        .map(|app| app.change_number)
+        .collect();
+    histograms.change_numbers = bucket(&change_numbers, buckets);
+    histograms
+}
+
+/// Split the values into buckets of the same width, empty ones included.
+fn bucket(values: &[u32], buckets: usize) -> Vec<Bucket> {
+    let (Some(&min), Some(&max)) = (values.iter().min(), values.iter().max()) else {
+        return Vec::new();
+    };
+    let buckets = buckets.max(1) as u64;
+    let width = (max - min) as u64 / buckets + 1;
+
+    let mut counts = Vec::new();
+    let mut start = min as u64;
+    while start <= max as u64 {
+        let end = (start + width - 1).min(max as u64);
+        counts.push(Bucket {
+            start: start as u32,
+            end: end as u32,
+            count: 0,
+        });
+        start += width;
+    }
+    for &value in values {
+        counts[((value - min) as u64 / width) as usize].count += 1;
+    }
+    counts
+}
+
+/// A unix timestamp, stored either as a number or a string.
+fn timestamp(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int32Type(value) => Some(*value as i64),
+        Value::UInt64Type(value) => i64::try_from(*value).ok(),
+        Value::Int64Type(value) => Some(*value),
+        Value::StringType(value) => value.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// The year of a unix timestamp, in UTC.
+fn year_of(timestamp: i64) -> i32 {
+    // Howard Hinnant's `civil_from_days`, with years starting in March
+    let days = timestamp.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // January and February are the last months of the shifted year
+    let in_next_year = (5 * day_of_year + 2) / 153 >= 10;
+    (era * 400 + year_of_era + in_next_year as i64) as i32
+}
+
+/// Levels of sections and arrays, 0 for empty key-values.
+fn depth(kv: &KeyValues) -> usize {
+    if kv.is_empty() {
+        return 0;
+    }
+    1 + kv.values().map(value_depth).max().unwrap_or(0)
+}
+
+fn value_depth(value: &Value) -> usize {
+    match value {
+        Value::KeyValueType(kv) => depth(kv),
+        Value::ArrayType(items) if !items.is_empty() => {
+            1 + items.iter().map(value_depth).max().unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+fn count_types(kv: &KeyValues, counts: &mut BTreeMap<&'static str, usize>) {
+    for value in kv.values() {
+        count_value_types(value, counts);
+    }
+}
+
+fn count_value_types(value: &Value, counts: &mut BTreeMap<&'static str, usize>) {
+    *counts.entry(value.kind_name()).or_default() += 1;
+    match value {
+        Value::KeyValueType(kv) => count_types(kv, counts),
+        Value::ArrayType(items) => {
+            for item in items {
+                count_value_types(item, counts);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,55 @@
+//! Zero-copy archives of a parsed [`AppInfo`], with rkyv.
+//!
+//! Unlike the binary cache, an archive is queried in place: [`access`] only validates
+//! the bytes, so a memory-mapped archive is usable right away without deserializing.
+//! Keys shared by the parser are stored once. Like the cache, an archive is tied to
+//! the vdfr version that wrote it.
+
+use rkyv::{rancor, util::AlignedVec, Archived};
+
+use crate::{AppInfo, ArchivedApp, ArchivedAppInfo, ArchivedValue, VdfrError};
+
+fn invalid_archive(error: rancor::Error) -> VdfrError {
+    VdfrError::InvalidCache(error.to_string())
+}
+
+impl AppInfo {
+    /// Write the app info into an archive, aligned so it can be accessed directly.
+    pub fn to_archive(&self) -> Result<AlignedVec, VdfrError> {
+        rkyv::to_bytes::<rancor::Error>(self).map_err(invalid_archive)
+    }
+
+    /// Deserialize app info from an archive, when the owned data is needed.
+    pub fn from_archive(bytes: &[u8]) -> Result<AppInfo, VdfrError> {
+        rkyv::from_bytes::<AppInfo, rancor::Error>(bytes).map_err(invalid_archive)
+    }
+}
+
+/// Validate an archive and access the app info in it, without deserializing.
+///
+/// The bytes must be aligned to 16 bytes, like an [`AlignedVec`] or a memory-mapped file.
+pub fn access(bytes: &[u8]) -> Result<&ArchivedAppInfo, VdfrError> {
+    rkyv::access::<ArchivedAppInfo, rancor::Error>(bytes).map_err(invalid_archive)
+}
+
+impl ArchivedAppInfo {
+    pub fn app(&self, app_id: u32) -> Option<&ArchivedApp> {
+        self.apps.get(&Archived::<u32>::from_native(app_id))
+    }
+}
+
+impl ArchivedApp {
+    /// Find the value at the key path, like [`crate::App::get`].
+    pub fn get(&self, keys: &[&str]) -> Option<&ArchivedValue> {
+        let (first, rest) = keys.split_first()?;
+        let mut value = self.key_values.get(*first)?;
+        for key in rest {
+            value = match value {
+                ArchivedValue::KeyValueType(key_values) => key_values.get(*key)?,
+                ArchivedValue::ArrayType(array) => array.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
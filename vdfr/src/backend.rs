@@ -0,0 +1,71 @@
+//! Runtime-selectable parser backends.
+//!
+//! Both the [`crate::parser`] (nom) and [`crate::legacy_parser`] (byteorder) modules
+//! can be used through the [`VdfParser`] trait, so callers can pick one at runtime.
+
+use crate::{AppInfo, KeyValueOptions, KeyValues, PackageInfo, VdfrError};
+
+/// A parser backend for the binary VDF formats.
+pub trait VdfParser {
+    /// Short name of the backend, e.g. for reporting.
+    fn name(&self) -> &'static str;
+
+    fn parse_app_info(&self, data: &[u8]) -> Result<AppInfo, VdfrError>;
+
+    fn parse_package_info(&self, data: &[u8]) -> Result<PackageInfo, VdfrError>;
+
+    fn parse_keyvalues(&self, data: &[u8]) -> Result<KeyValues, VdfrError>;
+}
+
+/// The nom-based parser from [`crate::parser`].
+#[derive(Debug, Clone, Default)]
+pub struct NomParser {
+    pub options: KeyValueOptions,
+}
+
+impl VdfParser for NomParser {
+    fn name(&self) -> &'static str {
+        "nom"
+    }
+
+    fn parse_app_info(&self, data: &[u8]) -> Result<AppInfo, VdfrError> {
+        crate::parser::parse_app_info_with_options(data, &self.options)
+    }
+
+    fn parse_package_info(&self, data: &[u8]) -> Result<PackageInfo, VdfrError> {
+        crate::parser::parse_package_info_with_options(data, &self.options)
+    }
+
+    fn parse_keyvalues(&self, data: &[u8]) -> Result<KeyValues, VdfrError> {
+        crate::parser::parse_keyvalues_with_options(data, &self.options)
+    }
+}
+
+/// The byteorder-based parser from [`crate::legacy_parser`].
+#[cfg(feature = "legacy")]
+#[derive(Debug, Clone, Default)]
+pub struct LegacyParser {
+    pub options: KeyValueOptions,
+}
+
+#[cfg(feature = "legacy")]
+impl VdfParser for LegacyParser {
+    fn name(&self) -> &'static str {
+        "legacy"
+    }
+
+    fn parse_app_info(&self, data: &[u8]) -> Result<AppInfo, VdfrError> {
+        let mut reader = std::io::Cursor::new(data);
+        crate::legacy_parser::parse_app_info_with_options(&mut reader, self.options.clone())
+    }
+
+    fn parse_package_info(&self, data: &[u8]) -> Result<PackageInfo, VdfrError> {
+        let mut reader = std::io::Cursor::new(data);
+        crate::legacy_parser::parse_package_info_with_options(&mut reader, self.options.clone())
+    }
+
+    fn parse_keyvalues(&self, data: &[u8]) -> Result<KeyValues, VdfrError> {
+        let mut reader = std::io::Cursor::new(data);
+        crate::legacy_parser::parse_keyvalues(&mut reader, self.options.clone())
+    }
+}
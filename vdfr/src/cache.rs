@@ -0,0 +1,244 @@
+//! Binary cache of a parsed [`AppInfo`].
+//!
+//! Loading the cache skips the VDF parsing entirely, which helps tools that
+//! read the same `appinfo.vdf` on every run. The cache is tied to the vdfr
+//! version that wrote it, and should be rebuilt from the VDF when it fails to load.
+
+use std::{
+    hash::Hash,
+    io::{Read, Write},
+};
+
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{AllowedEnumVariants, DecodeError, EncodeError},
+    Decode, Encode,
+};
+
+use crate::{App, AppInfo, Color, Map, Pointer, Value, VdfrError, WideString};
+
+/// Magic of the cache file, `VDFC` in little endian.
+const CACHE_MAGIC: u32 = 0x43_46_44_56;
+/// Bump whenever the cached data model changes.
+const CACHE_FORMAT_VERSION: u32 = 4;
+
+impl AppInfo {
+    /// Write the app info into the binary cache format.
+    pub fn save_cache<W>(&self, writer: &mut W) -> Result<(), VdfrError>
+    where
+        W: Write,
+    {
+        writer.write_all(&CACHE_MAGIC.to_le_bytes())?;
+        writer.write_all(&CACHE_FORMAT_VERSION.to_le_bytes())?;
+        bincode::encode_into_std_write(self, writer, bincode::config::standard())
+            .map_err(|e| VdfrError::InvalidCache(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Read app info back from the binary cache format.
+    pub fn load_cache<R>(reader: &mut R) -> Result<AppInfo, VdfrError>
+    where
+        R: Read,
+    {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let magic = u32::from_le_bytes(header[..4].try_into().unwrap());
+        if magic != CACHE_MAGIC {
+            return Err(VdfrError::UnknownMagic(magic));
+        }
+        let format_version = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if format_version != CACHE_FORMAT_VERSION {
+            return Err(VdfrError::InvalidCache(format!(
+                "unsupported cache version {}",
+                format_version
+            )));
+        }
+
+        bincode::decode_from_std_read(reader, bincode::config::standard())
+            .map_err(|e| VdfrError::InvalidCache(e.to_string()))
+    }
+}
+
+// Maps are encoded like bincode encodes a `BTreeMap`, whichever map backs `Map`.
+
+fn encode_map<K, V, E>(map: &Map<K, V>, encoder: &mut E) -> Result<(), EncodeError>
+where
+    K: Encode,
+    V: Encode,
+    E: Encoder,
+{
+    (map.len() as u64).encode(encoder)?;
+    for (key, value) in map {
+        key.encode(encoder)?;
+        value.encode(encoder)?;
+    }
+    Ok(())
+}
+
+fn decode_map<K, V, D>(decoder: &mut D) -> Result<Map<K, V>, DecodeError>
+where
+    K: Decode<D::Context> + Ord + Hash,
+    V: Decode<D::Context>,
+    D: Decoder,
+{
+    let len = u64::decode(decoder)?;
+    let len = usize::try_from(len).map_err(|_| DecodeError::OutsideUsizeRange(len))?;
+    decoder.claim_container_read::<(K, V)>(len)?;
+
+    let mut map = Map::new();
+    for _ in 0..len {
+        decoder.unclaim_bytes_read(core::mem::size_of::<(K, V)>());
+        let key = K::decode(decoder)?;
+        let value = V::decode(decoder)?;
+        map.insert(key, value);
+    }
+    Ok(map)
+}
+
+impl Encode for Value {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        match self {
+            Value::StringType(value) => {
+                0u32.encode(encoder)?;
+                value.encode(encoder)
+            }
+            Value::WideStringType(value) => {
+                1u32.encode(encoder)?;
+                value.units().encode(encoder)?;
+                value.is_big_endian().encode(encoder)
+            }
+            Value::Int32Type(value) => {
+                2u32.encode(encoder)?;
+                value.encode(encoder)
+            }
+            Value::PointerType(value) => {
+                3u32.encode(encoder)?;
+                value.0.encode(encoder)
+            }
+            Value::ColorType(value) => {
+                4u32.encode(encoder)?;
+                value.to_i32().encode(encoder)
+            }
+            Value::UInt64Type(value) => {
+                5u32.encode(encoder)?;
+                value.encode(encoder)
+            }
+            Value::Int64Type(value) => {
+                6u32.encode(encoder)?;
+                value.encode(encoder)
+            }
+            Value::Float32Type(value) => {
+                7u32.encode(encoder)?;
+                value.encode(encoder)
+            }
+            Value::KeyValueType(key_values) => {
+                8u32.encode(encoder)?;
+                encode_map(key_values, encoder)
+            }
+            Value::ArrayType(values) => {
+                9u32.encode(encoder)?;
+                values.encode(encoder)
+            }
+            Value::BytesType(value) => {
+                10u32.encode(encoder)?;
+                value.encode(encoder)
+            }
+            Value::UnknownType { type_id, raw } => {
+                11u32.encode(encoder)?;
+                type_id.encode(encoder)?;
+                raw.encode(encoder)
+            }
+        }
+    }
+}
+
+impl<Context> Decode<Context> for Value {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let variant = u32::decode(decoder)?;
+        Ok(match variant {
+            0 => Value::StringType(Decode::decode(decoder)?),
+            1 => {
+                let units = Decode::decode(decoder)?;
+                Value::WideStringType(WideString::from_units(units, Decode::decode(decoder)?))
+            }
+            2 => Value::Int32Type(Decode::decode(decoder)?),
+            3 => Value::PointerType(Pointer(Decode::decode(decoder)?)),
+            4 => Value::ColorType(Color::from_i32(Decode::decode(decoder)?)),
+            5 => Value::UInt64Type(Decode::decode(decoder)?),
+            6 => Value::Int64Type(Decode::decode(decoder)?),
+            7 => Value::Float32Type(Decode::decode(decoder)?),
+            8 => Value::KeyValueType(decode_map(decoder)?),
+            9 => Value::ArrayType(Decode::decode(decoder)?),
+            10 => Value::BytesType(Decode::decode(decoder)?),
+            11 => Value::UnknownType {
+                type_id: Decode::decode(decoder)?,
+                raw: Decode::decode(decoder)?,
+            },
+            found => {
+                return Err(DecodeError::UnexpectedVariant {
+                    type_name: "Value",
+                    allowed: &AllowedEnumVariants::Range { min: 0, max: 11 },
+                    found,
+                })
+            }
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(Value);
+
+impl Encode for App {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.id.encode(encoder)?;
+        self.size.encode(encoder)?;
+        self.state.encode(encoder)?;
+        self.last_update.encode(encoder)?;
+        self.access_token.encode(encoder)?;
+        self.checksum_txt.encode(encoder)?;
+        self.checksum_bin.encode(encoder)?;
+        self.change_number.encode(encoder)?;
+        encode_map(&self.key_values, encoder)
+    }
+}
+
+impl<Context> Decode<Context> for App {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(App {
+            id: Decode::decode(decoder)?,
+            size: Decode::decode(decoder)?,
+            state: Decode::decode(decoder)?,
+            last_update: Decode::decode(decoder)?,
+            access_token: Decode::decode(decoder)?,
+            checksum_txt: Decode::decode(decoder)?,
+            checksum_bin: Decode::decode(decoder)?,
+            change_number: Decode::decode(decoder)?,
+            key_values: decode_map(decoder)?,
+            raw: None,
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(App);
+
+impl Encode for AppInfo {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.version.encode(encoder)?;
+        self.universe.encode(encoder)?;
+        encode_map(&self.apps, encoder)?;
+        self.trailing_bytes.encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for AppInfo {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(AppInfo {
+            version: Decode::decode(decoder)?,
+            universe: Decode::decode(decoder)?,
+            apps: decode_map(decoder)?,
+            trailing_bytes: Decode::decode(decoder)?,
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(AppInfo);
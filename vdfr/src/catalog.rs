@@ -0,0 +1,101 @@
+//! Cross-reference between a parsed [`AppInfo`] and [`PackageInfo`].
+//!
+//! Packages list the apps they grant in their `appids` key-values, the catalog
+//! joins them both ways so licensing questions don't need a scan over every package.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::ToString,
+    vec::Vec,
+};
+
+use crate::{App, AppInfo, Package, PackageInfo, Pointer, Value};
+
+pub struct Catalog<'a> {
+    app_info: &'a AppInfo,
+    package_info: &'a PackageInfo,
+    app_packages: BTreeMap<u32, Vec<u32>>,
+    package_apps: BTreeMap<u32, Vec<u32>>,
+}
+
+impl<'a> Catalog<'a> {
+    pub fn new(app_info: &'a AppInfo, package_info: &'a PackageInfo) -> Self {
+        let mut app_packages: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        let mut package_apps = BTreeMap::new();
+
+        for (package_id, package) in &package_info.packages {
+            let app_ids = package_app_ids(package);
+            for app_id in &app_ids {
+                app_packages.entry(*app_id).or_default().push(*package_id);
+            }
+            package_apps.insert(*package_id, app_ids);
+        }
+
+        Catalog {
+            app_info,
+            package_info,
+            app_packages,
+            package_apps,
+        }
+    }
+
+    pub fn app(&self, app_id: u32) -> Option<&'a App> {
+        self.app_info.apps.get(&app_id)
+    }
+
+    pub fn package(&self, package_id: u32) -> Option<&'a Package> {
+        self.package_info.packages.get(&package_id)
+    }
+
+    /// IDs of the packages that grant the app.
+    pub fn packages_for_app(&self, app_id: u32) -> &[u32] {
+        self.app_packages
+            .get(&app_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// IDs of the apps granted by the package.
+    pub fn apps_in_package(&self, package_id: u32) -> &[u32] {
+        self.package_apps
+            .get(&package_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// IDs of every app granted by any of the owned packages.
+    pub fn owned_apps<I>(&self, package_ids: I) -> BTreeSet<u32>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        package_ids
+            .into_iter()
+            .flat_map(|package_id| self.apps_in_package(package_id))
+            .copied()
+            .collect()
+    }
+}
+
+/// Read the `appids` of a package, which is either at the root or nested under the package ID.
+fn package_app_ids(package: &Package) -> Vec<u32> {
+    let app_ids = package
+        .get(&["appids"])
+        .or_else(|| package.get(&[&package.id.to_string(), "appids"]));
+
+    match app_ids {
+        Some(Value::ArrayType(array)) => array.iter().filter_map(value_as_id).collect(),
+        Some(Value::KeyValueType(kv)) => kv.values().filter_map(value_as_id).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn value_as_id(value: &Value) -> Option<u32> {
+    match value {
+        Value::Int32Type(v) | Value::PointerType(Pointer(v)) => u32::try_from(*v).ok(),
+        Value::UInt64Type(v) => u32::try_from(*v).ok(),
+        Value::Int64Type(v) => u32::try_from(*v).ok(),
+        Value::StringType(s) => s.parse().ok(),
+        Value::WideStringType(s) => s.to_string_lossy().parse().ok(),
+        _ => None,
+    }
+}
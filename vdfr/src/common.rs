@@ -1,40 +1,145 @@
-use std::{collections::BTreeMap, ops::Deref};
+use alloc::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::ops::{Deref, Range};
 
 #[cfg(feature = "serde")]
 use serde::{ser::SerializeStruct, Deserialize, Serialize};
 
-pub(crate) const BIN_KV: u8 = b'\x00';
-pub(crate) const BIN_STRING: u8 = b'\x01';
-pub(crate) const BIN_INT32: u8 = b'\x02';
-pub(crate) const BIN_FLOAT32: u8 = b'\x03';
-pub(crate) const BIN_POINTER: u8 = b'\x04';
-pub(crate) const BIN_WIDESTRING: u8 = b'\x05';
-pub(crate) const BIN_COLOR: u8 = b'\x06';
-pub(crate) const BIN_UINT64: u8 = b'\x07';
-pub(crate) const BIN_END: u8 = b'\x08';
-pub(crate) const BIN_INT64: u8 = b'\x0A';
-pub(crate) const BIN_END_ALT: u8 = b'\x0B';
+/// Type byte preceding every node of binary key-values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum BinType {
+    Map = 0x00,
+    String = 0x01,
+    Int32 = 0x02,
+    Float32 = 0x03,
+    Pointer = 0x04,
+    WideString = 0x05,
+    Color = 0x06,
+    UInt64 = 0x07,
+    /// End of a map.
+    End = 0x08,
+    Int64 = 0x0A,
+    /// End of a map, in the alternative format of files like `UserGameStatsSchema_*.bin`.
+    EndAlt = 0x0B,
+}
+
+impl BinType {
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(BinType::Map),
+            0x01 => Some(BinType::String),
+            0x02 => Some(BinType::Int32),
+            0x03 => Some(BinType::Float32),
+            0x04 => Some(BinType::Pointer),
+            0x05 => Some(BinType::WideString),
+            0x06 => Some(BinType::Color),
+            0x07 => Some(BinType::UInt64),
+            0x08 => Some(BinType::End),
+            0x0A => Some(BinType::Int64),
+            0x0B => Some(BinType::EndAlt),
+            _ => None,
+        }
+    }
+
+    pub const fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// End byte of a map, for the normal or the alternative format.
+    pub const fn end(alt_format: bool) -> Self {
+        if alt_format {
+            BinType::EndAlt
+        } else {
+            BinType::End
+        }
+    }
+}
 
+pub(crate) const MAGIC_24: u32 = 0x07_56_44_24;
+pub(crate) const MAGIC_26: u32 = 0x07_56_44_26;
 pub(crate) const MAGIC_27: u32 = 0x07_56_44_27;
 pub(crate) const MAGIC_28: u32 = 0x07_56_44_28;
 pub(crate) const MAGIC_29: u32 = 0x07_56_44_29;
 
 pub(crate) const PKG_MAGIC_27: u32 = 0x06_56_55_27;
 pub(crate) const PKG_MAGIC_28: u32 = 0x06_56_55_28;
+/// `VBKV` in little endian.
+pub(crate) const VBKV_MAGIC: u32 = 0x56_4B_42_56;
 
-#[derive(Clone, Default)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "cache", derive(bincode::Encode, bincode::Decode))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct SHA1([u8; 20]);
 
 impl SHA1 {
-    pub fn new(data: [u8; 20]) -> Self {
+    pub const fn new(data: [u8; 20]) -> Self {
         SHA1(data)
     }
 
+    /// The all-zero hash, used where a file has no checksum.
+    pub const fn zero() -> Self {
+        SHA1([0; 20])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 20]
+    }
+
     pub fn as_bytes(&self) -> &[u8; 20] {
         &self.0
     }
 }
 
+/// Error of parsing a [`SHA1`] from a string that isn't 40 hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseSha1Error;
+
+impl core::fmt::Display for ParseSha1Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "SHA-1 must be 40 hex digits")
+    }
+}
+
+impl core::error::Error for ParseSha1Error {}
+
+impl core::str::FromStr for SHA1 {
+    type Err = ParseSha1Error;
+
+    /// Parse 40 hex digits, in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 40 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseSha1Error);
+        }
+        let mut data = [0; 20];
+        for (i, c) in s.as_bytes().chunks(2).enumerate() {
+            // The string is ASCII, so every chunk is valid UTF-8
+            let c = core::str::from_utf8(c).map_err(|_| ParseSha1Error)?;
+            data[i] = u8::from_str_radix(c, 16).map_err(|_| ParseSha1Error)?;
+        }
+        Ok(SHA1(data))
+    }
+}
+
+/// Formats as 40 lowercase hex digits.
+impl core::fmt::Display for SHA1 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
 impl Deref for SHA1 {
     type Target = [u8; 20];
 
@@ -49,7 +154,7 @@ impl Serialize for SHA1 {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&format!("{:02x?}", self))
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -60,28 +165,27 @@ impl<'de> Deserialize<'de> for SHA1 {
         D: serde::Deserializer<'de>,
     {
         let s: String = Deserialize::deserialize(deserializer)?;
-        let mut data = [0; 20];
-        for (i, c) in s.as_bytes().chunks(2).enumerate() {
-            data[i] = u8::from_str_radix(std::str::from_utf8(c).unwrap(), 16)
-                .map_err(serde::de::Error::custom)?;
-        }
-
-        Ok(SHA1(data))
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
-impl std::fmt::Debug for SHA1 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let merged = self
-            .0
-            .iter()
-            .fold(String::new(), |acc, x| acc + &format!("{:02x}", x));
-        write!(f, "{}", merged)
+impl core::fmt::Debug for SHA1 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cache", derive(bincode::Encode, bincode::Decode))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum AppInfoVersion {
+    /// Each app is split in sections, without an access token or checksums.
+    V24,
+    /// Apps have an access token, but no checksums.
+    V26,
     V27,
     V28,
     V29,
@@ -113,9 +217,12 @@ impl TryInto<AppInfoVersion> for u32 {
 
     fn try_into(self) -> Result<AppInfoVersion, VdfrError> {
         match self {
+            MAGIC_24 => Ok(AppInfoVersion::V24),
+            MAGIC_26 => Ok(AppInfoVersion::V26),
             MAGIC_27 => Ok(AppInfoVersion::V27),
             MAGIC_28 => Ok(AppInfoVersion::V28),
             MAGIC_29 => Ok(AppInfoVersion::V29),
+            _ if self >> 8 == MAGIC_29 >> 8 => Err(VdfrError::UnsupportedVersion(self)),
             _ => Err(VdfrError::UnknownMagic(self)),
         }
     }
@@ -124,6 +231,8 @@ impl TryInto<AppInfoVersion> for u32 {
 impl From<AppInfoVersion> for u32 {
     fn from(v: AppInfoVersion) -> u32 {
         match v {
+            AppInfoVersion::V24 => MAGIC_24,
+            AppInfoVersion::V26 => MAGIC_26,
             AppInfoVersion::V27 => MAGIC_27,
             AppInfoVersion::V28 => MAGIC_28,
             AppInfoVersion::V29 => MAGIC_29,
@@ -131,9 +240,29 @@ impl From<AppInfoVersion> for u32 {
     }
 }
 
-impl std::fmt::Display for AppInfoVersion {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl AppInfoVersion {
+    /// Size of the header of an app, from the app ID up to the key-values.
+    pub(crate) fn app_header_size(&self) -> usize {
+        // id + size + state + last_update + change_number
+        let fixed = 4 * 5;
+        let access_token = if *self == AppInfoVersion::V24 { 0 } else { 8 };
+        let checksum_txt = match self {
+            AppInfoVersion::V24 | AppInfoVersion::V26 => 0,
+            _ => 20,
+        };
+        let checksum_bin = match self {
+            AppInfoVersion::V24 | AppInfoVersion::V26 | AppInfoVersion::V27 => 0,
+            _ => 20,
+        };
+        fixed + access_token + checksum_txt + checksum_bin
+    }
+}
+
+impl core::fmt::Display for AppInfoVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
+            AppInfoVersion::V24 => write!(f, "v24"),
+            AppInfoVersion::V26 => write!(f, "v26"),
             AppInfoVersion::V27 => write!(f, "v27"),
             AppInfoVersion::V28 => write!(f, "v28"),
             AppInfoVersion::V29 => write!(f, "v29"),
@@ -147,6 +276,16 @@ pub enum PkgInfoVersion {
     V28,
 }
 
+impl PkgInfoVersion {
+    /// Whether the packages carry a PICS access token, added in v28.
+    pub fn has_token(&self) -> bool {
+        match self {
+            PkgInfoVersion::V27 => false,
+            PkgInfoVersion::V28 => true,
+        }
+    }
+}
+
 #[cfg(feature = "serde")]
 impl Serialize for PkgInfoVersion {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -175,6 +314,7 @@ impl TryInto<PkgInfoVersion> for u32 {
         match self {
             PKG_MAGIC_27 => Ok(PkgInfoVersion::V27),
             PKG_MAGIC_28 => Ok(PkgInfoVersion::V28),
+            _ if self >> 8 == PKG_MAGIC_28 >> 8 => Err(VdfrError::UnsupportedVersion(self)),
             _ => Err(VdfrError::UnknownMagic(self)),
         }
     }
@@ -189,8 +329,8 @@ impl From<PkgInfoVersion> for u32 {
     }
 }
 
-impl std::fmt::Display for PkgInfoVersion {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for PkgInfoVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
             PkgInfoVersion::V27 => write!(f, "v27"),
             PkgInfoVersion::V28 => write!(f, "v28"),
@@ -199,117 +339,541 @@ impl std::fmt::Display for PkgInfoVersion {
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum VdfrError {
     InvalidType(u8),
+    #[cfg(feature = "std")]
     ReadError(std::io::Error),
     UnknownMagic(u32),
+    /// The magic is of a known file type, but of a version that isn't supported.
+    UnsupportedVersion(u32),
     NomError(String),
-    InvalidStringIndex(usize, usize),
+    /// A key refers past the end of the v29 string pool (index, pool size).
+    StringPoolIndexOutOfBounds(usize, usize),
+    /// Key-values nested deeper than the `max_depth` limit.
+    DepthLimitExceeded(usize),
+    LimitExceeded(&'static str, usize),
+    InvalidUtf8,
+    /// The data ends in the middle of a value.
+    Truncated,
+    InvalidOffset(i64),
+    TrailingBytes(usize),
+    MissingTerminator,
+    StaleIndex,
+    InvalidCache(String),
+    InvalidText(usize, &'static str),
+    ChecksumMismatch(u32, u32),
+    /// A JSON value without a key-values equivalent, with the key path to it.
+    InvalidJson(String),
+    /// A patch that is malformed or doesn't fit the file it's applied to.
+    InvalidPatch(&'static str),
 }
 
-impl std::error::Error for VdfrError {}
+impl core::error::Error for VdfrError {}
 
-impl std::fmt::Display for VdfrError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for VdfrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
             VdfrError::InvalidType(t) => write!(f, "Invalid type {:#x}", t),
             VdfrError::UnknownMagic(v) => write!(f, "Unknown magic {:#x}", v),
-            VdfrError::InvalidStringIndex(c, t) => {
-                write!(f, "Invalid string index {} (total {})", c, t)
+            VdfrError::UnsupportedVersion(v) => write!(f, "Unsupported version {:#x}", v),
+            VdfrError::StringPoolIndexOutOfBounds(c, t) => {
+                write!(f, "String pool index {} out of bounds (total {})", c, t)
+            }
+            VdfrError::DepthLimitExceeded(max) => {
+                write!(f, "Limit max_depth exceeded (max {})", max)
             }
+            VdfrError::InvalidUtf8 => write!(f, "Invalid UTF-8 string"),
+            VdfrError::Truncated => write!(f, "Unexpected end of data"),
+            VdfrError::LimitExceeded(limit, max) => {
+                write!(f, "Limit {} exceeded (max {})", limit, max)
+            }
+            VdfrError::InvalidOffset(o) => write!(f, "Invalid offset {}", o),
+            VdfrError::TrailingBytes(n) => write!(f, "Unexpected {} trailing bytes", n),
+            VdfrError::MissingTerminator => write!(f, "Missing end terminator"),
+            VdfrError::StaleIndex => write!(f, "Index does not match the app info data"),
+            VdfrError::InvalidCache(e) => write!(f, "Invalid cache: {}", e),
+            VdfrError::ChecksumMismatch(expected, actual) => write!(
+                f,
+                "Checksum mismatch (expected {:#010x}, got {:#010x})",
+                expected, actual
+            ),
+            VdfrError::InvalidJson(path) => {
+                write!(f, "JSON null at {:?} has no key-values equivalent", path)
+            }
+            VdfrError::InvalidPatch(e) => write!(f, "Invalid patch: {}", e),
+            VdfrError::InvalidText(line, e) => {
+                write!(f, "Invalid text key-values at line {}: {}", line, e)
+            }
+            #[cfg(feature = "std")]
             VdfrError::ReadError(e) => e.fmt(f),
             VdfrError::NomError(e) => write!(f, "Nom error: {}", e),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for VdfrError {
     fn from(e: std::io::Error) -> Self {
-        VdfrError::ReadError(e)
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            VdfrError::Truncated
+        } else {
+            VdfrError::ReadError(e)
+        }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+// Value is recursive, so the bounds of the nested maps and arrays are given by hand.
+// Keys are shared `Arc`s, which need the sharing and pooling traits.
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(serialize_bounds(
+        __S: rkyv::ser::Writer + rkyv::ser::Allocator + rkyv::ser::Sharing,
+        __S::Error: rkyv::rancor::Source,
+    )),
+    rkyv(deserialize_bounds(
+        __D: rkyv::de::Pooling,
+        __D::Error: rkyv::rancor::Source,
+    )),
+    rkyv(bytecheck(bounds(
+        __C: rkyv::validation::ArchiveContext + rkyv::validation::SharedContext,
+        __C::Error: rkyv::rancor::Source,
+    )))
+)]
 pub enum Value {
-    StringType(String),
-    WideStringType(String),
+    /// Literals are stored without allocating, like `Value::StringType("Game".into())`.
+    StringType(#[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::AsOwned))] Cow<'static, str>),
+    WideStringType(WideString),
+    /// String that isn't valid UTF-8, kept as its original bytes with [`StringDecoding::Raw`].
+    BytesType(Vec<u8>),
     Int32Type(i32),
-    PointerType(i32),
-    ColorType(i32),
+    PointerType(Pointer),
+    ColorType(Color),
     UInt64Type(u64),
     Int64Type(i64),
     Float32Type(f32),
-    KeyValueType(KeyValues),
-    ArrayType(Vec<Value>),
+    KeyValueType(#[cfg_attr(feature = "rkyv", rkyv(omit_bounds))] KeyValues),
+    ArrayType(#[cfg_attr(feature = "rkyv", rkyv(omit_bounds))] Vec<Value>),
+    /// Value of a type byte this crate doesn't know, with
+    /// [`KeyValueOptions::tolerate_unknown_types`].
+    ///
+    /// Its size can't be known, so `raw` holds the rest of the data after the key, without
+    /// the end bytes of the sections around it, and is written back as-is.
+    ///
+    /// That rest includes every value after it: its siblings and the later sections of
+    /// the tree are in `raw` instead of being parsed.
+    UnknownType {
+        type_id: u8,
+        raw: Vec<u8>,
+    },
 }
 
 impl Value {
+    /// Type of the value, arrays are written as maps and unknown types have none.
+    pub fn bin_type(&self) -> Option<BinType> {
+        Some(match self {
+            Value::StringType(_) => BinType::String,
+            Value::WideStringType(_) => BinType::WideString,
+            Value::BytesType(_) => BinType::String,
+            Value::Int32Type(_) => BinType::Int32,
+            Value::PointerType(_) => BinType::Pointer,
+            Value::ColorType(_) => BinType::Color,
+            Value::UInt64Type(_) => BinType::UInt64,
+            Value::Int64Type(_) => BinType::Int64,
+            Value::Float32Type(_) => BinType::Float32,
+            Value::KeyValueType(_) | Value::ArrayType(_) => BinType::Map,
+            Value::UnknownType { .. } => return None,
+        })
+    }
+
+    /// Type byte of the value, as written in binary key-values.
+    pub fn type_byte(&self) -> u8 {
+        match self {
+            Value::UnknownType { type_id, .. } => *type_id,
+            known => known
+                .bin_type()
+                .expect("known types have a bin type")
+                .to_u8(),
+        }
+    }
+
     #[cfg(feature = "writer")]
     pub(crate) fn save_bin<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&[self.type_byte()])
+    }
+
+    pub fn kind(&self) -> ValueKind {
         match self {
-            Value::StringType(_) => {
-                writer.write_all(&[BIN_STRING])?;
-            }
-            Value::WideStringType(_) => {
-                writer.write_all(&[BIN_WIDESTRING])?;
-            }
-            Value::Int32Type(_) => {
-                writer.write_all(&[BIN_INT32])?;
-            }
-            Value::PointerType(_) => {
-                writer.write_all(&[BIN_POINTER])?;
-            }
-            Value::ColorType(_) => {
-                writer.write_all(&[BIN_COLOR])?;
-            }
-            Value::UInt64Type(_) => {
-                writer.write_all(&[BIN_UINT64])?;
-            }
-            Value::Int64Type(_) => {
-                writer.write_all(&[BIN_INT64])?;
-            }
-            Value::Float32Type(_) => {
-                writer.write_all(&[BIN_FLOAT32])?;
-            }
-            Value::KeyValueType(_) => {
-                writer.write_all(&[BIN_KV])?;
-            }
-            Value::ArrayType(_) => {
-                // Array type is KeyValueType
-                writer.write_all(&[BIN_KV])?;
-            }
+            Value::StringType(_) => ValueKind::String,
+            Value::WideStringType(_) => ValueKind::WideString,
+            Value::BytesType(_) => ValueKind::Bytes,
+            Value::Int32Type(_) => ValueKind::Int32,
+            Value::PointerType(_) => ValueKind::Pointer,
+            Value::ColorType(_) => ValueKind::Color,
+            Value::UInt64Type(_) => ValueKind::UInt64,
+            Value::Int64Type(_) => ValueKind::Int64,
+            Value::Float32Type(_) => ValueKind::Float32,
+            Value::KeyValueType(_) => ValueKind::Map,
+            Value::ArrayType(_) => ValueKind::Array,
+            Value::UnknownType { .. } => ValueKind::Unknown,
         }
+    }
 
-        Ok(())
+    /// Short name of the type of the value, see [`ValueKind::name`].
+    pub fn kind_name(&self) -> &'static str {
+        self.kind().name()
     }
 
+    /// Decode a color value into its channels.
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            Value::ColorType(color) => Some(*color),
+            _ => None,
+        }
+    }
+
+    /// Convert the value to a serde JSON value, with the representation of the options.
     #[cfg(feature = "serde")]
-    fn as_serde_json_value(&self) -> serde_json::Value {
+    pub fn to_json_with_options(&self, options: &JsonOptions) -> serde_json::Value {
         match self {
-            Value::StringType(s) | Value::WideStringType(s) => serde_json::Value::String(s.clone()),
-            Value::Int32Type(i) | Value::PointerType(i) | Value::ColorType(i) => {
-                serde_json::Value::Number(serde_json::Number::from(*i))
-            }
+            Value::StringType(s) => serde_json::Value::String(s.to_string()),
+            Value::WideStringType(s) => serde_json::Value::String(s.to_string_lossy()),
+            Value::BytesType(b) => serde_json::Value::String(String::from_utf8_lossy(b).into()),
+            Value::PointerType(p) if options.raw_integers => serde_json::Value::from(p.0),
+            Value::ColorType(c) if options.raw_integers => serde_json::Value::from(c.to_i32()),
+            Value::PointerType(p) => serde_json::Value::String(p.to_string()),
+            Value::ColorType(c) => serde_json::Value::String(c.to_string()),
+            Value::Int32Type(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
             Value::UInt64Type(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
             Value::Int64Type(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
-            Value::Float32Type(i) => {
-                serde_json::Value::Number(serde_json::Number::from_f64(f64::from(*i)).unwrap())
-            }
+            // NaN and infinity have no JSON representation, serde_json also uses null for them
+            Value::Float32Type(i) => serde_json::Number::from_f64(f64::from(*i))
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
             Value::KeyValueType(kv) => {
                 let map: serde_json::Map<String, serde_json::Value> = kv
                     .iter()
-                    .map(|(k, v)| (k.clone(), v.as_serde_json_value()))
+                    .map(|(k, v)| (String::from(&**k), v.to_json_with_options(options)))
                     .collect();
                 serde_json::Value::Object(map)
             }
             Value::ArrayType(array) => {
-                let veca = array.iter().map(|v| v.as_serde_json_value()).collect();
+                let veca = array
+                    .iter()
+                    .map(|v| v.to_json_with_options(options))
+                    .collect();
                 serde_json::Value::Array(veca)
             }
+            Value::UnknownType { type_id, raw } => serde_json::json!({
+                "type_id": type_id,
+                "raw": raw,
+            }),
         }
     }
 }
 
+/// Byte order mark, as the first code unit of a wide string.
+const BOM: u16 = 0xFEFF;
+
+/// A wide string, as the UTF-16 code units of the file.
+///
+/// Unpaired surrogates and a leading byte order mark are kept, so the string is written
+/// back with the bytes it was parsed from. Only the conversions to [`String`] are lossy.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct WideString {
+    units: Vec<u16>,
+    big_endian: bool,
+}
+
+impl WideString {
+    /// Wide string from its code units, written in big-endian order when set.
+    pub fn from_units(units: Vec<u16>, big_endian: bool) -> Self {
+        WideString { units, big_endian }
+    }
+
+    /// Wide string from code units read in little-endian order, a swapped
+    /// byte order mark switches the string to big-endian.
+    pub(crate) fn from_le_units(mut units: Vec<u16>) -> Self {
+        let big_endian = units.first() == Some(&BOM.swap_bytes());
+        if big_endian {
+            units.iter_mut().for_each(|unit| *unit = unit.swap_bytes());
+        }
+        WideString { units, big_endian }
+    }
+
+    /// Code units, including the byte order mark if the file has one.
+    pub fn units(&self) -> &[u16] {
+        &self.units
+    }
+
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Encoded bytes of the string, without the terminator.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.units
+            .iter()
+            .flat_map(|unit| match self.big_endian {
+                true => unit.to_be_bytes(),
+                false => unit.to_le_bytes(),
+            })
+            .collect()
+    }
+
+    /// The text without the byte order mark, unpaired surrogates become `U+FFFD`.
+    pub fn to_string_lossy(&self) -> String {
+        let units = self.units.strip_prefix(&[BOM]).unwrap_or(&self.units);
+        String::from_utf16_lossy(units)
+    }
+}
+
+impl From<&str> for WideString {
+    fn from(value: &str) -> Self {
+        WideString::from_units(value.encode_utf16().collect(), false)
+    }
+}
+
+impl From<String> for WideString {
+    fn from(value: String) -> Self {
+        WideString::from(value.as_str())
+    }
+}
+
+impl core::fmt::Display for WideString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+impl core::fmt::Debug for WideString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.to_string_lossy(), f)
+    }
+}
+
+/// Type of a [`Value`], without its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValueKind {
+    String,
+    WideString,
+    Bytes,
+    Int32,
+    Pointer,
+    Color,
+    UInt64,
+    Int64,
+    Float32,
+    Map,
+    Array,
+    Unknown,
+}
+
+impl ValueKind {
+    /// Short lowercase name, like `string`, `int32` or `section` for maps.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::String => "string",
+            ValueKind::WideString => "wstring",
+            ValueKind::Bytes => "bytes",
+            ValueKind::Int32 => "int32",
+            ValueKind::Pointer => "pointer",
+            ValueKind::Color => "color",
+            ValueKind::UInt64 => "uint64",
+            ValueKind::Int64 => "int64",
+            ValueKind::Float32 => "float32",
+            ValueKind::Map => "section",
+            ValueKind::Array => "array",
+            ValueKind::Unknown => "unknown",
+        }
+    }
+}
+
+impl core::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A pointer value, an address in the memory of the program that wrote the file.
+///
+/// It means nothing to any other process, Steam doesn't write them in its caches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Pointer(pub i32);
+
+impl From<i32> for Pointer {
+    fn from(value: i32) -> Self {
+        Pointer(value)
+    }
+}
+
+impl From<Pointer> for i32 {
+    fn from(pointer: Pointer) -> Self {
+        pointer.0
+    }
+}
+
+/// Formats as `*123`, to tell it apart from a plain integer.
+impl core::fmt::Display for Pointer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "*{}", self.0)
+    }
+}
+
+/// Serialized as a `"*123"` string, like it's displayed.
+#[cfg(feature = "serde")]
+impl Serialize for Pointer {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Channels of a color value, stored as the bytes of the `i32` in little-endian order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub fn from_i32(value: i32) -> Self {
+        let [r, g, b, a] = value.to_le_bytes();
+        Color { r, g, b, a }
+    }
+
+    pub fn to_i32(self) -> i32 {
+        i32::from_le_bytes([self.r, self.g, self.b, self.a])
+    }
+}
+
+impl From<i32> for Color {
+    fn from(value: i32) -> Self {
+        Color::from_i32(value)
+    }
+}
+
+impl From<Color> for i32 {
+    fn from(color: Color) -> Self {
+        color.to_i32()
+    }
+}
+
+/// Serialized as a `"#RRGGBBAA"` string, like it's displayed.
+#[cfg(feature = "serde")]
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Formats as `#RRGGBBAA`, like Source tools display colors.
+impl core::fmt::Display for Color {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+/// Representation options of [`Value::to_json_with_options`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Write pointers and colors as their raw `i32`, like plain integers, instead of
+    /// `"*123"` and `"#RRGGBBAA"` strings.
+    pub raw_integers: bool,
+}
+
+/// Same as [`Value::to_json_with_options`] with the default options.
+#[cfg(feature = "serde")]
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        value.to_json_with_options(&JsonOptions::default())
+    }
+}
+
+/// Convert a JSON value, numbers are mapped to the smallest fitting type:
+///
+/// - integers that fit in an `i32` become [`Value::Int32Type`]
+/// - other non-negative integers become [`Value::UInt64Type`], negative ones [`Value::Int64Type`]
+/// - floats become [`Value::Float32Type`], losing precision beyond `f32`
+/// - booleans become [`Value::Int32Type`] `1` or `0`, like Steam stores flags
+///
+/// Objects become maps and arrays become [`Value::ArrayType`], `null` has no equivalent
+/// and fails with [`VdfrError::InvalidJson`].
+#[cfg(feature = "serde")]
+impl TryFrom<&serde_json::Value> for Value {
+    type Error = VdfrError;
+
+    fn try_from(value: &serde_json::Value) -> Result<Self, VdfrError> {
+        value_from_json(value, &mut Vec::new())
+    }
+}
+
+#[cfg(feature = "serde")]
+fn value_from_json(value: &serde_json::Value, path: &mut Vec<String>) -> Result<Value, VdfrError> {
+    Ok(match value {
+        serde_json::Value::Null => return Err(VdfrError::InvalidJson(path.join("/"))),
+        serde_json::Value::Bool(b) => Value::Int32Type(i32::from(*b)),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                match i32::try_from(i) {
+                    Ok(i) => Value::Int32Type(i),
+                    Err(_) if i >= 0 => Value::UInt64Type(i as u64),
+                    Err(_) => Value::Int64Type(i),
+                }
+            } else if let Some(u) = n.as_u64() {
+                Value::UInt64Type(u)
+            } else {
+                Value::Float32Type(n.as_f64().unwrap_or_default() as f32)
+            }
+        }
+        serde_json::Value::String(s) => Value::StringType(s.clone().into()),
+        serde_json::Value::Array(items) => {
+            let mut array = Vec::with_capacity(items.len());
+            for (idx, item) in items.iter().enumerate() {
+                path.push(idx.to_string());
+                array.push(value_from_json(item, path)?);
+                path.pop();
+            }
+            Value::ArrayType(array)
+        }
+        serde_json::Value::Object(object) => {
+            let mut key_values = KeyValues::new();
+            for (key, item) in object {
+                path.push(key.clone());
+                key_values.insert(Key::from(key.as_str()), value_from_json(item, path)?);
+                path.pop();
+            }
+            Value::KeyValueType(key_values)
+        }
+    })
+}
+
 #[cfg(feature = "serde")]
 impl serde::Serialize for Value {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -317,15 +881,23 @@ impl serde::Serialize for Value {
         S: serde::Serializer,
     {
         match self {
-            Value::StringType(s) | Value::WideStringType(s) => serializer.serialize_str(s),
-            Value::Int32Type(i) | Value::PointerType(i) | Value::ColorType(i) => {
-                serializer.serialize_i32(*i)
-            }
+            Value::StringType(s) => serializer.serialize_str(s),
+            Value::WideStringType(s) => serializer.serialize_str(&s.to_string_lossy()),
+            Value::BytesType(b) => serializer.serialize_str(&String::from_utf8_lossy(b)),
+            Value::Int32Type(i) => serializer.serialize_i32(*i),
+            Value::PointerType(p) => p.serialize(serializer),
+            Value::ColorType(c) => c.serialize(serializer),
             Value::UInt64Type(i) => serializer.serialize_u64(*i),
             Value::Int64Type(i) => serializer.serialize_i64(*i),
             Value::Float32Type(i) => serializer.serialize_f32(*i),
             Value::KeyValueType(kv) => kv.serialize(serializer),
             Value::ArrayType(array) => array.serialize(serializer),
+            Value::UnknownType { type_id, raw } => {
+                let mut state = serializer.serialize_struct("UnknownType", 2)?;
+                state.serialize_field("type_id", type_id)?;
+                state.serialize_field("raw", raw)?;
+                state.end()
+            }
         }
     }
 }
@@ -343,14 +915,15 @@ fn fmt_string(s: &str) -> String {
     escaped
 }
 
-impl std::fmt::Debug for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Value {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Value::StringType(s) => write!(f, "\"{}\"", fmt_string(s)),
-            Value::WideStringType(s) => write!(f, "W\"{}\"", fmt_string(s)),
+            Value::WideStringType(s) => write!(f, "W\"{}\"", fmt_string(&s.to_string_lossy())),
+            Value::BytesType(b) => write!(f, "b\"{}\"", b.escape_ascii()),
             Value::Int32Type(i) => write!(f, "{}", i),
-            Value::PointerType(i) => write!(f, "\"*{}\"", i),
-            Value::ColorType(i) => write!(f, "{}", i),
+            Value::PointerType(p) => write!(f, "\"{}\"", p),
+            Value::ColorType(c) => write!(f, "{}", c.to_i32()),
             Value::UInt64Type(i) => write!(f, "{}", i),
             Value::Int64Type(i) => write!(f, "{}", i),
             Value::Float32Type(i) => write!(f, "{}", i),
@@ -365,20 +938,291 @@ impl std::fmt::Debug for Value {
                 }
                 write!(f, "]")
             }
+            Value::UnknownType { type_id, raw } => {
+                write!(f, "unknown({:#04x}, b\"{}\")", type_id, raw.escape_ascii())
+            }
         }
     }
 }
 
-pub type KeyValues = BTreeMap<String, Value>;
+/// A key in [`KeyValues`].
+///
+/// Keys are reference counted, so repeated keys can share the same storage
+/// (see [`KeyValueOptions::intern_keys`]).
+pub type Key = Arc<str>;
+
+/// Map of the parsed data, sorted by key.
+#[cfg(not(feature = "preserve_order"))]
+pub type Map<K, V> = BTreeMap<K, V>;
+/// Map of the parsed data, in the order it was parsed or inserted.
+#[cfg(feature = "preserve_order")]
+pub type Map<K, V> = indexmap::IndexMap<K, V>;
+
+pub type KeyValues = Map<Key, Value>;
+
+/// Metrics of a parse, from [`crate::parser::parse_app_info_with_stats`] and
+/// [`crate::parser::parse_package_info_with_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    pub sections: usize,
+    pub strings: usize,
+    pub wide_strings: usize,
+    pub int32s: usize,
+    pub float32s: usize,
+    pub pointers: usize,
+    pub colors: usize,
+    pub uint64s: usize,
+    pub int64s: usize,
+    /// Values of unknown type bytes, with [`KeyValueOptions::tolerate_unknown_types`].
+    pub unknowns: usize,
+    /// Total length of the string and wide string values, in bytes of UTF-8.
+    pub string_bytes: usize,
+    /// Deepest nesting of key-value nodes, the root node is depth 1.
+    pub max_depth: usize,
+    /// Keys read, with `intern_keys` the ones already in the key pool count as hits.
+    pub key_lookups: usize,
+    pub key_hits: usize,
+}
+
+impl ParseStats {
+    /// Total amount of values of every type, sections included.
+    pub fn values(&self) -> usize {
+        self.sections
+            + self.strings
+            + self.wide_strings
+            + self.int32s
+            + self.float32s
+            + self.pointers
+            + self.colors
+            + self.uint64s
+            + self.int64s
+            + self.unknowns
+    }
+
+    /// Share of keys that were served from the key pool, 0 when nothing was read.
+    pub fn pool_hit_rate(&self) -> f64 {
+        if self.key_lookups == 0 {
+            0.0
+        } else {
+            self.key_hits as f64 / self.key_lookups as f64
+        }
+    }
+
+    pub(crate) fn record(&mut self, value: &Value) {
+        match value {
+            Value::KeyValueType(_) | Value::ArrayType(_) => self.sections += 1,
+            Value::StringType(value) => {
+                self.strings += 1;
+                self.string_bytes += value.len();
+            }
+            Value::WideStringType(value) => {
+                self.wide_strings += 1;
+                self.string_bytes += value.to_string_lossy().len();
+            }
+            Value::BytesType(value) => {
+                self.strings += 1;
+                self.string_bytes += value.len();
+            }
+            Value::Int32Type(_) => self.int32s += 1,
+            Value::Float32Type(_) => self.float32s += 1,
+            Value::PointerType(_) => self.pointers += 1,
+            Value::ColorType(_) => self.colors += 1,
+            Value::UInt64Type(_) => self.uint64s += 1,
+            Value::Int64Type(_) => self.int64s += 1,
+            Value::UnknownType { .. } => self.unknowns += 1,
+        }
+    }
+}
+
+/// Hands out shared [`Key`]s for repeated key strings.
+#[derive(Debug, Default)]
+pub(crate) struct KeyInterner {
+    enabled: bool,
+    keys: BTreeSet<Key>,
+    /// Keys of the v29 string pool by index, created on their first use and then shared.
+    pool: Vec<Option<Key>>,
+    /// Metrics of the parse when requested, kept here since the interner goes through every parser.
+    pub(crate) stats: Option<ParseStats>,
+}
+
+impl KeyInterner {
+    pub(crate) fn new(enabled: bool) -> Self {
+        KeyInterner {
+            enabled,
+            keys: BTreeSet::new(),
+            pool: Vec::new(),
+            stats: None,
+        }
+    }
+
+    pub(crate) fn with_stats(enabled: bool) -> Self {
+        KeyInterner {
+            stats: Some(ParseStats::default()),
+            ..KeyInterner::new(enabled)
+        }
+    }
+
+    pub(crate) fn intern(&mut self, key: &str) -> Key {
+        if let Some(stats) = self.stats.as_mut() {
+            stats.key_lookups += 1;
+        }
+        if !self.enabled {
+            return Key::from(key);
+        }
+
+        if let Some(existing) = self.keys.get(key) {
+            if let Some(stats) = self.stats.as_mut() {
+                stats.key_hits += 1;
+            }
+            return existing.clone();
+        }
+        let key = Key::from(key);
+        self.keys.insert(key.clone());
+        key
+    }
+
+    /// The key of the string pool entry at `index`, `None` when it's out of bounds.
+    ///
+    /// Every occurrence of an entry shares the same key, with or without interning.
+    pub(crate) fn pool_key(&mut self, pool: &[String], index: usize) -> Option<Key> {
+        let entry = pool.get(index)?;
+        if self.pool.len() != pool.len() {
+            self.pool = alloc::vec![None; pool.len()];
+        }
+        if let Some(key) = &self.pool[index] {
+            if let Some(stats) = self.stats.as_mut() {
+                stats.key_lookups += 1;
+                stats.key_hits += 1;
+            }
+            return Some(key.clone());
+        }
+        let key = self.intern(entry);
+        self.pool[index] = Some(key.clone());
+        Some(key)
+    }
+}
+
+/// Limits enforced while parsing, to guard against hostile or corrupted input.
+///
+/// The default only limits the nesting depth (to [`ParseLimits::DEFAULT_MAX_DEPTH`]),
+/// since deeper trees would overflow the stack of the recursive parsers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum nesting depth of key-value nodes, the root node is depth 1.
+    pub max_depth: usize,
+    /// Maximum length of a single key or string value, in bytes.
+    pub max_string_len: usize,
+    /// Maximum amount of key-value pairs in a single key-value tree.
+    pub max_nodes: usize,
+    /// Maximum amount of entries in the v29 string pool.
+    pub max_pool_entries: usize,
+}
+
+impl ParseLimits {
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_string_len: usize::MAX,
+            max_nodes: usize::MAX,
+            max_pool_entries: usize::MAX,
+        }
+    }
+}
+
+/// How strings that aren't valid UTF-8 are decoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringDecoding {
+    /// Fail with [`VdfrError::InvalidUtf8`].
+    #[default]
+    Strict,
+    /// Replace the invalid sequences with U+FFFD.
+    Lossy,
+    /// Keep string values as [`Value::BytesType`], keys are decoded like [`StringDecoding::Lossy`].
+    Raw,
+}
+
+impl StringDecoding {
+    pub(crate) fn decode_key(self, bytes: &[u8]) -> Result<Cow<'_, str>, VdfrError> {
+        match self {
+            StringDecoding::Strict => core::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|_| VdfrError::InvalidUtf8),
+            StringDecoding::Lossy | StringDecoding::Raw => Ok(String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    pub(crate) fn decode_value(self, bytes: Vec<u8>) -> Result<Value, VdfrError> {
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(Value::StringType(s.into())),
+            Err(e) => match self {
+                StringDecoding::Strict => Err(VdfrError::InvalidUtf8),
+                StringDecoding::Lossy => Ok(Value::StringType(
+                    String::from_utf8_lossy(e.as_bytes()).into_owned().into(),
+                )),
+                StringDecoding::Raw => Ok(Value::BytesType(e.into_bytes())),
+            },
+        }
+    }
+}
 
 /// Options for reading key-value data.
 #[derive(Debug, Clone, Default)]
 pub struct KeyValueOptions {
     pub string_pool: Vec<String>,
     pub alt_format: bool,
+    pub limits: ParseLimits,
+    /// Fail on leftover data after the parsed content, or a missing end terminator.
+    pub strict: bool,
+    /// Share the storage of identical keys within a parsed file, instead of
+    /// allocating every occurrence separately.
+    ///
+    /// Keys from the v29 string pool are shared either way.
+    pub intern_keys: bool,
+    /// Handling of strings that aren't valid UTF-8, strict by default.
+    pub string_decoding: StringDecoding,
+    /// Keep values of unknown type bytes as [`Value::UnknownType`] instead of failing,
+    /// only supported by [`crate::parser`].
+    ///
+    /// The rest of the key-values after an unknown value goes into its `raw` bytes, so the
+    /// values after it, in its section or the ones around it, are missing from the result.
+    /// In app info, the size of every app bounds its key-values, so the next app is still
+    /// parsed.
+    pub tolerate_unknown_types: bool,
+    /// Keep the bytes of every parsed app in [`App::raw`], only supported by [`crate::parser`].
+    pub keep_raw: bool,
+    /// Keep sections keyed `0`, `1`, ... as sections instead of folding them into arrays
+    /// while parsing, only supported by [`crate::parser`].
+    pub keep_sequences: bool,
+}
+
+impl KeyValueOptions {
+    /// Check the end of a parsed section, in strict mode anything but a clean end is an error.
+    pub(crate) fn check_section_end(
+        &self,
+        terminated: bool,
+        trailing: usize,
+    ) -> Result<(), VdfrError> {
+        if !self.strict {
+            Ok(())
+        } else if !terminated {
+            Err(VdfrError::MissingTerminator)
+        } else if trailing > 0 {
+            Err(VdfrError::TrailingBytes(trailing))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct App {
     pub id: u32,
     pub size: u32,
@@ -389,6 +1233,11 @@ pub struct App {
     pub checksum_bin: Option<SHA1>,
     pub change_number: u32,
     pub key_values: KeyValues,
+    /// Bytes of the app as parsed, with [`KeyValueOptions::keep_raw`].
+    ///
+    /// Not kept in the cache or archives, and stale once the app is changed.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    pub raw: Option<Vec<u8>>,
 }
 
 #[cfg(feature = "serde")]
@@ -411,8 +1260,8 @@ impl serde::Serialize for App {
     }
 }
 
-impl std::fmt::Debug for App {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for App {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("App")
             .field("id", &self.id)
             .field("size", &self.size)
@@ -428,10 +1277,55 @@ impl std::fmt::Debug for App {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct AppInfo {
     pub version: AppInfoVersion,
     pub universe: u32,
-    pub apps: BTreeMap<u32, App>,
+    pub apps: Map<u32, App>,
+    /// Amount of bytes left unparsed after the apps (and string pool).
+    pub trailing_bytes: usize,
+}
+
+impl AppInfo {
+    /// Iterate over the apps accepted by the predicate, in app ID order.
+    pub fn find<F>(&self, mut predicate: F) -> impl Iterator<Item = &App>
+    where
+        F: FnMut(&App) -> bool,
+    {
+        self.apps.values().filter(move |app| predicate(app))
+    }
+
+    /// Iterate over the apps with the given `common/type`, like `Game` or `DLC`, ignoring case.
+    pub fn apps_of_type<'a>(&'a self, app_type: &'a str) -> impl Iterator<Item = &'a App> {
+        self.find(move |app| {
+            app.app_type()
+                .is_some_and(|t| t.eq_ignore_ascii_case(app_type))
+        })
+    }
+
+    pub fn games(&self) -> impl Iterator<Item = &App> {
+        self.apps_of_type("Game")
+    }
+
+    pub fn dlcs(&self) -> impl Iterator<Item = &App> {
+        self.apps_of_type("DLC")
+    }
+
+    pub fn tools(&self) -> impl Iterator<Item = &App> {
+        self.apps_of_type("Tool")
+    }
+
+    /// Remove the values matching any of the paths from every app, returning how many
+    /// were removed. See [`App::strip_paths`].
+    pub fn strip_paths(&mut self, paths: &[&str]) -> usize {
+        self.apps
+            .values_mut()
+            .map(|app| app.strip_paths(paths))
+            .sum()
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -453,34 +1347,160 @@ impl App {
         find_keys(&self.key_values, keys)
     }
 
+    /// The key-values of the app, below the `appinfo` section if it's wrapped in one.
+    pub(crate) fn sections(&self) -> &KeyValues {
+        match self.key_values.get("appinfo") {
+            Some(Value::KeyValueType(appinfo)) if self.key_values.len() == 1 => appinfo,
+            _ => &self.key_values,
+        }
+    }
+
+    fn sections_mut(&mut self) -> &mut KeyValues {
+        let wrapped = self.key_values.len() == 1
+            && matches!(self.key_values.get("appinfo"), Some(Value::KeyValueType(_)));
+        if !wrapped {
+            return &mut self.key_values;
+        }
+        match self.key_values.get_mut("appinfo") {
+            Some(Value::KeyValueType(appinfo)) => appinfo,
+            _ => unreachable!("checked above"),
+        }
+    }
+
+    /// Remove the values matching any of the `/` separated paths, like
+    /// `depots/*/encryptedmanifests`, returning how many were removed.
+    ///
+    /// Paths start below the `appinfo` section of apps wrapped in one. A `*` in a segment
+    /// matches any part of a key, like `developer_*`, and a `**` segment matches any number
+    /// of keys. The raw bytes of the app are dropped once something is removed.
+    pub fn strip_paths(&mut self, paths: &[&str]) -> usize {
+        let sections = self.sections_mut();
+        let mut value = Value::KeyValueType(core::mem::take(sections));
+        let mut removed = 0;
+        for path in paths {
+            let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            removed += strip_value(&mut value, &segments);
+        }
+        if let Value::KeyValueType(kv) = value {
+            *sections = kv;
+        }
+        if removed > 0 {
+            self.raw = None;
+        }
+        removed
+    }
+
+    /// The values matching a `/` separated path, like `depots/*/manifests/public`, with
+    /// their concrete paths like `depots/731/manifests/public`.
+    ///
+    /// Paths start below the `appinfo` section of apps wrapped in one, and match like
+    /// [`Value::get_glob`].
+    pub fn get_glob(&self, path_glob: &str) -> Vec<(String, &Value)> {
+        let segments: Vec<&str> = path_glob.split('/').filter(|s| !s.is_empty()).collect();
+        let mut matches = Vec::new();
+        glob_keyvalues(self.sections(), &segments, &mut String::new(), &mut matches);
+        matches
+    }
+
+    /// The `common/type` of the app, like `Game`, `DLC` or `Tool`.
+    pub fn app_type(&self) -> Option<&str> {
+        match find_keys(self.sections(), &["common", "type"]) {
+            Some(Value::StringType(app_type)) => Some(app_type),
+            _ => None,
+        }
+    }
+
     pub fn checksum_sha1_txt(&self) -> String {
-        format!("{:02x?}", self.checksum_txt)
+        self.checksum_txt.to_string()
     }
 
     pub fn checksum_sha1_bin(&self) -> Option<String> {
-        self.checksum_bin
-            .as_ref()
-            .map(|sha1| format!("{:02x?}", sha1))
+        self.checksum_bin.as_ref().map(|sha1| sha1.to_string())
+    }
+
+    /// The encoded app as parsed, from its ID to the end of its key-values, with
+    /// [`KeyValueOptions::keep_raw`].
+    ///
+    /// They can be written back as-is while the app is unchanged, in a file of the same
+    /// version (and the same string pool for v29).
+    pub fn raw_bytes(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
     }
 
     /// Convert the key-values to a serde JSON object.
     #[cfg(feature = "serde")]
     pub fn as_serde_keyvalues(&self) -> serde_json::Value {
+        self.as_serde_keyvalues_with_options(&JsonOptions::default())
+    }
+
+    /// Convert the key-values to a serde JSON object, with the representation of the options.
+    #[cfg(feature = "serde")]
+    pub fn as_serde_keyvalues_with_options(&self, options: &JsonOptions) -> serde_json::Value {
         let map: serde_json::Map<String, serde_json::Value> = self
             .key_values
             .iter()
-            .map(|(k, v)| (k.clone(), v.as_serde_json_value()))
+            .map(|(k, v)| (String::from(&**k), v.to_json_with_options(options)))
             .collect();
         serde_json::Value::Object(map)
     }
 }
 
+/// An app that was skipped by [`crate::parser::parse_app_info_resilient`].
+#[derive(Debug)]
+pub struct SkippedApp {
+    /// The app ID read from the header, or 0 if the header itself was unreadable.
+    pub id: u32,
+    /// Byte offset of the app header from the start of the file.
+    pub offset: usize,
+    /// The error that caused the app to be skipped.
+    pub error: VdfrError,
+}
+
+/// Progress of a parse, passed to the callback of [`crate::parser::parse_app_info_with_progress`]
+/// and [`crate::parser::parse_package_info_with_progress`] after every app or package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedSoFar {
+    /// Number of apps or packages parsed.
+    pub items: usize,
+    /// Bytes consumed from the start of the file.
+    pub bytes_consumed: usize,
+    /// Size of the whole file.
+    pub total_bytes: usize,
+}
+
+/// Byte ranges of the apps and nodes of a file, from [`crate::parser::parse_app_info_with_spans`].
+///
+/// Ranges are offsets from the start of the file.
+#[derive(Debug, Clone, Default)]
+pub struct SpanMap {
+    /// Range of each app, from its app ID to the end of its key-values.
+    pub apps: BTreeMap<u32, Range<usize>>,
+    /// Range of each node of an app by its key path, from the type byte to the end of the value.
+    ///
+    /// Items of folded arrays keep their index as key, like `["0"]`.
+    pub nodes: BTreeMap<u32, BTreeMap<Vec<Key>, Range<usize>>>,
+}
+
+impl SpanMap {
+    /// Byte range of an app.
+    pub fn app(&self, app_id: u32) -> Option<Range<usize>> {
+        self.apps.get(&app_id).cloned()
+    }
+
+    /// Byte range of a node of an app, by its key path.
+    pub fn node(&self, app_id: u32, path: &[&str]) -> Option<Range<usize>> {
+        let path: Vec<Key> = path.iter().map(|key| Key::from(*key)).collect();
+        self.nodes.get(&app_id)?.get(&path).cloned()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Package {
     pub id: u32,
     pub checksum: SHA1,
     pub change_number: u32,
-    pub pics: Option<u64>,
+    /// PICS access token of the package, only present since v28.
+    pub token: Option<u64>,
     pub key_values: KeyValues,
 }
 
@@ -494,7 +1514,7 @@ impl serde::Serialize for Package {
         state.serialize_field("id", &self.id)?;
         state.serialize_field("checksum", &self.checksum)?;
         state.serialize_field("change_number", &self.change_number)?;
-        state.serialize_field("pics", &self.pics)?;
+        state.serialize_field("token", &self.token)?;
         state.serialize_field("key_values", &self.key_values)?;
         state.end()
     }
@@ -504,7 +1524,9 @@ impl serde::Serialize for Package {
 pub struct PackageInfo {
     pub version: PkgInfoVersion,
     pub universe: u32,
-    pub packages: BTreeMap<u32, Package>,
+    pub packages: Map<u32, Package>,
+    /// Amount of bytes left unparsed after the packages.
+    pub trailing_bytes: usize,
 }
 
 #[cfg(feature = "serde")]
@@ -527,10 +1549,116 @@ impl Package {
     }
 }
 
+/// CRC-32 (IEEE), as used by VBKV files and shortcut app IDs.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Find the first NULL byte, the terminator of UTF-8 strings.
+pub(crate) fn find_nul(data: &[u8]) -> Option<usize> {
+    memchr::memchr(0, data)
+}
+
+/// Find the first NULL code unit, the terminator of UTF-16 strings.
+///
+/// Only two-byte aligned positions are matched, so a zero high byte of one
+/// character followed by a zero low byte of the next isn't mistaken for the end.
+pub(crate) fn find_wide_nul(data: &[u8]) -> Option<usize> {
+    // Searching for both bytes at once skips the zero high bytes of Latin text, which
+    // would stop a search for single zero bytes at every character
+    let finder = memchr::memmem::Finder::new(b"\0\0");
+    let mut start = 0;
+    while let Some(found) = finder.find(&data[start..]) {
+        let pos = start + found;
+        if pos % 2 == 0 {
+            return Some(pos);
+        }
+        start = pos + 1;
+    }
+    None
+}
+
+/// Names of the app sections of v24, by their section type.
+const APP_SECTIONS: [&str; 21] = [
+    "unknown",
+    "all",
+    "common",
+    "extended",
+    "config",
+    "stats",
+    "install",
+    "depots",
+    "vac",
+    "drm",
+    "ufs",
+    "ogg",
+    "items",
+    "policies",
+    "sysreqs",
+    "community",
+    "store",
+    "localization",
+    "broadcastgamedata",
+    "computed",
+    "albummetadata",
+];
+
+/// Name of a v24 app section, unknown types are named by their number.
+pub(crate) fn app_section_name(section: u8) -> String {
+    match APP_SECTIONS.get(section as usize) {
+        Some(name) => String::from(*name),
+        None => format!("section_{}", section),
+    }
+}
+
+/// Merge the sections of a v24 app under an `appinfo` root, like the newer versions have it.
+///
+/// The tree of a section usually has a single root named after the section, which is unwrapped.
+/// Sections with several roots are folded with `fold`, the rest is left as parsed.
+pub(crate) fn merge_app_sections(
+    app_id: u32,
+    sections: Vec<(u8, KeyValues)>,
+    fold: bool,
+) -> KeyValues {
+    let mut appinfo = KeyValues::new();
+    appinfo.insert("appid".into(), Value::Int32Type(app_id as i32));
+    for (section, key_values) in sections {
+        let mut entries = key_values.into_iter();
+        let value = match (entries.next(), entries.len()) {
+            (Some((_, root @ Value::KeyValueType(_))), 0) => root,
+            (first, _) => {
+                let section = first.into_iter().chain(entries).collect();
+                if fold {
+                    fold_sequence(section)
+                } else {
+                    Value::KeyValueType(section)
+                }
+            }
+        };
+        appinfo.insert(app_section_name(section).into(), value);
+    }
+
+    let mut root = KeyValues::new();
+    root.insert("appinfo".into(), Value::KeyValueType(appinfo));
+    root
+}
+
 /// Map a KeyValueType to a sequence of key-values
 /// If the mapping is "0" -> "Item", "1" -> "Item", etc.
 ///
 /// If not, keep the original key-value mapping
+#[cfg(feature = "legacy")]
 pub(crate) fn map_keyvalues_sequence(key_values: &KeyValues) -> KeyValues {
     key_values
         .iter()
@@ -541,30 +1669,23 @@ pub(crate) fn map_keyvalues_sequence(key_values: &KeyValues) -> KeyValues {
         .collect()
 }
 
+#[cfg(feature = "legacy")]
 fn map_value_data(value: &Value) -> Value {
     // This doesn't have ArrayType at all
     match value {
         Value::KeyValueType(sub_kv) => {
-            let total_keys = sub_kv.len();
-            let mut keys = sub_kv
-                .keys()
-                .filter_map(|k| k.parse::<usize>().ok())
-                .collect::<Vec<usize>>();
-            keys.sort();
-
-            // Check if keys is a sequence of numbers
-            let is_sequence = if keys.is_empty() || total_keys != keys.len() {
-                // If empty, it's not a sequence
-                false
-            } else {
-                keys.iter().enumerate().all(|(i, &key)| i == key)
-            };
-
-            if is_sequence {
-                let kv_array: Vec<Value> = keys
+            if is_sequence(sub_kv) {
+                // Keys like "01" parse as numbers too, so collect the values by their parsed index
+                // instead of looking them up again by the formatted key.
+                let mut indexed = sub_kv
                     .iter()
+                    .filter_map(|(k, v)| k.parse::<usize>().ok().map(|k| (k, v)))
+                    .collect::<Vec<(usize, &Value)>>();
+                indexed.sort_by_key(|(k, _)| *k);
+                let kv_array: Vec<Value> = indexed
+                    .into_iter()
                     // Map and collect the actual values data
-                    .map(|&key| map_value_data(sub_kv.get(&key.to_string()).unwrap()))
+                    .map(|(_, value)| map_value_data(value))
                     .collect();
                 // Return as an array
                 Value::ArrayType(kv_array)
@@ -577,16 +1698,352 @@ fn map_value_data(value: &Value) -> Value {
     }
 }
 
+/// Fold a section keyed `0`, `1`, ... into an array, like `map_keyvalues_sequence` but
+/// taking the section instead of cloning it. Sections below are left as they are.
+pub(crate) fn fold_sequence(key_values: KeyValues) -> Value {
+    if !is_sequence(&key_values) {
+        return Value::KeyValueType(key_values);
+    }
+    let mut indexed: Vec<(usize, Value)> = key_values
+        .into_iter()
+        .filter_map(|(k, v)| k.parse::<usize>().ok().map(|k| (k, v)))
+        .collect();
+    indexed.sort_by_key(|(k, _)| *k);
+    Value::ArrayType(indexed.into_iter().map(|(_, value)| value).collect())
+}
+
+/// The keys are the numbers from 0 without gaps, in any order.
+fn is_sequence(key_values: &KeyValues) -> bool {
+    let mut keys = key_values
+        .keys()
+        .filter_map(|k| k.parse::<usize>().ok())
+        .collect::<Vec<usize>>();
+    keys.sort();
+
+    // An empty section isn't a sequence
+    !keys.is_empty()
+        && keys.len() == key_values.len()
+        && keys.iter().enumerate().all(|(i, &key)| i == key)
+}
+
+/// Differences that [`keyvalues_semantic_eq_with_options`] doesn't count as a mismatch.
+#[derive(Debug, Clone, Copy)]
+pub struct SemanticEqOptions {
+    /// An array equals a section keyed by `"0"`, `"1"`, ... holding the same items.
+    pub sequences: bool,
+    /// Int32, UInt64 and Int64 values are equal when they hold the same number.
+    pub integers: bool,
+}
+
+impl Default for SemanticEqOptions {
+    fn default() -> Self {
+        SemanticEqOptions {
+            sequences: true,
+            integers: true,
+        }
+    }
+}
+
+/// Compare two key-values trees, ignoring the differences an encoding round-trip can introduce.
+pub fn keyvalues_semantic_eq(a: &KeyValues, b: &KeyValues) -> bool {
+    keyvalues_semantic_eq_with_options(a, b, &SemanticEqOptions::default())
+}
+
+pub fn keyvalues_semantic_eq_with_options(
+    a: &KeyValues,
+    b: &KeyValues,
+    options: &SemanticEqOptions,
+) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|(key, value)| {
+            b.get(key)
+                .is_some_and(|other| value_semantic_eq(value, other, options))
+        })
+}
+
+pub fn value_semantic_eq(a: &Value, b: &Value, options: &SemanticEqOptions) -> bool {
+    match (a, b) {
+        (Value::KeyValueType(a), Value::KeyValueType(b)) => {
+            keyvalues_semantic_eq_with_options(a, b, options)
+        }
+        (Value::ArrayType(a), Value::ArrayType(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| value_semantic_eq(a, b, options))
+        }
+        (Value::ArrayType(items), Value::KeyValueType(kv))
+        | (Value::KeyValueType(kv), Value::ArrayType(items))
+            if options.sequences =>
+        {
+            items.len() == kv.len()
+                && items.iter().enumerate().all(|(index, item)| {
+                    kv.get(index.to_string().as_str())
+                        .is_some_and(|other| value_semantic_eq(item, other, options))
+                })
+        }
+        _ if options.integers => match (integer_value(a), integer_value(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        },
+        _ => a == b,
+    }
+}
+
+fn integer_value(value: &Value) -> Option<i128> {
+    match value {
+        Value::Int32Type(v) => Some(*v as i128),
+        Value::UInt64Type(v) => Some(*v as i128),
+        Value::Int64Type(v) => Some(*v as i128),
+        _ => None,
+    }
+}
+
+impl Value {
+    /// Iterate over the values matching a `/` separated path below this value.
+    ///
+    /// A `*` segment matches any single key and `**` matches any number of keys,
+    /// array items are matched by their index. Values are found lazily, in key order.
+    pub fn find_all<'a>(&'a self, path_glob: &'a str) -> FindAll<'a> {
+        FindAll {
+            segments: path_glob.split('/').filter(|s| !s.is_empty()).collect(),
+            stack: alloc::vec![(self, 0)],
+        }
+    }
+
+    /// The values matching a `/` separated path below this value, with their concrete
+    /// paths, in key order.
+    ///
+    /// Like [`Value::find_all`], a `**` segment matches any number of keys and array items
+    /// are matched by their index, but a `*` also matches any part of a key, like
+    /// `developer_*`.
+    pub fn get_glob(&self, path_glob: &str) -> Vec<(String, &Value)> {
+        let segments: Vec<&str> = path_glob.split('/').filter(|s| !s.is_empty()).collect();
+        let mut matches = Vec::new();
+        glob_value(self, &segments, &mut String::new(), &mut matches);
+        matches
+    }
+
+    /// Children of a section or array.
+    fn children(&self) -> Vec<&Value> {
+        match self {
+            Value::KeyValueType(kv) => kv.values().collect(),
+            Value::ArrayType(array) => array.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Child of a section by key, or of an array by index.
+    fn child(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::KeyValueType(kv) => kv.get(key),
+            Value::ArrayType(array) => array.get(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the values matching a path, from [`Value::find_all`].
+pub struct FindAll<'a> {
+    segments: Vec<&'a str>,
+    /// Values left to visit, with the index of the next segment to match below them.
+    stack: Vec<(&'a Value, usize)>,
+}
+
+impl<'a> Iterator for FindAll<'a> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((value, index)) = self.stack.pop() {
+            let Some(&segment) = self.segments.get(index) else {
+                return Some(value);
+            };
+
+            match segment {
+                "*" | "**" => {
+                    let next = if segment == "*" { index + 1 } else { index };
+                    // Pushed in reverse, so they are visited in key order
+                    for child in value.children().into_iter().rev() {
+                        self.stack.push((child, next));
+                    }
+                    if segment == "**" {
+                        // Matching no keys at all is visited first
+                        self.stack.push((value, index + 1));
+                    }
+                }
+                _ => {
+                    if let Some(child) = value.child(segment) {
+                        self.stack.push((child, index + 1));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Remove the children of a section or array matching the path, arrays items are
+/// matched by their index.
+fn strip_value(value: &mut Value, segments: &[&str]) -> usize {
+    let Some((&segment, rest)) = segments.split_first() else {
+        return 0;
+    };
+    match segment {
+        // Everything below is removed, like with `*`
+        "**" if rest.is_empty() => strip_value(value, &["*"]),
+        // Matching no keys at all, then any number of keys below every child
+        "**" => {
+            let below: usize = children_mut(value, "*")
+                .into_iter()
+                .map(|child| strip_value(child, segments))
+                .sum();
+            below + strip_value(value, rest)
+        }
+        _ if rest.is_empty() => match value {
+            Value::KeyValueType(kv) => {
+                let len = kv.len();
+                kv.retain(|key, _| !glob_match(segment, key));
+                len - kv.len()
+            }
+            Value::ArrayType(array) => {
+                let len = array.len();
+                let mut index = 0;
+                array.retain(|_| {
+                    index += 1;
+                    !glob_match(segment, &(index - 1).to_string())
+                });
+                len - array.len()
+            }
+            _ => 0,
+        },
+        _ => children_mut(value, segment)
+            .into_iter()
+            .map(|child| strip_value(child, rest))
+            .sum(),
+    }
+}
+
+/// Children of a section or array whose key or index matches the pattern.
+fn children_mut<'a>(value: &'a mut Value, pattern: &str) -> Vec<&'a mut Value> {
+    match value {
+        Value::KeyValueType(kv) => kv
+            .iter_mut()
+            .filter(|(key, _)| glob_match(pattern, key))
+            .map(|(_, child)| child)
+            .collect(),
+        Value::ArrayType(array) => array
+            .iter_mut()
+            .enumerate()
+            .filter(|(index, _)| glob_match(pattern, &index.to_string()))
+            .map(|(_, child)| child)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn glob_value<'a>(
+    value: &'a Value,
+    segments: &[&str],
+    path: &mut String,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    let Some((&segment, rest)) = segments.split_first() else {
+        matches.push((path.clone(), value));
+        return;
+    };
+    let next = if segment == "**" {
+        // Matching no keys at all comes first
+        glob_value(value, rest, path, matches);
+        segments
+    } else {
+        rest
+    };
+    match value {
+        Value::KeyValueType(kv) => {
+            for (key, child) in kv {
+                glob_entry(key, child, segment, next, path, matches);
+            }
+        }
+        Value::ArrayType(array) => {
+            for (index, item) in array.iter().enumerate() {
+                glob_entry(&index.to_string(), item, segment, next, path, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Like [`glob_value`], for the key-values at the root of an app.
+fn glob_keyvalues<'a>(
+    kv: &'a KeyValues,
+    segments: &[&str],
+    path: &mut String,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    let Some((&segment, rest)) = segments.split_first() else {
+        return;
+    };
+    let next = if segment == "**" {
+        // Matching no keys at all comes first
+        glob_keyvalues(kv, rest, path, matches);
+        segments
+    } else {
+        rest
+    };
+    for (key, child) in kv {
+        glob_entry(key, child, segment, next, path, matches);
+    }
+}
+
+/// Match the rest of the path below a child, if its key matches the segment.
+fn glob_entry<'a>(
+    key: &str,
+    child: &'a Value,
+    segment: &str,
+    next: &[&str],
+    path: &mut String,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    if segment != "**" && !glob_match(segment, key) {
+        return;
+    }
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('/');
+    }
+    path.push_str(key);
+    glob_value(child, next, path, matches);
+    path.truncate(len);
+}
+
+/// Match a key against a pattern where `*` matches any part of it.
+fn glob_match(pattern: &str, key: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = key.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let Some(last) = parts.next_back() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
 // Recursively search for the specified sequence of keys in the key-value data.
 // The order of the keys dictates the hierarchy, with all except the last having
 // to be a Value::KeyValueType.
-fn find_keys<'a>(kv: &'a KeyValues, keys: &[&str]) -> Option<&'a Value> {
+pub(crate) fn find_keys<'a>(kv: &'a KeyValues, keys: &[&str]) -> Option<&'a Value> {
     if keys.is_empty() {
         return None;
     }
 
     let key = *keys.first().unwrap();
-    let value = kv.get(&key.to_string());
+    let value = kv.get(key);
     if keys.len() == 1 {
         value
     } else {
@@ -595,11 +2052,15 @@ fn find_keys<'a>(kv: &'a KeyValues, keys: &[&str]) -> Option<&'a Value> {
 }
 
 fn find_key_next<'a>(value: Option<&'a Value>, keys: &[&str]) -> Option<&'a Value> {
+    if keys.is_empty() {
+        return value;
+    }
+
     match value {
         Some(Value::KeyValueType(kv)) => find_keys(kv, keys),
         Some(Value::ArrayType(array)) => {
             // Check next key is a number
-            if let Ok(index) = keys.first().unwrap().parse::<usize>() {
+            if let Ok(index) = keys[0].parse::<usize>() {
                 let value = array.get(index);
 
                 // If the value is a KeyValueType, call recursively
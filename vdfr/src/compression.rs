@@ -0,0 +1,119 @@
+//! Transparent decompression of gzip and zstd compressed inputs, and compressed outputs.
+//!
+//! Archived app info snapshots are usually stored compressed, [`decompress`] detects
+//! the compression by its magic bytes so the result can be passed to any of the parsers.
+//! [`CompressedWriter`] wraps a writer for the writer functions, to compress while writing.
+
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+    path::Path,
+};
+
+use crate::VdfrError;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression from the magic bytes at the start of the data.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if data.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Conventional file extension, without the dot.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+        }
+    }
+}
+
+/// Writer compressing everything written into the inner writer.
+///
+/// [`CompressedWriter::finish`] must be called after writing, to write the end of the stream.
+pub enum CompressedWriter<W: Write> {
+    None(W),
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Compress with the default level of the compression.
+    pub fn new(writer: W, compression: Compression) -> std::io::Result<Self> {
+        Ok(match compression {
+            Compression::None => CompressedWriter::None(writer),
+            Compression::Gzip => CompressedWriter::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Compression::Zstd => CompressedWriter::Zstd(zstd::Encoder::new(writer, 0)?),
+        })
+    }
+
+    /// Finish the compressed stream and return the inner writer.
+    pub fn finish(self) -> std::io::Result<W> {
+        match self {
+            CompressedWriter::None(writer) => Ok(writer),
+            CompressedWriter::Gzip(encoder) => encoder.finish(),
+            CompressedWriter::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CompressedWriter::None(writer) => writer.write(buf),
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CompressedWriter::None(writer) => writer.flush(),
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Decompress the data when it's gzip or zstd compressed, otherwise it's borrowed as is.
+///
+/// Concatenated gzip members and zstd frames are decompressed one after another.
+pub fn decompress(data: &[u8]) -> Result<Cow<'_, [u8]>, VdfrError> {
+    match Compression::detect(data) {
+        Compression::None => Ok(Cow::Borrowed(data)),
+        Compression::Gzip => {
+            let mut decompressed = Vec::new();
+            flate2::read::MultiGzDecoder::new(data).read_to_end(&mut decompressed)?;
+            Ok(Cow::Owned(decompressed))
+        }
+        Compression::Zstd => Ok(Cow::Owned(zstd::stream::decode_all(data)?)),
+    }
+}
+
+/// Read a file, decompressing it when it's compressed.
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, VdfrError> {
+    let data = std::fs::read(path)?;
+    match decompress(&data)? {
+        Cow::Borrowed(_) => Ok(data),
+        Cow::Owned(decompressed) => Ok(decompressed),
+    }
+}
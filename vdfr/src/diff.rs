@@ -0,0 +1,81 @@
+//! Changes between two versions of an app info file, like the changelists of Steam's PICS.
+//!
+//! Changed apps list the top-level sections that differ, like `common` or `depots`.
+//! Apps wrapped in an `appinfo` section are compared below it.
+
+use alloc::{collections::BTreeSet, vec::Vec};
+
+use crate::{App, AppInfo, Key, KeyValues};
+
+/// An app found in both files, with different key-values or change number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppChange {
+    pub app_id: u32,
+    pub old_change_number: u32,
+    pub new_change_number: u32,
+    /// Top-level sections that were added, removed or changed, in key order.
+    ///
+    /// Empty when only the header of the app changed.
+    pub sections: Vec<Key>,
+}
+
+/// Apps that differ between two files, each list ordered by app ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSet {
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+    pub changed: Vec<AppChange>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare the apps of two parsed files.
+pub fn appinfo_changes(old: &AppInfo, new: &AppInfo) -> ChangeSet {
+    let mut changes = ChangeSet::default();
+    for (app_id, old_app) in &old.apps {
+        match new.apps.get(app_id) {
+            Some(new_app) => {
+                if let Some(change) = app_change(old_app, new_app) {
+                    changes.changed.push(change);
+                }
+            }
+            None => changes.removed.push(*app_id),
+        }
+    }
+    changes.added = new
+        .apps
+        .keys()
+        .filter(|app_id| !old.apps.contains_key(*app_id))
+        .copied()
+        .collect();
+
+    changes.removed.sort_unstable();
+    changes.added.sort_unstable();
+    changes.changed.sort_by_key(|change| change.app_id);
+    changes
+}
+
+fn app_change(old: &App, new: &App) -> Option<AppChange> {
+    let sections = changed_sections(old.sections(), new.sections());
+    if sections.is_empty() && old.change_number == new.change_number {
+        return None;
+    }
+    Some(AppChange {
+        app_id: new.id,
+        old_change_number: old.change_number,
+        new_change_number: new.change_number,
+        sections,
+    })
+}
+
+fn changed_sections(old: &KeyValues, new: &KeyValues) -> Vec<Key> {
+    let keys: BTreeSet<&Key> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect()
+}
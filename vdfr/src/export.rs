@@ -0,0 +1,29 @@
+//! Conversions of whole files into other formats, without holding the whole file parsed.
+
+use std::io::Write;
+
+use crate::{parser::iter_app_info, VdfrError};
+
+/// Convert app info data into JSON, parsing and writing one app at a time.
+///
+/// The JSON is shaped like the serialized [`AppInfo`](crate::AppInfo), with the apps in
+/// the order of the file. Only the app being written is held parsed, so the parsed tree
+/// and the JSON document are never both in memory. On error, the writer is left with
+/// the JSON written so far.
+pub fn stream_json<W: Write>(writer: &mut W, data: &[u8]) -> Result<(), VdfrError> {
+    let apps = iter_app_info(data)?;
+    writer.write_all(b"{\"version\":")?;
+    serde_json::to_writer(&mut *writer, &apps.version()).map_err(std::io::Error::from)?;
+    write!(writer, ",\"universe\":{},\"apps\":{{", apps.universe())?;
+
+    for (index, app) in apps.enumerate() {
+        let app = app?;
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        write!(writer, "\"{}\":", app.id)?;
+        serde_json::to_writer(&mut *writer, &app).map_err(std::io::Error::from)?;
+    }
+    writer.write_all(b"}}")?;
+    Ok(())
+}
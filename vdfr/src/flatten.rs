@@ -0,0 +1,90 @@
+//! Flat views of key-values, keyed by `/` separated paths like `depots/731/manifests`.
+//!
+//! Each leaf is a single entry, which makes it easy to build CSV exports, diffs or
+//! database rows on top.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{KeyValues, Value};
+
+pub trait Flatten {
+    /// The leaves below, keyed by their paths, sorted by path (or in the order they're
+    /// found with `preserve_order`). Array items have their index as key, and empty
+    /// sections or arrays are kept as values.
+    ///
+    /// Keys containing a `/` can't be told apart from nested ones.
+    fn flatten(&self) -> KeyValues;
+
+    /// Rebuild the key-values from the paths of a flattened map.
+    ///
+    /// Arrays come back as numbered sections, and a path going through a leaf replaces
+    /// it with a section.
+    fn unflatten(&self) -> KeyValues;
+}
+
+impl Flatten for KeyValues {
+    fn flatten(&self) -> KeyValues {
+        let mut flat = KeyValues::new();
+        flatten_keyvalues(self, &mut String::new(), &mut flat);
+        flat
+    }
+
+    fn unflatten(&self) -> KeyValues {
+        let mut root = KeyValues::new();
+        for (path, value) in self {
+            let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            insert_path(&mut root, &segments, value.clone());
+        }
+        root
+    }
+}
+
+fn flatten_keyvalues(kv: &KeyValues, path: &mut String, flat: &mut KeyValues) {
+    for (key, value) in kv {
+        flatten_value(key, value, path, flat);
+    }
+}
+
+fn flatten_value(key: &str, value: &Value, path: &mut String, flat: &mut KeyValues) {
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('/');
+    }
+    path.push_str(key);
+
+    match value {
+        Value::KeyValueType(kv) if !kv.is_empty() => flatten_keyvalues(kv, path, flat),
+        Value::ArrayType(array) if !array.is_empty() => {
+            for (index, item) in array.iter().enumerate() {
+                flatten_value(&index.to_string(), item, path, flat);
+            }
+        }
+        _ => {
+            flat.insert(path.as_str().into(), value.clone());
+        }
+    }
+    path.truncate(len);
+}
+
+fn insert_path(kv: &mut KeyValues, segments: &[&str], value: Value) {
+    let Some((&key, rest)) = segments.split_first() else {
+        return;
+    };
+    if rest.is_empty() {
+        kv.insert(key.into(), value);
+        return;
+    }
+
+    let child = kv
+        .entry(key.into())
+        .or_insert_with(|| Value::KeyValueType(KeyValues::new()));
+    if !matches!(child, Value::KeyValueType(_)) {
+        *child = Value::KeyValueType(KeyValues::new());
+    }
+    if let Value::KeyValueType(child) = child {
+        insert_path(child, rest, value);
+    }
+}
@@ -0,0 +1,152 @@
+//! Approximate heap usage of the parsed data, to budget memory or compare parsing modes.
+//!
+//! Sizes are estimates: allocations are counted from their capacity, and maps from the
+//! layout of their nodes or tables without the allocator overhead. Keys shared through
+//! interning are only counted once.
+
+use alloc::{borrow::Cow, collections::BTreeSet, vec::Vec};
+use core::mem::{size_of, size_of_val};
+
+use crate::{App, AppInfo, Key, KeyValues, Map, Package, PackageInfo, Value};
+
+/// Counts the bytes of the allocations, remembering the keys already counted.
+#[derive(Default)]
+struct HeapSize {
+    keys: BTreeSet<usize>,
+    bytes: usize,
+}
+
+impl HeapSize {
+    fn key(&mut self, key: &Key) {
+        // An `Arc<str>` allocates its strong and weak counts in front of the string
+        if self.keys.insert(key.as_ptr() as usize) {
+            self.bytes += 2 * size_of::<usize>() + key.len();
+        }
+    }
+
+    fn map<K, V>(&mut self, map: &Map<K, V>) {
+        self.bytes += map_size::<K, V>(map);
+    }
+
+    fn keyvalues(&mut self, kv: &KeyValues) {
+        self.map(kv);
+        for (key, value) in kv {
+            self.key(key);
+            self.value(value);
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::StringType(Cow::Owned(s)) => self.bytes += s.capacity(),
+            Value::StringType(Cow::Borrowed(_)) => {}
+            Value::WideStringType(s) => self.bytes += size_of_val(s.units()),
+            Value::BytesType(bytes) | Value::UnknownType { raw: bytes, .. } => {
+                self.bytes += bytes.capacity()
+            }
+            Value::KeyValueType(kv) => self.keyvalues(kv),
+            Value::ArrayType(items) => {
+                self.vec(items);
+                for item in items {
+                    self.value(item);
+                }
+            }
+            Value::Int32Type(_)
+            | Value::PointerType(_)
+            | Value::ColorType(_)
+            | Value::UInt64Type(_)
+            | Value::Int64Type(_)
+            | Value::Float32Type(_) => {}
+        }
+    }
+
+    fn vec<T>(&mut self, vec: &Vec<T>) {
+        self.bytes += vec.capacity() * size_of::<T>();
+    }
+
+    fn app(&mut self, app: &App) {
+        self.keyvalues(&app.key_values);
+        if let Some(raw) = &app.raw {
+            self.vec(raw);
+        }
+    }
+}
+
+/// Bytes of the nodes of a B-tree, which hold up to 11 entries.
+#[cfg(not(feature = "preserve_order"))]
+fn map_size<K, V>(map: &Map<K, V>) -> usize {
+    const CAPACITY: usize = 11;
+    let nodes = map.len().div_ceil(CAPACITY);
+    // Parent pointer, index in the parent and length, then the entries
+    let leaf = size_of::<usize>() + 2 * size_of::<u16>() + CAPACITY * size_of::<(K, V)>();
+    // About one internal node per 11 leaves past the first, with their child pointers
+    let internal = leaf + (CAPACITY + 1) * size_of::<usize>();
+    nodes * leaf + nodes.saturating_sub(1) / CAPACITY * internal
+}
+
+/// Bytes of the entries and of the hash table of an index map.
+#[cfg(feature = "preserve_order")]
+fn map_size<K, V>(map: &Map<K, V>) -> usize {
+    // Entries keep their hash, the table holds their indices and a control byte each
+    let entries = map.capacity() * (size_of::<(K, V)>() + size_of::<u64>());
+    let buckets = if map.is_empty() {
+        0
+    } else {
+        (map.capacity() * 8 / 7).next_power_of_two()
+    };
+    entries + buckets * (size_of::<usize>() + 1)
+}
+
+impl AppInfo {
+    /// Approximate bytes allocated on the heap by the apps, without the struct itself.
+    ///
+    /// Meant to budget memory and compare parsing modes, like parsing with
+    /// [`KeyValueOptions::intern_keys`](crate::KeyValueOptions::intern_keys) or not.
+    pub fn approx_heap_size(&self) -> usize {
+        let mut size = HeapSize::default();
+        size.map(&self.apps);
+        for app in self.apps.values() {
+            size.app(app);
+        }
+        size.bytes
+    }
+}
+
+impl App {
+    /// Approximate bytes allocated on the heap by the key-values and raw bytes of the app.
+    pub fn approx_heap_size(&self) -> usize {
+        let mut size = HeapSize::default();
+        size.app(self);
+        size.bytes
+    }
+}
+
+impl PackageInfo {
+    /// Approximate bytes allocated on the heap by the packages, see
+    /// [`AppInfo::approx_heap_size`].
+    pub fn approx_heap_size(&self) -> usize {
+        let mut size = HeapSize::default();
+        size.map(&self.packages);
+        for package in self.packages.values() {
+            size.keyvalues(&package.key_values);
+        }
+        size.bytes
+    }
+}
+
+impl Package {
+    pub fn approx_heap_size(&self) -> usize {
+        let mut size = HeapSize::default();
+        size.keyvalues(&self.key_values);
+        size.bytes
+    }
+}
+
+impl Value {
+    /// Approximate bytes allocated on the heap by the value and everything below it.
+    pub fn approx_heap_size(&self) -> usize {
+        let mut size = HeapSize::default();
+        size.value(self);
+        size.bytes
+    }
+}
@@ -0,0 +1,218 @@
+//! Sidecar index for random access into app info files.
+//!
+//! The index maps every app ID to the byte offset of its record, so a single app
+//! can be decoded with [`parse_app_at`] without parsing the whole file.
+//! It is usually stored next to the data as `appinfo.vdf.idx`, see [`sidecar_path`].
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    parser::{parse_app_at, parse_app_info_header},
+    App, AppInfoVersion, KeyValueOptions, VdfrError,
+};
+
+/// Magic of the index file, `VDFI` in little endian.
+const INDEX_MAGIC: u32 = 0x49_46_44_56;
+const INDEX_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Offset of the app record from the start of the file.
+    pub offset: u64,
+    pub change_number: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AppInfoIndex {
+    pub version: AppInfoVersion,
+    /// Length of the indexed file, used to detect a stale index.
+    pub data_len: u64,
+    pub entries: BTreeMap<u32, IndexEntry>,
+}
+
+impl AppInfoIndex {
+    /// Build the index by walking the app headers, without decoding any key-values.
+    pub fn build(data: &[u8]) -> Result<Self, VdfrError> {
+        let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+
+        let mut entries = BTreeMap::new();
+        let mut offset = data.len() - header.payload.len();
+        loop {
+            let app_id = read_u32_at(data, offset)?;
+            if app_id == 0 {
+                break;
+            }
+            let size = read_u32_at(data, offset + 4)?;
+            let change_number = read_u32_at(data, offset + change_number_offset(header.version))?;
+            entries.insert(
+                app_id,
+                IndexEntry {
+                    offset: offset as u64,
+                    change_number,
+                },
+            );
+            // The size field covers everything in the app after itself
+            offset += 4 + 4 + size as usize;
+        }
+
+        Ok(AppInfoIndex {
+            version: header.version,
+            data_len: data.len() as u64,
+            entries,
+        })
+    }
+
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), VdfrError>
+    where
+        W: Write,
+    {
+        writer.write_all(&INDEX_MAGIC.to_le_bytes())?;
+        writer.write_all(&INDEX_FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&u32::from(self.version).to_le_bytes())?;
+        writer.write_all(&self.data_len.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (app_id, entry) in &self.entries {
+            writer.write_all(&app_id.to_le_bytes())?;
+            writer.write_all(&entry.offset.to_le_bytes())?;
+            writer.write_all(&entry.change_number.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R>(reader: &mut R) -> Result<Self, VdfrError>
+    where
+        R: Read,
+    {
+        let magic = read_u32(reader)?;
+        if magic != INDEX_MAGIC {
+            return Err(VdfrError::UnknownMagic(magic));
+        }
+        let format_version = read_u32(reader)?;
+        if format_version != INDEX_FORMAT_VERSION {
+            return Err(VdfrError::UnknownMagic(format_version));
+        }
+        let version: AppInfoVersion = read_u32(reader)?.try_into()?;
+        let data_len = read_u64(reader)?;
+
+        let count = read_u32(reader)?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let app_id = read_u32(reader)?;
+            let offset = read_u64(reader)?;
+            let change_number = read_u32(reader)?;
+            entries.insert(
+                app_id,
+                IndexEntry {
+                    offset,
+                    change_number,
+                },
+            );
+        }
+
+        Ok(AppInfoIndex {
+            version,
+            data_len,
+            entries,
+        })
+    }
+}
+
+/// Path of the sidecar index for an app info file, `appinfo.vdf` -> `appinfo.vdf.idx`.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".idx");
+    PathBuf::from(name)
+}
+
+/// App info data opened together with its index, decoding apps on demand.
+pub struct IndexedAppInfo<'a> {
+    data: &'a [u8],
+    index: AppInfoIndex,
+    universe: u32,
+    options: KeyValueOptions,
+}
+
+impl<'a> IndexedAppInfo<'a> {
+    /// Open the data with its index.
+    ///
+    /// Only the file header (and the string pool for v29) is parsed here.
+    pub fn open(data: &'a [u8], index: AppInfoIndex) -> Result<Self, VdfrError> {
+        let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+        if index.data_len != data.len() as u64 || index.version != header.version {
+            return Err(VdfrError::StaleIndex);
+        }
+
+        Ok(IndexedAppInfo {
+            data,
+            index,
+            universe: header.universe,
+            options: header.options,
+        })
+    }
+
+    pub fn version(&self) -> AppInfoVersion {
+        self.index.version
+    }
+
+    pub fn universe(&self) -> u32 {
+        self.universe
+    }
+
+    pub fn index(&self) -> &AppInfoIndex {
+        &self.index
+    }
+
+    pub fn app_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.index.entries.keys().copied()
+    }
+
+    /// Decode a single app, or `None` if it isn't in the index.
+    pub fn get(&self, app_id: u32) -> Result<Option<App>, VdfrError> {
+        let Some(entry) = self.index.entries.get(&app_id) else {
+            return Ok(None);
+        };
+        let offset = usize::try_from(entry.offset)
+            .map_err(|_| VdfrError::InvalidOffset(entry.offset as i64))?;
+        let app = parse_app_at(self.data, offset, &self.options, self.index.version)?;
+        if app.id != app_id {
+            return Err(VdfrError::StaleIndex);
+        }
+        Ok(Some(app))
+    }
+}
+
+/// Offset of the change number in an app record.
+fn change_number_offset(version: AppInfoVersion) -> usize {
+    // app id + size + state + last_update
+    let offset = 4 + 4 + 4 + 4;
+    match version {
+        AppInfoVersion::V24 => offset,
+        // + access_token
+        AppInfoVersion::V26 => offset + 8,
+        // + access_token + checksum_txt
+        _ => offset + 8 + 20,
+    }
+}
+
+fn read_u32_at(data: &[u8], offset: usize) -> Result<u32, VdfrError> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(VdfrError::InvalidOffset(offset as i64))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, VdfrError> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, VdfrError> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
@@ -1,17 +1,29 @@
-use std::{collections::BTreeMap, io::Error};
+use std::io::{BufRead, Read};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 
 use crate::{
     common::{
-        map_keyvalues_sequence, App, AppInfo, KeyValueOptions, KeyValues, Package, PackageInfo,
-        Value, VdfrError, BIN_COLOR, BIN_END, BIN_END_ALT, BIN_FLOAT32, BIN_INT32, BIN_INT64,
-        BIN_KV, BIN_POINTER, BIN_STRING, BIN_UINT64, BIN_WIDESTRING,
+        find_wide_nul, map_keyvalues_sequence, merge_app_sections, App, AppInfo, KeyInterner,
+        KeyValueOptions, KeyValues, Map, Package, PackageInfo, Value, VdfrError,
     },
-    AppInfoVersion, PkgInfoVersion, SHA1,
+    AppInfoVersion, BinType, Color, PkgInfoVersion, Pointer, WideString, SHA1,
 };
 
 pub fn parse_app_info<R>(reader: &mut R) -> Result<AppInfo, VdfrError>
+where
+    R: std::io::BufRead + std::io::Seek,
+{
+    parse_app_info_with_options(reader, KeyValueOptions::default())
+}
+
+/// Parse app info with the given options.
+///
+/// The string pool of the options is ignored, v29 files use their own pool.
+pub fn parse_app_info_with_options<R>(
+    reader: &mut R,
+    mut options: KeyValueOptions,
+) -> Result<AppInfo, VdfrError>
 where
     R: std::io::BufRead + std::io::Seek,
 {
@@ -19,25 +31,125 @@ where
 
     let universe = reader.read_u32::<LittleEndian>()?;
 
-    let mut options = KeyValueOptions::default();
+    options.string_pool = Vec::new();
+
+    // For v29, the apps end where the string pool starts
+    let mut section_end = None;
+    let mut pool_trailing = 0;
 
     if version == AppInfoVersion::V29 {
         let offset_table = reader.read_i64::<LittleEndian>()?;
-        let old_offset = reader.stream_position().unwrap();
-        reader.seek(std::io::SeekFrom::Start(offset_table as u64))?;
-        let string_count = reader.read_u32::<LittleEndian>()?;
-        options.string_pool = (0..string_count)
-            .map(|_| read_string(reader, false).unwrap())
-            .collect();
+        let offset_start =
+            u64::try_from(offset_table).map_err(|_| VdfrError::InvalidOffset(offset_table))?;
+        let old_offset = reader.stream_position()?;
+        reader.seek(std::io::SeekFrom::Start(offset_start))?;
+        options.string_pool = read_string_pool(reader, &options)?;
+        pool_trailing = remaining_bytes(reader)?;
+        section_end = Some(offset_start);
         reader.seek(std::io::SeekFrom::Start(old_offset))?;
     }
 
     let mut appinfo = AppInfo {
         universe,
         version,
-        apps: BTreeMap::new(),
+        apps: Map::new(),
+        trailing_bytes: 0,
+    };
+
+    read_apps(reader, &options, version, &mut appinfo.apps)?;
+
+    let trailing = match section_end {
+        Some(end) => end.saturating_sub(reader.stream_position()?) + pool_trailing,
+        None => remaining_bytes(reader)?,
+    };
+    appinfo.trailing_bytes = trailing as usize;
+    options.check_section_end(true, appinfo.trailing_bytes)?;
+
+    Ok(appinfo)
+}
+
+/// Parse app info from a reader that can't seek, like stdin or a socket.
+///
+/// For v29, the string pool is stored after the apps, so the apps section is
+/// buffered in memory until the string pool has been read.
+pub fn parse_app_info_from_stream<R>(
+    reader: &mut R,
+    mut options: KeyValueOptions,
+) -> Result<AppInfo, VdfrError>
+where
+    R: std::io::BufRead,
+{
+    let version: AppInfoVersion = reader.read_u32::<LittleEndian>()?.try_into()?;
+
+    let universe = reader.read_u32::<LittleEndian>()?;
+
+    options.string_pool = Vec::new();
+
+    let mut appinfo = AppInfo {
+        universe,
+        version,
+        apps: Map::new(),
+        trailing_bytes: 0,
     };
 
+    if version == AppInfoVersion::V29 {
+        let offset_table = reader.read_i64::<LittleEndian>()?;
+        // The offset is from the start of the file, we already read the version, universe and offset
+        let section_len = offset_table
+            .checked_sub(4 + 4 + 8)
+            .and_then(|len| u64::try_from(len).ok())
+            .ok_or(VdfrError::InvalidOffset(offset_table))?;
+
+        let mut section = Vec::new();
+        (&mut *reader).take(section_len).read_to_end(&mut section)?;
+        if (section.len() as u64) < section_len {
+            return Err(VdfrError::Truncated);
+        }
+
+        options.string_pool = read_string_pool(reader, &options)?;
+        let pool_trailing = std::io::copy(reader, &mut std::io::sink())?;
+
+        let mut section_reader = std::io::Cursor::new(section);
+        read_apps(&mut section_reader, &options, version, &mut appinfo.apps)?;
+        let section_trailing = section_len - section_reader.position();
+        appinfo.trailing_bytes = (section_trailing + pool_trailing) as usize;
+    } else {
+        read_apps(reader, &options, version, &mut appinfo.apps)?;
+        appinfo.trailing_bytes = std::io::copy(reader, &mut std::io::sink())? as usize;
+    }
+
+    options.check_section_end(true, appinfo.trailing_bytes)?;
+
+    Ok(appinfo)
+}
+
+fn read_string_pool<R>(reader: &mut R, options: &KeyValueOptions) -> Result<Vec<String>, VdfrError>
+where
+    R: std::io::BufRead,
+{
+    let string_count = reader.read_u32::<LittleEndian>()?;
+    if string_count as usize > options.limits.max_pool_entries {
+        return Err(VdfrError::LimitExceeded(
+            "max_pool_entries",
+            options.limits.max_pool_entries,
+        ));
+    }
+    (0..string_count)
+        .map(|_| read_key(reader, options).map(|key| key.to_string()))
+        .collect()
+}
+
+/// Read apps until the zero app ID terminator.
+fn read_apps<R>(
+    reader: &mut R,
+    options: &KeyValueOptions,
+    version: AppInfoVersion,
+    apps: &mut Map<u32, App>,
+) -> Result<(), VdfrError>
+where
+    R: std::io::BufRead,
+{
+    let mut keys = KeyInterner::new(options.intern_keys);
     loop {
         let app_id = reader.read_u32::<LittleEndian>()?;
         if app_id == 0 {
@@ -47,16 +159,21 @@ where
         let size = reader.read_u32::<LittleEndian>()?;
         let state = reader.read_u32::<LittleEndian>()?;
         let last_update = reader.read_u32::<LittleEndian>()?;
-        let access_token = reader.read_u64::<LittleEndian>()?;
+        let access_token = match version {
+            AppInfoVersion::V24 => 0,
+            _ => reader.read_u64::<LittleEndian>()?,
+        };
 
         let mut checksum_txt: [u8; 20] = [0; 20];
-        reader.read_exact(&mut checksum_txt)?;
+        if !matches!(version, AppInfoVersion::V24 | AppInfoVersion::V26) {
+            reader.read_exact(&mut checksum_txt)?;
+        }
 
         let change_number = reader.read_u32::<LittleEndian>()?;
 
         let checksum_bin = match version {
-            // Skip checksum_bin for v27
-            AppInfoVersion::V27 => None,
+            // Skip checksum_bin before v28
+            AppInfoVersion::V24 | AppInfoVersion::V26 | AppInfoVersion::V27 => None,
             _ => {
                 let mut checksum_bin: [u8; 20] = [0; 20];
                 reader.read_exact(&mut checksum_bin)?;
@@ -64,8 +181,21 @@ where
             }
         };
 
-        let key_values = parse_keyvalues(reader, options.clone())?;
-        let key_values = map_keyvalues_sequence(&key_values);
+        let key_values = if version == AppInfoVersion::V24 {
+            let mut sections = Vec::new();
+            loop {
+                let section = reader.read_u8()?;
+                if section == 0 {
+                    break;
+                }
+                let key_values = parse_keyvalues_node(reader, options, &mut keys, 1, &mut 0)?;
+                sections.push((section, key_values));
+            }
+            map_keyvalues_sequence(&merge_app_sections(app_id, sections, false))
+        } else {
+            let key_values = parse_keyvalues_node(reader, options, &mut keys, 1, &mut 0)?;
+            map_keyvalues_sequence(&key_values)
+        };
 
         let app = App {
             id: app_id,
@@ -77,14 +207,26 @@ where
             checksum_bin: checksum_bin.map(SHA1::new),
             change_number,
             key_values,
+            raw: None,
         };
-        appinfo.apps.insert(app_id, app);
+        apps.insert(app_id, app);
     }
 
-    Ok(appinfo)
+    Ok(())
 }
 
 pub fn parse_package_info<R>(reader: &mut R) -> Result<PackageInfo, VdfrError>
+where
+    R: std::io::BufRead + std::io::Seek,
+{
+    parse_package_info_with_options(reader, KeyValueOptions::default())
+}
+
+/// Parse package info with the given options.
+pub fn parse_package_info_with_options<R>(
+    reader: &mut R,
+    options: KeyValueOptions,
+) -> Result<PackageInfo, VdfrError>
 where
     R: std::io::BufRead + std::io::Seek,
 {
@@ -94,9 +236,11 @@ where
     let mut packageinfo = PackageInfo {
         version,
         universe,
-        packages: BTreeMap::new(),
+        packages: Map::new(),
+        trailing_bytes: 0,
     };
 
+    let mut keys = KeyInterner::new(options.intern_keys);
     loop {
         let package_id = reader.read_u32::<LittleEndian>()?;
 
@@ -109,25 +253,29 @@ where
 
         let change_number = reader.read_u32::<LittleEndian>()?;
 
-        let pics = match version {
-            PkgInfoVersion::V27 => None,
-            PkgInfoVersion::V28 => Some(reader.read_u64::<LittleEndian>()?),
+        let token = if version.has_token() {
+            Some(reader.read_u64::<LittleEndian>()?)
+        } else {
+            None
         };
 
-        let key_values = parse_keyvalues(reader, KeyValueOptions::default())?;
+        let key_values = parse_keyvalues_node(reader, &options, &mut keys, 1, &mut 0)?;
         let key_values = map_keyvalues_sequence(&key_values);
 
         let package = Package {
             id: package_id,
             checksum: SHA1::new(checksum),
             change_number,
-            pics,
+            token,
             key_values,
         };
 
         packageinfo.packages.insert(package_id, package);
     }
 
+    packageinfo.trailing_bytes = remaining_bytes(reader)? as usize;
+    options.check_section_end(true, packageinfo.trailing_bytes)?;
+
     Ok(packageinfo)
 }
 
@@ -135,11 +283,39 @@ pub fn parse_keyvalues<R>(reader: &mut R, options: KeyValueOptions) -> Result<Ke
 where
     R: std::io::BufRead + std::io::Seek,
 {
-    let current_bin_end = if options.alt_format {
-        BIN_END_ALT
-    } else {
-        BIN_END
-    };
+    let mut keys = KeyInterner::new(options.intern_keys);
+    let key_values = parse_keyvalues_node(reader, &options, &mut keys, 1, &mut 0)?;
+    options.check_section_end(true, remaining_bytes(reader)? as usize)?;
+    Ok(map_keyvalues_sequence(&key_values))
+}
+
+/// Count the bytes left in the reader, without moving it.
+fn remaining_bytes<R>(reader: &mut R) -> Result<u64, VdfrError>
+where
+    R: std::io::Seek,
+{
+    let current = reader.stream_position()?;
+    let end = reader.seek(std::io::SeekFrom::End(0))?;
+    reader.seek(std::io::SeekFrom::Start(current))?;
+    Ok(end.saturating_sub(current))
+}
+
+fn parse_keyvalues_node<R>(
+    reader: &mut R,
+    options: &KeyValueOptions,
+    keys: &mut KeyInterner,
+    depth: usize,
+    nodes: &mut usize,
+) -> Result<KeyValues, VdfrError>
+where
+    R: std::io::BufRead,
+{
+    let limits = &options.limits;
+    if depth > limits.max_depth {
+        return Err(VdfrError::DepthLimitExceeded(limits.max_depth));
+    }
+
+    let current_bin_end = BinType::end(options.alt_format).to_u8();
 
     let mut node = KeyValues::new();
 
@@ -149,70 +325,138 @@ where
             return Ok(node);
         }
 
+        *nodes += 1;
+        if *nodes > limits.max_nodes {
+            return Err(VdfrError::LimitExceeded("max_nodes", limits.max_nodes));
+        }
+
         let key = if options.string_pool.is_empty() {
-            read_string(reader, false)?
+            keys.intern(&read_key(reader, options)?)
         } else {
             let idx = reader.read_u32::<LittleEndian>()? as usize;
-            options.string_pool[idx].clone()
+            keys.pool_key(&options.string_pool, idx).ok_or(
+                VdfrError::StringPoolIndexOutOfBounds(idx, options.string_pool.len()),
+            )?
         };
 
-        if t == BIN_KV {
-            let subnode = parse_keyvalues(reader, options.clone())?;
-            node.insert(key, Value::KeyValueType(subnode));
-        } else if t == BIN_STRING {
-            let s = read_string(reader, false)?;
-            node.insert(key, Value::StringType(s));
-        } else if t == BIN_WIDESTRING {
-            let s = read_string(reader, true)?;
-            node.insert(key, Value::WideStringType(s));
-        } else if [BIN_INT32, BIN_POINTER, BIN_COLOR].contains(&t) {
-            let val = reader.read_i32::<LittleEndian>()?;
-            if t == BIN_INT32 {
-                node.insert(key, Value::Int32Type(val));
-            } else if t == BIN_POINTER {
-                node.insert(key, Value::PointerType(val));
-            } else if t == BIN_COLOR {
-                node.insert(key, Value::ColorType(val));
+        let value = match BinType::from_u8(t) {
+            Some(BinType::Map) => {
+                let subnode = parse_keyvalues_node(reader, options, keys, depth + 1, nodes)?;
+                Value::KeyValueType(subnode)
             }
-        } else if t == BIN_UINT64 {
-            let val = reader.read_u64::<LittleEndian>()?;
-            node.insert(key, Value::UInt64Type(val));
-        } else if t == BIN_INT64 {
-            let val = reader.read_i64::<LittleEndian>()?;
-            node.insert(key, Value::Int64Type(val));
-        } else if t == BIN_FLOAT32 {
-            let val = reader.read_f32::<LittleEndian>()?;
-            node.insert(key, Value::Float32Type(val));
-        } else {
-            return Err(VdfrError::InvalidType(t));
+            Some(BinType::String) => {
+                let bytes = read_bytes(reader, limits.max_string_len)?;
+                options.string_decoding.decode_value(bytes)?
+            }
+            Some(BinType::WideString) => {
+                Value::WideStringType(read_wide_string(reader, limits.max_string_len)?)
+            }
+            Some(BinType::Int32) => Value::Int32Type(reader.read_i32::<LittleEndian>()?),
+            Some(BinType::Pointer) => {
+                Value::PointerType(Pointer(reader.read_i32::<LittleEndian>()?))
+            }
+            Some(BinType::Color) => {
+                Value::ColorType(Color::from_i32(reader.read_i32::<LittleEndian>()?))
+            }
+            Some(BinType::UInt64) => Value::UInt64Type(reader.read_u64::<LittleEndian>()?),
+            Some(BinType::Int64) => Value::Int64Type(reader.read_i64::<LittleEndian>()?),
+            Some(BinType::Float32) => Value::Float32Type(reader.read_f32::<LittleEndian>()?),
+            // The end byte of the other format
+            Some(BinType::End | BinType::EndAlt) | None => {
+                return Err(VdfrError::InvalidType(t));
+            }
+        };
+        node.insert(key, value);
+    }
+}
+
+/// Read a key or pool string, decoded with the [`crate::StringDecoding`] of the options.
+fn read_key<R>(reader: &mut R, options: &KeyValueOptions) -> Result<String, VdfrError>
+where
+    R: std::io::BufRead,
+{
+    let bytes = read_bytes(reader, options.limits.max_string_len)?;
+    Ok(options.string_decoding.decode_key(&bytes)?.into_owned())
+}
+
+/// Read a NULL terminated string, without the terminator.
+fn read_bytes<R>(reader: &mut R, max_len: usize) -> Result<Vec<u8>, VdfrError>
+where
+    R: std::io::BufRead,
+{
+    let mut buf: Vec<u8> = vec![];
+    // Read one byte past the limit, so we can tell a string that is exactly at the limit apart
+    let limit = (max_len as u64).saturating_add(1);
+    (&mut *reader).take(limit).read_until(0, &mut buf)?;
+    match buf.pop() {
+        Some(0) => {}
+        _ if buf.len() >= max_len => {
+            return Err(VdfrError::LimitExceeded("max_string_len", max_len));
         }
+        _ => return Err(VdfrError::Truncated),
     }
+    Ok(buf)
 }
 
-fn read_string<R>(reader: &mut R, wide: bool) -> Result<String, Error>
+/// Read a NULL terminated little-endian UTF-16 string, scanning the reader's buffer directly.
+fn read_wide_string<R>(reader: &mut R, max_len: usize) -> Result<WideString, VdfrError>
 where
     R: std::io::BufRead,
 {
-    if wide {
-        let mut buf: Vec<u16> = vec![];
-        loop {
-            // Maybe this should be big-endian?
-            let c = reader.read_u16::<LittleEndian>()?;
-            if c == 0 {
-                break;
-            }
-            buf.push(c);
+    let mut buf: Vec<u16> = vec![];
+    // Low byte of a code unit split across two buffer fills
+    let mut pending: Option<u8> = None;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Err(VdfrError::Truncated);
         }
-        Ok(std::string::String::from_utf16_lossy(&buf).to_string())
-    } else {
-        let mut buf: Vec<u8> = vec![];
-        loop {
-            let c = reader.read_u8()?;
+
+        let mut chunk = available;
+        let mut consumed = 0;
+        let mut done = false;
+
+        if let Some(low) = pending.take() {
+            // Read little-endian like the other units, a byte order mark swaps them all
+            let c = u16::from_le_bytes([low, chunk[0]]);
+            chunk = &chunk[1..];
+            consumed += 1;
             if c == 0 {
-                break;
+                done = true;
+            } else {
+                buf.push(c);
             }
-            buf.push(c);
         }
-        Ok(std::string::String::from_utf8_lossy(&buf).to_string())
+
+        if !done {
+            let units = match find_wide_nul(chunk) {
+                Some(end) => {
+                    done = true;
+                    consumed += end + 2;
+                    &chunk[..end]
+                }
+                None => {
+                    let even = chunk.len() & !1;
+                    consumed += chunk.len();
+                    if even < chunk.len() {
+                        pending = Some(chunk[even]);
+                    }
+                    &chunk[..even]
+                }
+            };
+            buf.extend(
+                units
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]])),
+            );
+        }
+        reader.consume(consumed);
+
+        if buf.len() * 2 > max_len {
+            return Err(VdfrError::LimitExceeded("max_string_len", max_len));
+        }
+        if done {
+            return Ok(WideString::from_le_units(buf));
+        }
     }
 }
@@ -1,8 +1,46 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod analysis;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+pub mod backend;
+#[cfg(feature = "cache")]
+mod cache;
+pub mod catalog;
 pub mod common;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod diff;
+#[cfg(feature = "serde")]
+pub mod export;
+pub mod flatten;
+mod heap;
+#[cfg(feature = "std")]
+pub mod index;
 
 #[cfg(feature = "legacy")]
 pub mod legacy_parser;
+pub mod lint;
+pub mod localization;
+mod lookup;
+pub mod names;
 pub mod parser;
+pub mod patch;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod schema;
+#[cfg(feature = "regex")]
+pub mod search;
+pub mod shortcuts;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod steam;
+pub mod text;
+pub mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 #[cfg(feature = "writer")]
 pub mod writer;
 
@@ -11,3 +49,7 @@ pub use common::*;
 // Re-export serde_json, if feature serde is enabled
 #[cfg(feature = "serde")]
 pub use serde_json;
+
+// Re-export regex for the search, if feature regex is enabled
+#[cfg(feature = "regex")]
+pub use regex;
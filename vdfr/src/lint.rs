@@ -0,0 +1,188 @@
+//! Consistency checks of parsed app info, reported as findings instead of errors.
+//!
+//! [`check`] looks at the parsed data only, [`check_data`] also compares the size
+//! and binary checksum of every app with the bytes they describe.
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{parser::parse_app_info_with_spans, AppInfo, Key, KeyValues, Value, VdfrError, SHA1};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    /// The binary checksum of an app is not the SHA-1 of its key-values.
+    ChecksumMismatch,
+    /// The size field of an app disagrees with the bytes of the app.
+    SizeMismatch,
+    /// A value of a type Steam doesn't use in app info, like a wide string, pointer or color.
+    UnexpectedValueType,
+    /// A depot has a manifest for a branch that isn't listed in `depots/branches`.
+    DanglingBranch,
+}
+
+impl Rule {
+    /// Name of the rule in kebab case, like `checksum-mismatch`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::ChecksumMismatch => "checksum-mismatch",
+            Rule::SizeMismatch => "size-mismatch",
+            Rule::UnexpectedValueType => "unexpected-value-type",
+            Rule::DanglingBranch => "dangling-branch",
+        }
+    }
+}
+
+impl core::fmt::Display for Rule {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub app_id: u32,
+    pub rule: Rule,
+    /// Key path of the offending value, empty for findings about the whole app.
+    ///
+    /// The path starts below the `appinfo` section of apps wrapped in one.
+    pub path: Vec<Key>,
+    pub message: String,
+}
+
+/// Check the parsed apps, the findings are ordered by app ID.
+pub fn check(app_info: &AppInfo) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for app in app_info.apps.values() {
+        let mut path = Vec::new();
+        check_value_types(app.id, app.sections(), &mut path, &mut findings);
+        check_branches(app.id, app.sections(), &mut findings);
+    }
+    findings.sort_by_key(|finding| finding.app_id);
+    findings
+}
+
+/// Parse the app info and check it, including the size and binary checksum of every app.
+pub fn check_data(data: &[u8]) -> Result<Vec<Finding>, VdfrError> {
+    let (app_info, spans) = parse_app_info_with_spans(data, false)?;
+    let header_size = app_info.version.app_header_size();
+
+    let mut findings = Vec::new();
+    for app in app_info.apps.values() {
+        let Some(range) = spans.app(app.id) else {
+            continue;
+        };
+        // The size counts everything after the app ID and itself
+        let payload = range.len() - 8;
+        if app.size as usize != payload {
+            findings.push(Finding {
+                app_id: app.id,
+                rule: Rule::SizeMismatch,
+                path: Vec::new(),
+                message: format!("size is {} but the app has {} bytes", app.size, payload),
+            });
+        }
+
+        if let Some(checksum_bin) = app.checksum_bin {
+            let key_values = &data[range.start + header_size..range.end];
+            let digest = SHA1::new(sha1_smol::Sha1::from(key_values).digest().bytes());
+            if digest != checksum_bin {
+                findings.push(Finding {
+                    app_id: app.id,
+                    rule: Rule::ChecksumMismatch,
+                    path: Vec::new(),
+                    message: format!(
+                        "binary checksum is {} but the key-values hash to {}",
+                        checksum_bin, digest
+                    ),
+                });
+            }
+        }
+    }
+
+    findings.extend(check(&app_info));
+    // Stable, so the findings of an app keep the order of the rules above
+    findings.sort_by_key(|finding| finding.app_id);
+    Ok(findings)
+}
+
+fn check_value_types(
+    app_id: u32,
+    key_values: &KeyValues,
+    path: &mut Vec<Key>,
+    findings: &mut Vec<Finding>,
+) {
+    for (key, value) in key_values {
+        path.push(key.clone());
+        check_value_type(app_id, value, path, findings);
+        path.pop();
+    }
+}
+
+fn check_value_type(app_id: u32, value: &Value, path: &mut Vec<Key>, findings: &mut Vec<Finding>) {
+    match value {
+        Value::WideStringType(_)
+        | Value::PointerType(_)
+        | Value::ColorType(_)
+        | Value::UnknownType { .. } => {
+            findings.push(Finding {
+                app_id,
+                rule: Rule::UnexpectedValueType,
+                path: path.clone(),
+                message: format!("{} value in app info", value.kind_name()),
+            });
+        }
+        Value::KeyValueType(key_values) => check_value_types(app_id, key_values, path, findings),
+        Value::ArrayType(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                path.push(format!("{}", idx).into());
+                check_value_type(app_id, item, path, findings);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_branches(app_id: u32, key_values: &KeyValues, findings: &mut Vec<Finding>) {
+    let Some(Value::KeyValueType(depots)) = key_values.get("depots") else {
+        return;
+    };
+    let branches = match depots.get("branches") {
+        Some(Value::KeyValueType(branches)) => Some(branches),
+        _ => None,
+    };
+
+    for (depot_id, depot) in depots {
+        // Depots are the numbered sections, next to `branches` and other settings
+        if depot_id.parse::<u32>().is_err() {
+            continue;
+        }
+        let Value::KeyValueType(depot) = depot else {
+            continue;
+        };
+        for manifests_key in ["manifests", "encryptedmanifests"] {
+            let Some(Value::KeyValueType(manifests)) = depot.get(manifests_key) else {
+                continue;
+            };
+            for branch in manifests.keys() {
+                if branches.is_some_and(|branches| branches.contains_key(branch)) {
+                    continue;
+                }
+                findings.push(Finding {
+                    app_id,
+                    rule: Rule::DanglingBranch,
+                    path: [
+                        Key::from("depots"),
+                        depot_id.clone(),
+                        Key::from(manifests_key),
+                        branch.clone(),
+                    ]
+                    .into(),
+                    message: format!(
+                        "depot {} has a manifest for unknown branch {}",
+                        depot_id, branch
+                    ),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Resolution of `#token` style localization references in app info strings.
+//!
+//! A localization subtree holds a section per language, with the tokens either directly
+//! in it or in a `tokens` section below it:
+//! ```text
+//! "localization" { "english" { "tokens" { "#app_name" "Example" } } }
+//! ```
+
+use alloc::{borrow::Cow, string::String};
+
+use crate::{KeyValues, Value};
+
+/// Language used when a token is missing from the requested one.
+pub const FALLBACK_LANGUAGE: &str = "english";
+
+/// Resolves tokens from the localization subtree for a language.
+#[derive(Debug, Clone, Copy)]
+pub struct Localizer<'a> {
+    tokens: Option<&'a KeyValues>,
+    fallback: Option<&'a KeyValues>,
+}
+
+impl<'a> Localizer<'a> {
+    pub fn new(localization: &'a KeyValues, language: &str) -> Self {
+        Localizer {
+            tokens: language_tokens(localization, language),
+            fallback: language_tokens(localization, FALLBACK_LANGUAGE),
+        }
+    }
+
+    /// Text of a token, with or without its leading `#`, ignoring case.
+    ///
+    /// Only the text of wide strings is owned, since it's converted from UTF-16.
+    pub fn token(&self, token: &str) -> Option<Cow<'a, str>> {
+        let token = token.strip_prefix('#').unwrap_or(token);
+        find_token(self.tokens, token).or_else(|| find_token(self.fallback, token))
+    }
+
+    /// Replace every known `#token` in the text, unknown tokens are kept as they are.
+    ///
+    /// A token is a `#` followed by letters, digits and underscores.
+    pub fn resolve(&self, text: &str) -> String {
+        let mut resolved = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('#') {
+            resolved.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+
+            match self.token(&after[..len]) {
+                Some(value) if len > 0 => resolved.push_str(&value),
+                _ => resolved.push_str(&rest[start..start + 1 + len]),
+            }
+            rest = &after[len..];
+        }
+        resolved.push_str(rest);
+        resolved
+    }
+}
+
+fn language_tokens<'a>(localization: &'a KeyValues, language: &str) -> Option<&'a KeyValues> {
+    let section = localization
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(language))
+        .map(|(_, value)| value)?;
+    match section {
+        Value::KeyValueType(section) => match section.get("tokens") {
+            Some(Value::KeyValueType(tokens)) => Some(tokens),
+            _ => Some(section),
+        },
+        _ => None,
+    }
+}
+
+fn find_token<'a>(tokens: Option<&'a KeyValues>, token: &str) -> Option<Cow<'a, str>> {
+    tokens?.iter().find_map(|(key, value)| {
+        let key = key.strip_prefix('#').unwrap_or(key);
+        if !key.eq_ignore_ascii_case(token) {
+            return None;
+        }
+        match value {
+            Value::StringType(text) => Some(Cow::Borrowed(&**text)),
+            Value::WideStringType(text) => Some(Cow::Owned(text.to_string_lossy())),
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,51 @@
+//! Case-insensitive lookups in key-values, for the files whose key casing changed
+//! between client versions.
+
+use alloc::vec::Vec;
+
+use crate::{KeyValues, Value};
+
+/// Get a value by its key, ignoring the case.
+pub(crate) fn get<'a>(key_values: &'a KeyValues, name: &str) -> Option<&'a Value> {
+    key_values
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+/// Get a section by its key, ignoring the case.
+pub(crate) fn get_section<'a>(key_values: &'a KeyValues, name: &str) -> Option<&'a KeyValues> {
+    match get(key_values, name) {
+        Some(Value::KeyValueType(section)) => Some(section),
+        _ => None,
+    }
+}
+
+/// Get a string by its key, ignoring the case.
+#[cfg(feature = "std")]
+pub(crate) fn get_str<'a>(key_values: &'a KeyValues, name: &str) -> Option<&'a str> {
+    match get(key_values, name) {
+        Some(Value::StringType(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Items of a numbered section with their index, which is folded into an array
+/// when the numbers start at zero.
+///
+/// The items of an unfolded section are sorted by their number, keys that aren't a
+/// number are skipped.
+pub(crate) fn sequence(value: &Value) -> Vec<(u32, &Value)> {
+    match value {
+        Value::ArrayType(items) => (0..).zip(items).collect(),
+        Value::KeyValueType(items) => {
+            let mut indexed = items
+                .iter()
+                .filter_map(|(key, value)| Some((key.parse().ok()?, value)))
+                .collect::<Vec<_>>();
+            indexed.sort_by_key(|(index, _)| *index);
+            indexed
+        }
+        _ => Vec::new(),
+    }
+}
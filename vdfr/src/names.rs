@@ -0,0 +1,92 @@
+//! Lookup of apps by their name, from `common/name`.
+//!
+//! Names are normalized before indexing, so `Half-Life 2™` and `half life 2` are the same name.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use crate::{common::find_keys, AppInfo, Value};
+
+/// Normalized app names to the IDs of the apps with that name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameIndex {
+    pub names: BTreeMap<String, Vec<u32>>,
+}
+
+impl NameIndex {
+    /// IDs of the apps with the name, after normalizing it.
+    pub fn get(&self, name: &str) -> &[u32] {
+        self.names
+            .get(&normalize_name(name))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Apps with a name within `max_distance` edits of the query, closest first.
+    ///
+    /// The distance is counted in characters between the normalized names.
+    pub fn fuzzy(&self, query: &str, max_distance: usize) -> Vec<(u32, usize)> {
+        let query: Vec<char> = normalize_name(query).chars().collect();
+
+        let mut matches = Vec::new();
+        for (name, ids) in &self.names {
+            let name: Vec<char> = name.chars().collect();
+            if name.len().abs_diff(query.len()) > max_distance {
+                continue;
+            }
+            let distance = edit_distance(&query, &name);
+            if distance <= max_distance {
+                matches.extend(ids.iter().map(|&id| (id, distance)));
+            }
+        }
+        matches.sort_by_key(|&(id, distance)| (distance, id));
+        matches
+    }
+}
+
+impl AppInfo {
+    /// Index the apps by their normalized `common/name`, apps without a name are left out.
+    ///
+    /// The name is read below the `appinfo` section of apps wrapped in one.
+    pub fn build_name_index(&self) -> NameIndex {
+        let mut index = NameIndex::default();
+        for app in self.apps.values() {
+            if let Some(Value::StringType(name)) = find_keys(app.sections(), &["common", "name"]) {
+                let name = normalize_name(name);
+                if !name.is_empty() {
+                    index.names.entry(name).or_default().push(app.id);
+                }
+            }
+        }
+        index
+    }
+}
+
+/// Lowercase the name and keep only its words, separated by single spaces.
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for word in name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.extend(word.chars().flat_map(char::to_lowercase));
+    }
+    normalized
+}
+
+/// Levenshtein distance between two strings of characters.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = alloc::vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        core::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
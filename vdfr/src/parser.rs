@@ -1,25 +1,35 @@
-use std::collections::BTreeMap;
+use alloc::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::String,
+    vec::Vec,
+};
+use core::ops::Range;
 
 use nom::{
-    bytes::complete::{take, take_until},
+    bytes::complete::take,
     error::{ErrorKind, ParseError},
-    multi::{count, many0},
-    number::complete::{be_u16, le_f32, le_i32, le_i64, le_u16, le_u32, le_u64, le_u8},
-    IResult, Parser,
+    multi::count,
+    number::complete::{le_f32, le_i32, le_i64, le_u32, le_u64, le_u8},
+    IResult, Parser as _,
 };
 
 use crate::{
     common::{
-        map_keyvalues_sequence, App, AppInfo, KeyValueOptions, KeyValues, Value, VdfrError,
-        BIN_COLOR, BIN_END, BIN_END_ALT, BIN_FLOAT32, BIN_INT32, BIN_INT64, BIN_KV, BIN_POINTER,
-        BIN_STRING, BIN_UINT64, BIN_WIDESTRING,
+        crc32, find_nul, find_wide_nul, fold_sequence, merge_app_sections, App, AppInfo, Key,
+        KeyInterner, KeyValueOptions, KeyValues, Map, ParseStats, ParsedSoFar, SkippedApp, SpanMap,
+        Value, VdfrError, WideString, VBKV_MAGIC,
     },
-    AppInfoVersion, Package, PackageInfo, PkgInfoVersion, SHA1,
+    AppInfoVersion, BinType, Color, Package, PackageInfo, PkgInfoVersion, Pointer, SHA1,
 };
 
 fn throw_nom_error(error: nom::Err<nom::error::Error<&[u8]>>) -> VdfrError {
     // clone the error to avoid lifetime issues
     match &error {
+        nom::Err::Error(e) | nom::Err::Failure(e) if e.code == ErrorKind::Eof => {
+            VdfrError::Truncated
+        }
         nom::Err::Error(e) | nom::Err::Failure(e) => {
             // get like 64 bytes of data to show in the error message
             let str_data = if let nom::Err::Error(_) = &error {
@@ -32,20 +42,14 @@ fn throw_nom_error(error: nom::Err<nom::error::Error<&[u8]>>) -> VdfrError {
 
             VdfrError::NomError(format!("{}: {:?}, data: {:?}", str_data, e.code, data))
         }
-        nom::Err::Incomplete(e) => {
-            let need_amount = if let nom::Needed::Size(amount) = e {
-                format!("{} bytes", amount)
-            } else {
-                "unknown amount".to_string()
-            };
-
-            VdfrError::NomError(format!("Incomplete data, need: {}", need_amount))
-        }
+        nom::Err::Incomplete(_) => VdfrError::Truncated,
     }
 }
 
 struct VdfrNomError {
     message: String,
+    // Structured error to surface instead of the message, if any
+    error: Option<VdfrError>,
 }
 
 fn format_data(data: &[u8]) -> &[u8] {
@@ -58,113 +62,666 @@ fn format_data(data: &[u8]) -> &[u8] {
 
 impl ParseError<&[u8]> for VdfrNomError {
     fn from_error_kind(input: &[u8], kind: nom::error::ErrorKind) -> Self {
+        // Running out of data, or a string without its terminator
+        let truncated = matches!(kind, ErrorKind::Eof | ErrorKind::TakeUntil);
         VdfrNomError {
             message: format!("Error: {:?}, data: {:?}", kind, format_data(input)),
+            error: truncated.then_some(VdfrError::Truncated),
         }
     }
 
     // if combining multiple errors, we show them one after the other
     fn append(input: &[u8], kind: ErrorKind, other: Self) -> Self {
         let message = format!("{}{:?}:\t{:?}\n", other.message, kind, format_data(input));
-        println!("{}", message);
-        VdfrNomError { message }
+        VdfrNomError {
+            message,
+            error: other.error,
+        }
     }
 
     fn from_char(input: &[u8], c: char) -> Self {
         let message = format!("'{}':\t{:?}\n", c, format_data(input));
-        println!("{}", message);
-        VdfrNomError { message }
+        VdfrNomError {
+            message,
+            error: None,
+        }
     }
 
     fn or(self, other: Self) -> Self {
         let message = format!("{}\tOR\n{}\n", self.message, other.message);
-        println!("{}", message);
-        VdfrNomError { message }
+        VdfrNomError {
+            message,
+            error: self.error.or(other.error),
+        }
     }
 }
 
 impl VdfrNomError {
-    fn with_message(&self, input: &str) -> Self {
+    fn with_message(self, input: &str) -> Self {
         VdfrNomError {
             message: format!("{}:\n{}", input, self.message),
+            error: self.error,
+        }
+    }
+
+    fn limit_exceeded(input: &[u8], limit: &'static str, max: usize) -> nom::Err<Self> {
+        nom::Err::Failure(VdfrNomError {
+            message: format!(
+                "Limit {} exceeded (max {}), data: {:?}",
+                limit,
+                max,
+                format_data(input)
+            ),
+            error: Some(VdfrError::LimitExceeded(limit, max)),
+        })
+    }
+
+    fn depth_exceeded(input: &[u8], max: usize) -> nom::Err<Self> {
+        nom::Err::Failure(VdfrNomError {
+            message: format!(
+                "Limit max_depth exceeded (max {}), data: {:?}",
+                max,
+                format_data(input)
+            ),
+            error: Some(VdfrError::DepthLimitExceeded(max)),
+        })
+    }
+
+    fn with_error(self, error: VdfrError) -> Self {
+        VdfrNomError {
+            error: Some(error),
+            ..self
         }
     }
 }
 
 fn throw_nom_custom_error(error: nom::Err<VdfrNomError>) -> VdfrError {
     match error {
-        nom::Err::Error(e) | nom::Err::Failure(e) => VdfrError::NomError(e.message),
-        nom::Err::Incomplete(e) => {
-            let need_amount = if let nom::Needed::Size(amount) = e {
-                format!("{} bytes", amount)
-            } else {
-                "unknown amount".to_string()
-            };
-
-            VdfrError::NomError(format!("Incomplete data, need: {}", need_amount))
-        }
+        nom::Err::Error(e) | nom::Err::Failure(e) => match e.error {
+            Some(error) => error,
+            None => VdfrError::NomError(e.message),
+        },
+        nom::Err::Incomplete(_) => VdfrError::Truncated,
     }
 }
 
-pub fn parse_app_info(data: &[u8]) -> Result<AppInfo, VdfrError> {
-    let (data, (version, universe)) = (le_u32, le_u32).parse(data).map_err(throw_nom_error)?;
-    let version: AppInfoVersion = version.try_into()?;
+pub(crate) struct AppInfoHeader<'a> {
+    pub(crate) version: AppInfoVersion,
+    pub(crate) universe: u32,
+    pub(crate) payload: &'a [u8],
+    pub(crate) options: KeyValueOptions,
+    // Unread bytes after the string pool
+    pub(crate) pool_trailing: usize,
+}
+
+pub(crate) fn parse_app_info_header<'a>(
+    data: &'a [u8],
+    options: &KeyValueOptions,
+) -> Result<AppInfoHeader<'a>, VdfrError> {
+    parse_app_info_header_as(data, options, None)
+}
 
-    let (payloads, options) = match version {
-        AppInfoVersion::V27 | AppInfoVersion::V28 => (data, KeyValueOptions::default()),
+/// Parse the header, using the layout of the given version instead of the magic when set.
+fn parse_app_info_header_as<'a>(
+    data: &'a [u8],
+    options: &KeyValueOptions,
+    hint: Option<AppInfoVersion>,
+) -> Result<AppInfoHeader<'a>, VdfrError> {
+    let (data, (magic, universe)) = (le_u32, le_u32).parse(data).map_err(throw_nom_error)?;
+    let version: AppInfoVersion = match hint {
+        Some(version) => version,
+        None => magic.try_into()?,
+    };
+
+    let (payload, options, pool_trailing) = match version {
+        AppInfoVersion::V24 | AppInfoVersion::V26 | AppInfoVersion::V27 | AppInfoVersion::V28 => (
+            data,
+            KeyValueOptions {
+                string_pool: Vec::new(),
+                ..options.clone()
+            },
+            0,
+        ),
         AppInfoVersion::V29 => {
             let (data, offset) = le_i64(data).map_err(throw_nom_error)?;
 
             // Use nom to jump to offset_table and read the string pool
             // data is the remaining data after reading version, universe, and offset.
             // to ensure we actually jump to the offset, we need to subtract the amount of data read so far.
-            let read_amount = 4usize + 4 + 8;
-            let offset_actual = (offset as usize) - read_amount;
+            let read_amount = 4i64 + 4 + 8;
+            let offset_actual = offset
+                .checked_sub(read_amount)
+                .and_then(|o| usize::try_from(o).ok())
+                .ok_or(VdfrError::InvalidOffset(offset))?;
             // Left side, is the remainder which is the string pools, while payload is the actual data.
             let (string_pools, payload) = take(offset_actual)(data).map_err(throw_nom_error)?;
             let (string_pools, count) = le_u32(string_pools).map_err(throw_nom_error)?;
+            if count as usize > options.limits.max_pool_entries {
+                return Err(VdfrError::LimitExceeded(
+                    "max_pool_entries",
+                    options.limits.max_pool_entries,
+                ));
+            }
 
-            let (_, string_pool) =
-                read_string_pools(string_pools, count as usize).map_err(throw_nom_custom_error)?;
+            let (pool_rest, string_pool) = read_string_pools(string_pools, count as usize, options)
+                .map_err(throw_nom_custom_error)?;
 
             (
                 payload,
                 KeyValueOptions {
                     string_pool,
-                    alt_format: false,
+                    ..options.clone()
                 },
+                pool_rest.len(),
             )
         }
     };
 
-    let (_, mut apps) = parse_apps(payloads, &options, &version).map_err(throw_nom_custom_error)?;
+    Ok(AppInfoHeader {
+        version,
+        universe,
+        payload,
+        options,
+        pool_trailing,
+    })
+}
+
+pub fn parse_app_info(data: &[u8]) -> Result<AppInfo, VdfrError> {
+    parse_app_info_with_options(data, &KeyValueOptions::default())
+}
+
+/// Parse app info with the given options.
+///
+/// The string pool of the options is ignored, v29 files use their own pool.
+pub fn parse_app_info_with_options(
+    data: &[u8],
+    options: &KeyValueOptions,
+) -> Result<AppInfo, VdfrError> {
+    parse_app_info_with_progress(data, options, None)
+}
+
+/// Parse app info with the given options, reporting the progress after every app.
+///
+/// Meant for frontends rendering a progress bar while parsing large files.
+pub fn parse_app_info_with_progress(
+    data: &[u8],
+    options: &KeyValueOptions,
+    progress: Option<&mut dyn FnMut(ParsedSoFar)>,
+) -> Result<AppInfo, VdfrError> {
+    let header = parse_app_info_header(data, options)?;
+    let progress = progress.map(|callback| Progress::new(data, callback));
+    let mut keys = KeyInterner::new(options.intern_keys);
+    parse_app_info_payload(header, &mut keys, progress)
+}
+
+/// Parse app info with the given options, also returning metrics of the parse.
+pub fn parse_app_info_with_stats(
+    data: &[u8],
+    options: &KeyValueOptions,
+) -> Result<(AppInfo, ParseStats), VdfrError> {
+    let header = parse_app_info_header(data, options)?;
+    let mut keys = KeyInterner::with_stats(options.intern_keys);
+    let app_info = parse_app_info_payload(header, &mut keys, None)?;
+    Ok((app_info, keys.stats.unwrap_or_default()))
+}
+
+/// Parse app info with the layout of the given version, without checking the magic.
+///
+/// Meant for files with a magic that isn't known yet, but which are expected to share
+/// the layout of a known version. The returned app info carries the given version.
+pub fn parse_app_info_with_hint(
+    data: &[u8],
+    version: AppInfoVersion,
+) -> Result<AppInfo, VdfrError> {
+    let options = KeyValueOptions::default();
+    let header = parse_app_info_header_as(data, &options, Some(version))?;
+    let mut keys = KeyInterner::new(options.intern_keys);
+    parse_app_info_payload(header, &mut keys, None)
+}
+
+fn parse_app_info_payload(
+    header: AppInfoHeader,
+    keys: &mut KeyInterner,
+    progress: Option<Progress>,
+) -> Result<AppInfo, VdfrError> {
+    let (rest, (apps, terminated)) = parse_apps(
+        header.payload,
+        &header.options,
+        &header.version,
+        keys,
+        progress,
+    )
+    .map_err(throw_nom_custom_error)?;
 
-    // Remove the empty app (0)
-    apps.remove(&0);
+    let trailing_bytes = rest.len() + header.pool_trailing;
+    header
+        .options
+        .check_section_end(terminated, trailing_bytes)?;
 
     Ok(AppInfo {
-        version,
-        universe,
+        version: header.version,
+        universe: header.universe,
         apps,
+        trailing_bytes,
     })
 }
 
+/// Parse app info, also returning the byte range of every app in the file.
+///
+/// With `record_nodes`, the range of every key-values node is recorded too, by its key path.
+/// Nodes are not recorded for v24 apps, since their sections are merged into a new tree.
+pub fn parse_app_info_with_spans(
+    data: &[u8],
+    record_nodes: bool,
+) -> Result<(AppInfo, SpanMap), VdfrError> {
+    let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+    let options = &header.options;
+    let version = &header.version;
+
+    let mut keys = KeyInterner::new(options.intern_keys);
+    let mut apps = Map::new();
+    let mut spans = SpanMap::default();
+    let base = data.as_ptr() as usize;
+
+    let mut rest = header.payload;
+    loop {
+        let start = rest.as_ptr() as usize - base;
+        let (next, mut app) = match parse_app_header(rest, version) {
+            Ok((next, app)) if app.id == 0 => {
+                rest = next;
+                break;
+            }
+            Ok(parsed) => parsed,
+            // Data that isn't an app, left as trailing bytes like `parse_apps`
+            Err(nom::Err::Error(_)) => break,
+            Err(error) => return Err(throw_nom_custom_error(error)),
+        };
+
+        let next = if *version == AppInfoVersion::V24 || !record_nodes {
+            let (next, parsed) =
+                parse_app(rest, options, version, &mut keys).map_err(throw_nom_custom_error)?;
+            app = parsed;
+            next
+        } else {
+            let mut recorder = SpanRecorder {
+                base,
+                path: Vec::new(),
+                spans: BTreeMap::new(),
+            };
+            let (next, key_values) = parse_bytes_kv_node(
+                next,
+                options,
+                &mut keys,
+                1,
+                &mut TreeState::default(),
+                Some(&mut recorder),
+            )
+            .map_err(throw_nom_custom_error)?;
+            app.key_values = key_values;
+            spans.nodes.insert(app.id, recorder.spans);
+            next
+        };
+
+        spans
+            .apps
+            .insert(app.id, start..next.as_ptr() as usize - base);
+        apps.insert(app.id, app);
+        rest = next;
+    }
+
+    let trailing_bytes = rest.len() + header.pool_trailing;
+    Ok((
+        AppInfo {
+            version: header.version,
+            universe: header.universe,
+            apps,
+            trailing_bytes,
+        },
+        spans,
+    ))
+}
+
+/// Parse app info while skipping over apps that fail to parse.
+///
+/// When an app's key-values are corrupt, the error is recorded and parsing
+/// resumes at the next app header, located with the app's `size` field.
+/// Only errors in the file header (magic, universe, string pool) are fatal.
+pub fn parse_app_info_resilient(data: &[u8]) -> Result<(AppInfo, Vec<SkippedApp>), VdfrError> {
+    let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+    let options = &header.options;
+    let version = &header.version;
+
+    let mut keys = KeyInterner::new(options.intern_keys);
+    let mut apps = Map::new();
+    let mut skipped = Vec::new();
+
+    let mut rest = header.payload;
+    while !rest.is_empty() {
+        // The v29 payload ends before the string pool, so count from the start of the data
+        let offset = rest.as_ptr() as usize - data.as_ptr() as usize;
+        match parse_app(rest, options, version, &mut keys) {
+            Ok((next, app)) if app.id == 0 => {
+                rest = next;
+                break;
+            }
+            Ok((next, app)) => {
+                apps.insert(app.id, app);
+                rest = next;
+            }
+            Err(error) => {
+                let error = throw_nom_custom_error(error);
+                // The size field covers everything in the app after itself
+                let app_header: IResult<&[u8], (u32, u32)> = (le_u32, le_u32).parse(rest);
+                match app_header {
+                    Ok((after_size, (id, size))) if (size as usize) <= after_size.len() => {
+                        skipped.push(SkippedApp { id, offset, error });
+                        rest = &after_size[size as usize..];
+                    }
+                    Ok((_, (id, _))) => {
+                        skipped.push(SkippedApp { id, offset, error });
+                        break;
+                    }
+                    Err(_) => {
+                        skipped.push(SkippedApp {
+                            id: 0,
+                            offset,
+                            error,
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok((
+        AppInfo {
+            version: header.version,
+            universe: header.universe,
+            apps,
+            trailing_bytes: rest.len() + header.pool_trailing,
+        },
+        skipped,
+    ))
+}
+
+/// Parse app info, only decoding the apps accepted by the predicate.
+///
+/// Rejected apps are skipped with their `size` field without reading their key-values,
+/// so picking a few apps out of a large file is much faster than a full parse.
+pub fn parse_app_info_filtered<F>(data: &[u8], mut predicate: F) -> Result<AppInfo, VdfrError>
+where
+    F: FnMut(u32) -> bool,
+{
+    let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+    let options = &header.options;
+    let version = &header.version;
+
+    let mut keys = KeyInterner::new(options.intern_keys);
+    let mut apps = Map::new();
+
+    let mut rest = header.payload;
+    loop {
+        let (after_id, app_id) = le_u32::<_, VdfrNomError>(rest).map_err(throw_nom_custom_error)?;
+        if app_id == 0 {
+            rest = after_id;
+            break;
+        }
+
+        if predicate(app_id) {
+            let (next, app) =
+                parse_app(rest, options, version, &mut keys).map_err(throw_nom_custom_error)?;
+            apps.insert(app.id, app);
+            rest = next;
+        } else {
+            // The size field covers everything in the app after itself
+            let (after_size, size) =
+                le_u32::<_, VdfrNomError>(after_id).map_err(throw_nom_custom_error)?;
+            let (next, _) = take::<_, _, VdfrNomError>(size as usize)(after_size)
+                .map_err(throw_nom_custom_error)?;
+            rest = next;
+        }
+    }
+
+    Ok(AppInfo {
+        version: header.version,
+        universe: header.universe,
+        apps,
+        trailing_bytes: rest.len() + header.pool_trailing,
+    })
+}
+
+/// Parse a single app starting at `offset` in the app info data.
+///
+/// The offset points at the app ID, and for v29 files the options must
+/// carry the string pool of the file.
+pub fn parse_app_at(
+    data: &[u8],
+    offset: usize,
+    options: &KeyValueOptions,
+    version: AppInfoVersion,
+) -> Result<App, VdfrError> {
+    let record = data
+        .get(offset..)
+        .ok_or(VdfrError::InvalidOffset(offset as i64))?;
+
+    let mut keys = KeyInterner::new(options.intern_keys);
+    let (_, app) =
+        parse_app(record, options, &version, &mut keys).map_err(throw_nom_custom_error)?;
+    Ok(app)
+}
+
+/// Iterate over the apps of an app info file, parsing each one as it's reached.
+///
+/// Only the app being parsed is held in memory, which keeps streaming large files
+/// bounded. The header (and the v29 string pool) is read upfront.
+pub fn iter_app_info(data: &[u8]) -> Result<AppInfoIter<'_>, VdfrError> {
+    let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+    let keys = KeyInterner::new(header.options.intern_keys);
+    Ok(AppInfoIter {
+        rest: header.payload,
+        header,
+        keys,
+        done: false,
+    })
+}
+
+/// Apps of an app info file in file order, see [`iter_app_info`].
+///
+/// Iteration stops at the terminator, or after the first error.
+pub struct AppInfoIter<'a> {
+    header: AppInfoHeader<'a>,
+    rest: &'a [u8],
+    keys: KeyInterner,
+    done: bool,
+}
+
+impl AppInfoIter<'_> {
+    pub fn version(&self) -> AppInfoVersion {
+        self.header.version
+    }
+
+    pub fn universe(&self) -> u32 {
+        self.header.universe
+    }
+}
+
+impl Iterator for AppInfoIter<'_> {
+    type Item = Result<App, VdfrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let header = &self.header;
+        let (rest, terminated) =
+            match parse_app(self.rest, &header.options, &header.version, &mut self.keys) {
+                Ok((next, app)) if app.id != 0 => {
+                    self.rest = next;
+                    return Some(Ok(app));
+                }
+                Ok((next, _)) => (next, true),
+                // Data that isn't an app, left as trailing bytes like `parse_apps`
+                Err(nom::Err::Error(_)) => (self.rest, false),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(throw_nom_custom_error(error)));
+                }
+            };
+        self.done = true;
+        let trailing_bytes = rest.len() + header.pool_trailing;
+        header
+            .options
+            .check_section_end(terminated, trailing_bytes)
+            .err()
+            .map(Err)
+    }
+}
+
+/// Reports the progress of a parse to a callback, from the position in the file.
+struct Progress<'p> {
+    callback: &'p mut dyn FnMut(ParsedSoFar),
+    /// Address of the start of the file, the consumed bytes are relative to it.
+    base: usize,
+    total_bytes: usize,
+    items: usize,
+}
+
+impl<'p> Progress<'p> {
+    fn new(data: &[u8], callback: &'p mut dyn FnMut(ParsedSoFar)) -> Self {
+        Progress {
+            callback,
+            base: data.as_ptr() as usize,
+            total_bytes: data.len(),
+            items: 0,
+        }
+    }
+
+    /// Report one more parsed item, with `rest` starting after it.
+    fn report(&mut self, rest: &[u8]) {
+        self.items += 1;
+        (self.callback)(ParsedSoFar {
+            items: self.items,
+            bytes_consumed: rest.as_ptr() as usize - self.base,
+            total_bytes: self.total_bytes,
+        });
+    }
+}
+
+/// Parse apps until the zero app ID terminator.
+///
+/// Also returns whether the terminator was found, or parsing stopped at data that isn't an app.
 fn parse_apps<'a>(
     data: &'a [u8],
-    options: &'a KeyValueOptions,
-    version: &'a AppInfoVersion,
-) -> IResult<&'a [u8], BTreeMap<u32, App>, VdfrNomError> {
-    let (rest, apps) = many0(|d| parse_app(d, options, version)).parse(data)?;
-
-    let hash_apps: BTreeMap<u32, App> = apps.into_iter().map(|app| (app.id, app)).collect();
+    options: &KeyValueOptions,
+    version: &AppInfoVersion,
+    keys: &mut KeyInterner,
+    mut progress: Option<Progress>,
+) -> IResult<&'a [u8], (Map<u32, App>, bool), VdfrNomError> {
+    let mut apps = Map::new();
 
-    Ok((rest, hash_apps))
+    let mut rest = data;
+    loop {
+        match parse_app(rest, options, version, keys) {
+            // End of apps
+            Ok((next, app)) if app.id == 0 => return Ok((next, (apps, true))),
+            Ok((next, app)) => {
+                apps.insert(app.id, app);
+                rest = next;
+                if let Some(progress) = progress.as_mut() {
+                    progress.report(rest);
+                }
+            }
+            Err(nom::Err::Error(_)) => return Ok((rest, (apps, false))),
+            Err(error) => return Err(error),
+        }
+    }
 }
 
 fn parse_app<'a>(
     data: &'a [u8],
-    options: &'a KeyValueOptions,
-    version: &'a AppInfoVersion,
+    options: &KeyValueOptions,
+    version: &AppInfoVersion,
+    keys: &mut KeyInterner,
+) -> IResult<&'a [u8], App, VdfrNomError> {
+    let (rest, mut app) = parse_app_record(data, options, version, keys)?;
+    if options.keep_raw && app.id != 0 {
+        app.raw = Some(data[..data.len() - rest.len()].to_vec());
+    }
+    Ok((rest, app))
+}
+
+fn parse_app_record<'a>(
+    data: &'a [u8],
+    options: &KeyValueOptions,
+    version: &AppInfoVersion,
+    keys: &mut KeyInterner,
+) -> IResult<&'a [u8], App, VdfrNomError> {
+    let (data, app) = parse_app_header(data, version)?;
+    if app.id == 0 {
+        return Ok((data, app));
+    }
+
+    if options.tolerate_unknown_types {
+        // The size bounds the key-values, so the next app is found after unknown values
+        let len = (app.size as usize).saturating_sub(version.app_header_size() - 8);
+        let (rest, payload) = take(len)(data)?;
+        let (_, app) = parse_app_payload(payload, options, version, keys, app)?;
+        return Ok((rest, app));
+    }
+    parse_app_payload(data, options, version, keys, app)
+}
+
+/// Parse the key-values of an app after its header.
+fn parse_app_payload<'a>(
+    data: &'a [u8],
+    options: &KeyValueOptions,
+    version: &AppInfoVersion,
+    keys: &mut KeyInterner,
+    mut app: App,
+) -> IResult<&'a [u8], App, VdfrNomError> {
+    if *version == AppInfoVersion::V24 {
+        let (data, sections) = parse_app_sections(data, options, keys)?;
+        app.key_values = merge_app_sections(app.id, sections, !options.keep_sequences);
+        return Ok((data, app));
+    }
+
+    let (data, key_values) = parse_bytes_kv(data, options, keys)?;
+    app.key_values = key_values;
+    Ok((data, app))
+}
+
+/// Parse the sections of a v24 app, each prefixed by its type until a zero type.
+fn parse_app_sections<'a>(
+    data: &'a [u8],
+    options: &KeyValueOptions,
+    keys: &mut KeyInterner,
+) -> IResult<&'a [u8], Vec<(u8, KeyValues)>, VdfrNomError> {
+    let mut sections = Vec::new();
+
+    let mut rest = data;
+    let mut rest_taken = false;
+    loop {
+        // Unknown values take the rest of the data, including the sections after them
+        if rest.is_empty() && rest_taken {
+            return Ok((rest, sections));
+        }
+        let (next, section) = le_u8(rest)?;
+        if section == 0 {
+            return Ok((next, sections));
+        }
+        let mut tree = TreeState::default();
+        let (next, key_values) = parse_bytes_kv_node(next, options, keys, 1, &mut tree, None)?;
+        rest_taken = tree.rest_taken;
+        sections.push((section, key_values));
+        rest = next;
+    }
+}
+
+/// Parse the header of an app, leaving the key-values empty.
+fn parse_app_header<'a>(
+    data: &'a [u8],
+    version: &AppInfoVersion,
 ) -> IResult<&'a [u8], App, VdfrNomError> {
     let (data, app_id) = le_u32(data)?;
 
@@ -178,32 +735,36 @@ fn parse_app<'a>(
                 state: 0,
                 last_update: 0,
                 access_token: 0,
-                checksum_txt: SHA1::default(),
-                checksum_bin: Some(SHA1::default()),
+                checksum_txt: SHA1::zero(),
+                checksum_bin: Some(SHA1::zero()),
                 change_number: 0,
-                key_values: BTreeMap::new(),
+                key_values: KeyValues::new(),
+                raw: None,
             },
         ))
     } else {
-        let (data, (size, state, last_update, access_token)) =
-            (le_u32, le_u32, le_u32, le_u64).parse(data)?;
+        let (data, (size, state, last_update)) = (le_u32, le_u32, le_u32).parse(data)?;
+        let (data, access_token) = match version {
+            AppInfoVersion::V24 => (data, 0),
+            _ => le_u64(data)?,
+        };
 
-        let (data, checksum_txt) = take(20usize)(data)?;
+        let (data, checksum_txt) = match version {
+            AppInfoVersion::V24 | AppInfoVersion::V26 => (data, SHA1::zero()),
+            _ => parse_sha1(data)?,
+        };
         let (data, change_number) = le_u32(data)?;
         let (data, checksum_bin) = match version {
-            AppInfoVersion::V27 => {
+            AppInfoVersion::V24 | AppInfoVersion::V26 | AppInfoVersion::V27 => {
                 // we skip checksum_bin
                 (data, None)
             }
             _ => {
-                let (data, checksum_bin) = take(20usize)(data)?;
-                (data, Some(SHA1::new(checksum_bin.try_into().unwrap())))
+                let (data, checksum_bin) = parse_sha1(data)?;
+                (data, Some(checksum_bin))
             }
         };
 
-        let (data, key_values) = parse_bytes_kv(data, options)?;
-        let key_values = map_keyvalues_sequence(&key_values);
-
         Ok((
             data,
             App {
@@ -212,262 +773,647 @@ fn parse_app<'a>(
                 state,
                 last_update,
                 access_token,
-                checksum_txt: SHA1::new(checksum_txt.try_into().unwrap()),
+                checksum_txt,
                 checksum_bin,
                 change_number,
-                key_values,
+                key_values: KeyValues::new(),
+                raw: None,
             },
         ))
     }
 }
 
+impl AppInfo {
+    /// Update the app info from a newer version of the file, only decoding the apps that changed.
+    ///
+    /// Apps are compared by their change number and checksums, apps missing from the new data
+    /// are removed. Returns the IDs of the apps that were added or changed.
+    pub fn update_from(&mut self, data: &[u8]) -> Result<Vec<u32>, VdfrError> {
+        let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+        let options = &header.options;
+        let version = &header.version;
+
+        let mut keys = KeyInterner::new(options.intern_keys);
+        let mut seen = BTreeSet::new();
+        let mut updated = Vec::new();
+
+        let mut rest = header.payload;
+        loop {
+            let (after_header, app) =
+                parse_app_header(rest, version).map_err(throw_nom_custom_error)?;
+            if app.id == 0 {
+                rest = after_header;
+                break;
+            }
+            seen.insert(app.id);
+
+            match self.apps.get_mut(&app.id) {
+                Some(old)
+                    if old.change_number == app.change_number
+                        && old.checksum_txt == app.checksum_txt
+                        && old.checksum_bin == app.checksum_bin =>
+                {
+                    old.size = app.size;
+                    old.state = app.state;
+                    old.last_update = app.last_update;
+                    old.access_token = app.access_token;
+
+                    // The size field covers everything in the app after itself
+                    let (next, _) = take::<_, _, VdfrNomError>(8 + app.size as usize)(rest)
+                        .map_err(throw_nom_custom_error)?;
+                    rest = next;
+                }
+                _ => {
+                    let (next, app) = parse_app(rest, options, version, &mut keys)
+                        .map_err(throw_nom_custom_error)?;
+                    updated.push(app.id);
+                    self.apps.insert(app.id, app);
+                    rest = next;
+                }
+            }
+        }
+
+        self.apps.retain(|id, _| seen.contains(id));
+        self.version = header.version;
+        self.universe = header.universe;
+        self.trailing_bytes = rest.len() + header.pool_trailing;
+
+        Ok(updated)
+    }
+}
+
 pub fn parse_package_info(data: &[u8]) -> Result<PackageInfo, VdfrError> {
+    parse_package_info_with_options(data, &KeyValueOptions::default())
+}
+
+/// Parse package info with the given options.
+pub fn parse_package_info_with_options(
+    data: &[u8],
+    options: &KeyValueOptions,
+) -> Result<PackageInfo, VdfrError> {
+    parse_package_info_with_progress(data, options, None)
+}
+
+/// Parse package info with the given options, reporting the progress after every package.
+pub fn parse_package_info_with_progress(
+    data: &[u8],
+    options: &KeyValueOptions,
+    progress: Option<&mut dyn FnMut(ParsedSoFar)>,
+) -> Result<PackageInfo, VdfrError> {
+    let progress = progress.map(|callback| Progress::new(data, callback));
+    let mut keys = KeyInterner::new(options.intern_keys);
+    parse_package_info_inner(data, options, &mut keys, progress)
+}
+
+/// Parse package info with the given options, also returning metrics of the parse.
+pub fn parse_package_info_with_stats(
+    data: &[u8],
+    options: &KeyValueOptions,
+) -> Result<(PackageInfo, ParseStats), VdfrError> {
+    let mut keys = KeyInterner::with_stats(options.intern_keys);
+    let package_info = parse_package_info_inner(data, options, &mut keys, None)?;
+    Ok((package_info, keys.stats.unwrap_or_default()))
+}
+
+fn parse_package_info_inner(
+    data: &[u8],
+    options: &KeyValueOptions,
+    keys: &mut KeyInterner,
+    progress: Option<Progress>,
+) -> Result<PackageInfo, VdfrError> {
     let (data, (version, universe)) = (le_u32, le_u32).parse(data).map_err(throw_nom_error)?;
     let version: PkgInfoVersion = version.try_into()?;
 
-    let (_, mut packages) = parse_packages(data, &KeyValueOptions::default(), &version)
-        .map_err(throw_nom_custom_error)?;
+    let (rest, (packages, terminated)) =
+        parse_packages(data, options, &version, keys, progress).map_err(throw_nom_custom_error)?;
 
-    packages.remove(&0xffffffff); // Remove the empty package (0xffffffff
+    let trailing_bytes = rest.len();
+    options.check_section_end(terminated, trailing_bytes)?;
 
     Ok(PackageInfo {
         version,
         universe,
         packages,
+        trailing_bytes,
+    })
+}
+
+/// Iterate over the packages of a package info file, parsing each one as it's reached.
+///
+/// Only the package being parsed is held in memory, like [`iter_app_info`].
+pub fn iter_package_info(data: &[u8]) -> Result<PackageInfoIter<'_>, VdfrError> {
+    let (rest, (version, universe)) = (le_u32, le_u32).parse(data).map_err(throw_nom_error)?;
+    let options = KeyValueOptions::default();
+    Ok(PackageInfoIter {
+        version: version.try_into()?,
+        universe,
+        rest,
+        keys: KeyInterner::new(options.intern_keys),
+        options,
+        done: false,
     })
 }
 
+/// Packages of a package info file in file order, see [`iter_package_info`].
+///
+/// Iteration stops at the terminator, or after the first error.
+pub struct PackageInfoIter<'a> {
+    version: PkgInfoVersion,
+    universe: u32,
+    rest: &'a [u8],
+    options: KeyValueOptions,
+    keys: KeyInterner,
+    done: bool,
+}
+
+impl PackageInfoIter<'_> {
+    pub fn version(&self) -> PkgInfoVersion {
+        self.version
+    }
+
+    pub fn universe(&self) -> u32 {
+        self.universe
+    }
+}
+
+impl Iterator for PackageInfoIter<'_> {
+    type Item = Result<Package, VdfrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (rest, terminated) =
+            match parse_package(self.rest, &self.options, &self.version, &mut self.keys) {
+                Ok((next, package)) if package.id != 0xffffffff => {
+                    self.rest = next;
+                    return Some(Ok(package));
+                }
+                Ok((next, _)) => (next, true),
+                Err(nom::Err::Error(_)) => (self.rest, false),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(throw_nom_custom_error(error)));
+                }
+            };
+        self.done = true;
+        self.options
+            .check_section_end(terminated, rest.len())
+            .err()
+            .map(Err)
+    }
+}
+
+/// Parse packages until the `0xffffffff` package ID terminator.
+///
+/// Also returns whether the terminator was found, or parsing stopped at data that isn't a package.
 fn parse_packages<'a>(
     data: &'a [u8],
-    options: &'a KeyValueOptions,
-    version: &'a PkgInfoVersion,
-) -> IResult<&'a [u8], BTreeMap<u32, Package>, VdfrNomError> {
-    let (rest, packages) = many0(|d| parse_package(d, options, version)).parse(data)?;
+    options: &KeyValueOptions,
+    version: &PkgInfoVersion,
+    keys: &mut KeyInterner,
+    mut progress: Option<Progress>,
+) -> IResult<&'a [u8], (Map<u32, Package>, bool), VdfrNomError> {
+    let mut packages = Map::new();
 
-    let hash_packages: BTreeMap<u32, Package> =
-        packages.into_iter().map(|app| (app.id, app)).collect();
-
-    Ok((rest, hash_packages))
+    let mut rest = data;
+    loop {
+        match parse_package(rest, options, version, keys) {
+            // End of packages
+            Ok((next, package)) if package.id == 0xffffffff => return Ok((next, (packages, true))),
+            Ok((next, package)) => {
+                packages.insert(package.id, package);
+                rest = next;
+                if let Some(progress) = progress.as_mut() {
+                    progress.report(rest);
+                }
+            }
+            Err(nom::Err::Error(_)) => return Ok((rest, (packages, false))),
+            Err(error) => return Err(error),
+        }
+    }
 }
 
 fn parse_package<'a>(
     data: &'a [u8],
-    options: &'a KeyValueOptions,
-    version: &'a PkgInfoVersion,
+    options: &KeyValueOptions,
+    version: &PkgInfoVersion,
+    keys: &mut KeyInterner,
 ) -> IResult<&'a [u8], Package, VdfrNomError> {
     let (data, package_id) = le_u32(data)?;
     if package_id == 0xffffffff {
-        let pics = match version {
-            PkgInfoVersion::V27 => None,
-            PkgInfoVersion::V28 => Some(0),
-        };
+        let token = version.has_token().then_some(0);
         return Ok((
             data,
             Package {
                 id: 0xffffffff,
-                checksum: SHA1::default(),
+                checksum: SHA1::zero(),
                 change_number: 0,
-                pics,
-                key_values: BTreeMap::new(),
+                token,
+                key_values: KeyValues::new(),
             },
         ));
     }
 
-    let (data, checksum) = take(20usize)(data)?;
+    let (data, checksum) = parse_sha1(data)?;
     let (data, change_number) = le_u32(data)?;
-    let (data, pics) = match version {
-        PkgInfoVersion::V27 => (data, None),
-        PkgInfoVersion::V28 => {
-            let (data, pics) = le_u64(data)?;
-            (data, Some(pics))
-        }
+    let (data, token) = if version.has_token() {
+        let (data, token) = le_u64(data)?;
+        (data, Some(token))
+    } else {
+        (data, None)
     };
 
-    let (data, key_values) = parse_bytes_kv(data, options)?;
-    let key_values = map_keyvalues_sequence(&key_values);
+    let (data, key_values) = parse_bytes_kv(data, options, keys)?;
 
     Ok((
         data,
         Package {
             id: package_id,
-            checksum: SHA1::new(checksum.try_into().unwrap()),
+            checksum,
             change_number,
-            pics,
+            token,
             key_values,
         },
     ))
 }
 
 pub fn parse_keyvalues(data: &[u8]) -> Result<KeyValues, VdfrError> {
-    let (_, key_values) =
-        parse_bytes_kv(data, &KeyValueOptions::default()).map_err(throw_nom_custom_error)?;
-    let key_values = map_keyvalues_sequence(&key_values);
+    parse_keyvalues_with_options(data, &KeyValueOptions::default())
+}
+
+/// Parse key-values with the given options.
+pub fn parse_keyvalues_with_options(
+    data: &[u8],
+    options: &KeyValueOptions,
+) -> Result<KeyValues, VdfrError> {
+    let mut keys = KeyInterner::new(options.intern_keys);
+    parse_keyvalues_inner(data, options, &mut keys)
+}
+
+fn parse_keyvalues_inner(
+    data: &[u8],
+    options: &KeyValueOptions,
+    keys: &mut KeyInterner,
+) -> Result<KeyValues, VdfrError> {
+    let (rest, key_values) = parse_bytes_kv(data, options, keys).map_err(throw_nom_custom_error)?;
+    options.check_section_end(true, rest.len())?;
     Ok(key_values)
 }
 
+pub fn parse_vbkv(data: &[u8]) -> Result<KeyValues, VdfrError> {
+    parse_vbkv_with_options(data, &KeyValueOptions::default())
+}
+
+/// Parse key-values in the `VBKV` container, verifying the CRC-32 of the payload.
+pub fn parse_vbkv_with_options(
+    data: &[u8],
+    options: &KeyValueOptions,
+) -> Result<KeyValues, VdfrError> {
+    parse_keyvalues_with_options(vbkv_payload(data)?, options)
+}
+
+/// The payload of a `VBKV` container, once its magic and checksum are verified.
+fn vbkv_payload(data: &[u8]) -> Result<&[u8], VdfrError> {
+    let (payload, (magic, checksum)) = (le_u32, le_u32).parse(data).map_err(throw_nom_error)?;
+    if magic != VBKV_MAGIC {
+        return Err(VdfrError::UnknownMagic(magic));
+    }
+
+    let actual = crc32(payload);
+    if actual != checksum {
+        return Err(VdfrError::ChecksumMismatch(checksum, actual));
+    }
+    Ok(payload)
+}
+
+/// A key-value parser reusable across many small blobs, like network messages or
+/// cache entries.
+///
+/// The only state kept between calls is the keys: those of the
+/// [`KeyValueOptions::string_pool`] are created once, and with
+/// [`KeyValueOptions::intern_keys`] identical keys are shared between all the parsed
+/// blobs. Without either option it parses like [`parse_keyvalues_with_options`], since
+/// keys are read in place and values are moved into the result, so there are no
+/// scratch buffers to reuse. [`Parser::clear`] drops the kept keys when they shouldn't
+/// be kept around anymore.
+pub struct Parser {
+    options: KeyValueOptions,
+    keys: KeyInterner,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::with_options(KeyValueOptions::default())
+    }
+
+    pub fn with_options(options: KeyValueOptions) -> Self {
+        let keys = KeyInterner::new(options.intern_keys);
+        Parser { options, keys }
+    }
+
+    pub fn options(&self) -> &KeyValueOptions {
+        &self.options
+    }
+
+    /// Parse key-values, like [`parse_keyvalues_with_options`].
+    pub fn parse_keyvalues(&mut self, data: &[u8]) -> Result<KeyValues, VdfrError> {
+        parse_keyvalues_inner(data, &self.options, &mut self.keys)
+    }
+
+    /// Parse key-values in the `VBKV` container, like [`parse_vbkv_with_options`].
+    pub fn parse_vbkv(&mut self, data: &[u8]) -> Result<KeyValues, VdfrError> {
+        parse_keyvalues_inner(vbkv_payload(data)?, &self.options, &mut self.keys)
+    }
+
+    /// Forget the interned and string pool keys, the parsed key-values keep theirs.
+    pub fn clear(&mut self) {
+        self.keys = KeyInterner::new(self.options.intern_keys);
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn parse_bytes_kv<'a>(
     data: &'a [u8],
-    options: &'a KeyValueOptions,
+    options: &KeyValueOptions,
+    keys: &mut KeyInterner,
 ) -> IResult<&'a [u8], KeyValues, VdfrNomError> {
-    let bin_end = if options.alt_format {
-        BIN_END_ALT
-    } else {
-        BIN_END
-    };
+    parse_bytes_kv_node(data, options, keys, 1, &mut TreeState::default(), None)
+}
+
+/// State shared by the nodes of a single key-value tree.
+#[derive(Default)]
+struct TreeState {
+    nodes: usize,
+    /// An unknown value took the rest of the data, so the sections around it end
+    /// without their end bytes.
+    rest_taken: bool,
+}
+
+/// Records the byte range of every node while parsing, by their key path.
+struct SpanRecorder {
+    /// Address of the start of the file, offsets are relative to it.
+    base: usize,
+    path: Vec<Key>,
+    spans: BTreeMap<Vec<Key>, Range<usize>>,
+}
+
+impl SpanRecorder {
+    fn offset(&self, data: &[u8]) -> usize {
+        data.as_ptr() as usize - self.base
+    }
+}
+
+fn parse_bytes_kv_node<'a>(
+    data: &'a [u8],
+    options: &KeyValueOptions,
+    keys: &mut KeyInterner,
+    depth: usize,
+    tree: &mut TreeState,
+    mut recorder: Option<&mut SpanRecorder>,
+) -> IResult<&'a [u8], KeyValues, VdfrNomError> {
+    let limits = &options.limits;
+    if let Some(stats) = keys.stats.as_mut() {
+        stats.max_depth = stats.max_depth.max(depth);
+    }
+    if depth > limits.max_depth {
+        return Err(VdfrNomError::depth_exceeded(data, limits.max_depth));
+    }
+
+    let bin_end = BinType::end(options.alt_format).to_u8();
 
     let mut node = KeyValues::new();
 
     let mut data = data;
     loop {
+        // Unknown values take the rest of the data, ending the sections around them too
+        if data.is_empty() && tree.rest_taken {
+            return Ok((data, node));
+        }
         let (res, bin) = le_u8(data)?;
 
         if bin == bin_end {
             return Ok((res, node));
         }
 
+        tree.nodes += 1;
+        if tree.nodes > limits.max_nodes {
+            return Err(VdfrNomError::limit_exceeded(
+                data,
+                "max_nodes",
+                limits.max_nodes,
+            ));
+        }
+
         let (res, key) = if options.string_pool.is_empty() {
-            parse_utf8(res)?
+            let (res, key) = parse_str(res, options)?;
+            (res, keys.intern(&key))
         } else {
             let (res, index) = le_u32(res)?;
             let index = index as usize;
-            if index >= options.string_pool.len() {
+            let Some(key) = keys.pool_key(&options.string_pool, index) else {
                 // use empty input
                 // convert u32 into 4 bytes of u8
                 let index_num = index.to_le_bytes();
-                let error_data =
+                let mut error_data =
                     VdfrNomError::from_error_kind(&index_num, nom::error::ErrorKind::LengthValue)
                         .with_message(&format!(
                             "Index out of bounds in string pool (index: {}, pool size: {})",
                             index,
                             options.string_pool.len()
                         ));
+                error_data.error = Some(VdfrError::StringPoolIndexOutOfBounds(
+                    index,
+                    options.string_pool.len(),
+                ));
                 return Err(nom::Err::Failure(error_data));
-            }
-            (res, options.string_pool[index].clone())
+            };
+            (res, key)
         };
 
-        let (res, value) = match bin {
-            BIN_KV => {
-                let (res, subnode) = parse_bytes_kv(res, options)?;
-                (res, Value::KeyValueType(subnode))
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.path.push(key.clone());
+        }
+
+        let (res, value) = match BinType::from_u8(bin) {
+            Some(BinType::Map) => {
+                let (res, subnode) = parse_bytes_kv_node(
+                    res,
+                    options,
+                    keys,
+                    depth + 1,
+                    tree,
+                    recorder.as_deref_mut(),
+                )?;
+                // Sections below are already folded, so a single pass folds the whole tree
+                if options.keep_sequences {
+                    (res, Value::KeyValueType(subnode))
+                } else {
+                    (res, fold_sequence(subnode))
+                }
             }
-            BIN_STRING => {
-                let (res, value) = parse_utf8(res)?;
-                (res, Value::StringType(value))
+            Some(BinType::String) => {
+                let (res, bytes) = parse_nul_terminated(res, limits.max_string_len)?;
+                let value = options
+                    .string_decoding
+                    .decode_value(bytes.to_vec())
+                    .map_err(|e| invalid_utf8(bytes, e))?;
+                (res, value)
             }
-            BIN_WIDESTRING => {
-                let (res, value) = parse_utf16(res)?;
+            Some(BinType::WideString) => {
+                let (res, value) = parse_utf16(res, limits.max_string_len)?;
                 (res, Value::WideStringType(value))
             }
-            BIN_INT32 | BIN_POINTER | BIN_COLOR => {
+            Some(bin @ (BinType::Int32 | BinType::Pointer | BinType::Color)) => {
                 let (res, value) = le_i32(res)?;
                 let value = match bin {
-                    BIN_INT32 => Value::Int32Type(value),
-                    BIN_POINTER => Value::PointerType(value),
-                    BIN_COLOR => Value::ColorType(value),
+                    BinType::Int32 => Value::Int32Type(value),
+                    BinType::Pointer => Value::PointerType(Pointer(value)),
+                    BinType::Color => Value::ColorType(Color::from_i32(value)),
                     _ => unreachable!(),
                 };
                 (res, value)
             }
-            BIN_UINT64 => {
+            Some(BinType::UInt64) => {
                 let (res, value) = le_u64(res)?;
                 (res, Value::UInt64Type(value))
             }
-            BIN_INT64 => {
+            Some(BinType::Int64) => {
                 let (res, value) = le_i64(res)?;
                 (res, Value::Int64Type(value))
             }
-            BIN_FLOAT32 => {
+            Some(BinType::Float32) => {
                 let (res, value) = le_f32(res)?;
                 (res, Value::Float32Type(value))
             }
+            _ if options.tolerate_unknown_types => {
+                // The end bytes of this section and the ones around it are left out
+                let ends = res
+                    .iter()
+                    .rev()
+                    .take(depth)
+                    .take_while(|&&b| b == bin_end)
+                    .count();
+                let raw = res[..res.len() - ends].to_vec();
+                tree.rest_taken = true;
+                (&res[res.len()..], Value::UnknownType { type_id: bin, raw })
+            }
             _ => {
                 let error_data =
                     VdfrNomError::from_error_kind(&[bin], nom::error::ErrorKind::LengthValue)
                         .with_message(&format!(
                             "unknown type in key-values (type: {}, key: {})",
                             bin, &key
-                        ));
+                        ))
+                        .with_error(VdfrError::InvalidType(bin));
                 return Err(nom::Err::Failure(error_data));
             }
         };
 
+        if let Some(recorder) = recorder.as_deref_mut() {
+            let range = recorder.offset(data)..recorder.offset(res);
+            let path = recorder.path.clone();
+            recorder.path.pop();
+            recorder.spans.insert(path, range);
+        }
+
+        if let Some(stats) = keys.stats.as_mut() {
+            stats.record(&value);
+        }
         node.insert(key, value);
         data = res;
     }
 }
 
-fn read_string_pools(data: &[u8], amount: usize) -> IResult<&[u8], Vec<String>, VdfrNomError> {
-    count(parse_utf8, amount).parse(data)
+fn parse_sha1(input: &[u8]) -> IResult<&[u8], SHA1, VdfrNomError> {
+    let (rest, checksum) = take(20usize)(input)?;
+    let checksum: [u8; 20] = checksum.try_into().map_err(|_| {
+        nom::Err::Failure(VdfrNomError::from_error_kind(
+            checksum,
+            nom::error::ErrorKind::LengthValue,
+        ))
+    })?;
+    Ok((rest, SHA1::new(checksum)))
 }
 
-fn parse_utf8(input: &[u8]) -> IResult<&[u8], String, VdfrNomError> {
-    // Parse until NULL byte
-    let (rest, buf) = take_until("\0")(input)?;
-    let (rest, _) = le_u8(rest)?; // Skip NULL byte
-    let s = std::str::from_utf8(buf).map_err(|_| {
-        nom::Err::Failure(
-            VdfrNomError::from_error_kind(buf, nom::error::ErrorKind::Char)
-                .with_message("Failed to parse UTF-8 string"),
-        )
-    })?;
-    Ok((rest, s.to_string()))
+fn read_string_pools<'a>(
+    data: &'a [u8],
+    amount: usize,
+    options: &KeyValueOptions,
+) -> IResult<&'a [u8], Vec<String>, VdfrNomError> {
+    count(
+        |d| parse_str(d, options).map(|(rest, s)| (rest, s.into_owned())),
+        amount,
+    )
+    .parse(data)
 }
 
-enum Endian {
-    Be,
-    Le,
+/// Parse a key or pool string, decoded with the [`crate::StringDecoding`] of the options.
+fn parse_str<'a>(
+    input: &'a [u8],
+    options: &KeyValueOptions,
+) -> IResult<&'a [u8], Cow<'a, str>, VdfrNomError> {
+    let (rest, buf) = parse_nul_terminated(input, options.limits.max_string_len)?;
+    let s = options
+        .string_decoding
+        .decode_key(buf)
+        .map_err(|e| invalid_utf8(buf, e))?;
+    Ok((rest, s))
 }
 
-fn parse_utf16(input: &[u8]) -> IResult<&[u8], String, VdfrNomError> {
+fn invalid_utf8(buf: &[u8], error: VdfrError) -> nom::Err<VdfrNomError> {
+    nom::Err::Failure(
+        VdfrNomError::from_error_kind(buf, nom::error::ErrorKind::Char)
+            .with_message("Failed to parse UTF-8 string")
+            .with_error(error),
+    )
+}
+
+fn parse_nul_terminated(input: &[u8], max_len: usize) -> IResult<&[u8], &[u8], VdfrNomError> {
     // Parse until NULL byte
-    let (rest, buf) = take_until("\0\0")(input)?;
-    // Check if BOM is preset, if not assume BE
-    let (buf, bom) = if buf.len() >= 2 {
-        // Has BOM, check if LE or BE
-        let big_endian = buf[0] == 0xFE && buf[1] == 0xFF;
-        let little_endian = buf[0] == 0xFF && buf[1] == 0xFE;
-
-        match (big_endian, little_endian) {
-            // If BE/LE, skip BOM bytes and set endianness
-            (true, false) => (&buf[2..], Endian::Be),
-            (false, true) => (&buf[2..], Endian::Le),
-            _ => (buf, Endian::Le),
-        }
-    } else {
-        // No BOM, assume LE (Windows use LE for wchar_t)
-        (buf, Endian::Le)
+    let Some(end) = find_nul(input) else {
+        return Err(nom::Err::Error(VdfrNomError::from_error_kind(
+            input,
+            ErrorKind::TakeUntil,
+        )));
     };
+    let (buf, rest) = input.split_at(end);
+    if buf.len() > max_len {
+        return Err(VdfrNomError::limit_exceeded(
+            input,
+            "max_string_len",
+            max_len,
+        ));
+    }
+    let (rest, _) = le_u8(rest)?; // Skip NULL byte
+    Ok((rest, buf))
+}
 
-    // Consume NULL byte
-    let (rest, _) = match bom {
-        Endian::Be => be_u16(rest)?,
-        Endian::Le => le_u16(rest)?,
+fn parse_utf16(input: &[u8], max_len: usize) -> IResult<&[u8], WideString, VdfrNomError> {
+    // Parse until NULL code unit
+    let Some(end) = find_wide_nul(input) else {
+        return Err(nom::Err::Error(VdfrNomError::from_error_kind(
+            input,
+            ErrorKind::TakeUntil,
+        )));
     };
-    // Consume one more
-    let (rest, _) = le_u8(rest)?;
-
-    // Add missing NULL if odd length
-    let buf = buf
-        .iter()
-        .copied()
-        .chain(std::iter::once(0))
-        .collect::<Vec<u8>>();
-
-    let sbita: Vec<u16> = (0..buf.len() / 2)
-        .map(|i| {
-            let temp_buf = [buf[i * 2], buf[i * 2 + 1]];
-            match bom {
-                Endian::Be => u16::from_be_bytes(temp_buf),
-                Endian::Le => u16::from_le_bytes(temp_buf),
-            }
-        })
+    let (buf, rest) = (&input[..end], &input[end + 2..]);
+    if buf.len() > max_len {
+        return Err(VdfrNomError::limit_exceeded(
+            input,
+            "max_string_len",
+            max_len,
+        ));
+    }
+    // Code units are kept as they are, the byte order mark decides the endianness
+    let units = buf
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
         .collect();
-    let s = String::from_utf16(&sbita).map_err(|_| {
-        nom::Err::Failure(
-            VdfrNomError::from_error_kind(&buf, nom::error::ErrorKind::Char)
-                .with_message("Failed to parse UTF-16 string"),
-        )
-    })?;
-    Ok((rest, s))
+    Ok((rest, WideString::from_le_units(units)))
 }
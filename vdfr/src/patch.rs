@@ -0,0 +1,330 @@
+//! Binary deltas between two app info files, at the granularity of apps.
+//!
+//! A [`Patch`] holds the encoded apps that were added or replaced and the IDs of the
+//! removed ones, so a mirror only needs the apps that changed instead of the whole file.
+//! Applying it rebuilds the new file byte for byte, which is checked with its SHA-1.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{parser::parse_app_info_header, AppInfoVersion, KeyValueOptions, VdfrError, SHA1};
+
+/// Magic of a patch file, `VDFP` in little endian.
+const PATCH_MAGIC: u32 = 0x50_46_44_56;
+const PATCH_FORMAT_VERSION: u32 = 1;
+/// Length written for an optional section that is left out.
+const ABSENT: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+    /// An app only in the new file, with its encoded bytes.
+    Add(u32, Vec<u8>),
+    /// An app only in the base file.
+    Remove(u32),
+    /// An app whose encoded bytes changed, with the new ones.
+    Replace(u32, Vec<u8>),
+}
+
+impl PatchOp {
+    pub fn app_id(&self) -> u32 {
+        match self {
+            PatchOp::Add(app_id, _) | PatchOp::Remove(app_id) | PatchOp::Replace(app_id, _) => {
+                *app_id
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// SHA-1 of the file the patch applies to.
+    pub base: SHA1,
+    /// SHA-1 of the file the patch rebuilds.
+    pub result: SHA1,
+    pub magic: u32,
+    pub universe: u32,
+    /// Bytes after the apps of the new file, like the v29 string pool, when they changed.
+    pub tail: Option<Vec<u8>>,
+    /// App IDs in the order of the new file, when it isn't ascending.
+    pub order: Option<Vec<u32>>,
+    /// Changed apps, by app ID.
+    pub ops: Vec<PatchOp>,
+}
+
+impl Patch {
+    /// Compare the apps of two app info files, without decoding their key-values.
+    pub fn create(base: &[u8], new: &[u8]) -> Result<Self, VdfrError> {
+        let old_layout = Layout::read(base)?;
+        let new_layout = Layout::read(new)?;
+        let old_apps: BTreeMap<u32, &[u8]> = old_layout.apps.iter().copied().collect();
+        let new_apps: BTreeMap<u32, &[u8]> = new_layout.apps.iter().copied().collect();
+
+        let mut ops = Vec::new();
+        for (app_id, bytes) in &new_apps {
+            match old_apps.get(app_id) {
+                None => ops.push(PatchOp::Add(*app_id, bytes.to_vec())),
+                Some(old) if old != bytes => ops.push(PatchOp::Replace(*app_id, bytes.to_vec())),
+                Some(_) => {}
+            }
+        }
+        ops.extend(
+            old_apps
+                .keys()
+                .filter(|app_id| !new_apps.contains_key(*app_id))
+                .map(|app_id| PatchOp::Remove(*app_id)),
+        );
+        ops.sort_by_key(PatchOp::app_id);
+
+        let order: Vec<u32> = new_layout.apps.iter().map(|(app_id, _)| *app_id).collect();
+        let patch = Patch {
+            base: sha1(base),
+            result: sha1(new),
+            magic: new_layout.magic,
+            universe: new_layout.universe,
+            tail: (new_layout.tail != old_layout.tail).then(|| new_layout.tail.to_vec()),
+            order: (!order.windows(2).all(|pair| pair[0] < pair[1])).then_some(order),
+            ops,
+        };
+
+        // Files this can't describe, like ones with a gap before the string pool
+        if patch.rebuild(&old_layout)? != new {
+            return Err(VdfrError::InvalidPatch(
+                "the new file can't be rebuilt from its apps",
+            ));
+        }
+        Ok(patch)
+    }
+
+    /// Rebuild the new file from the base one.
+    pub fn apply(&self, base: &[u8]) -> Result<Vec<u8>, VdfrError> {
+        if sha1(base) != self.base {
+            return Err(VdfrError::InvalidPatch("the base file doesn't match"));
+        }
+        let data = self.rebuild(&Layout::read(base)?)?;
+        if sha1(&data) != self.result {
+            return Err(VdfrError::InvalidPatch("the patched file doesn't match"));
+        }
+        Ok(data)
+    }
+
+    fn rebuild(&self, base: &Layout) -> Result<Vec<u8>, VdfrError> {
+        let mut apps: BTreeMap<u32, &[u8]> = base.apps.iter().copied().collect();
+        for op in &self.ops {
+            match op {
+                PatchOp::Add(app_id, bytes) | PatchOp::Replace(app_id, bytes) => {
+                    apps.insert(*app_id, bytes);
+                }
+                PatchOp::Remove(app_id) => {
+                    apps.remove(app_id);
+                }
+            }
+        }
+        let version: AppInfoVersion = self.magic.try_into()?;
+        let tail = self.tail.as_deref().unwrap_or(base.tail);
+
+        let apps_len: usize = apps.values().map(|bytes| bytes.len()).sum();
+        let mut data = Vec::with_capacity(16 + apps_len + 4 + tail.len());
+        data.extend_from_slice(&self.magic.to_le_bytes());
+        data.extend_from_slice(&self.universe.to_le_bytes());
+        if version == AppInfoVersion::V29 {
+            // Offset of the string pool, filled in after the apps
+            data.extend_from_slice(&[0; 8]);
+        }
+
+        match &self.order {
+            Some(order) => {
+                for app_id in order {
+                    let bytes = apps
+                        .get(app_id)
+                        .ok_or(VdfrError::InvalidPatch("the order lists a missing app"))?;
+                    data.extend_from_slice(bytes);
+                }
+            }
+            None => apps
+                .values()
+                .for_each(|bytes| data.extend_from_slice(bytes)),
+        }
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        if version == AppInfoVersion::V29 {
+            let offset = data.len() as i64;
+            data[8..16].copy_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(tail);
+        Ok(data)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&PATCH_MAGIC.to_le_bytes());
+        data.extend_from_slice(&PATCH_FORMAT_VERSION.to_le_bytes());
+        data.extend_from_slice(self.base.as_bytes());
+        data.extend_from_slice(self.result.as_bytes());
+        data.extend_from_slice(&self.magic.to_le_bytes());
+        data.extend_from_slice(&self.universe.to_le_bytes());
+
+        match &self.tail {
+            Some(tail) => push_bytes(&mut data, tail),
+            None => data.extend_from_slice(&ABSENT.to_le_bytes()),
+        }
+        match &self.order {
+            Some(order) => {
+                data.extend_from_slice(&(order.len() as u32).to_le_bytes());
+                order
+                    .iter()
+                    .for_each(|app_id| data.extend_from_slice(&app_id.to_le_bytes()));
+            }
+            None => data.extend_from_slice(&ABSENT.to_le_bytes()),
+        }
+
+        data.extend_from_slice(&(self.ops.len() as u32).to_le_bytes());
+        for op in &self.ops {
+            let tag: u8 = match op {
+                PatchOp::Remove(_) => 0,
+                PatchOp::Add(_, _) => 1,
+                PatchOp::Replace(_, _) => 2,
+            };
+            data.push(tag);
+            data.extend_from_slice(&op.app_id().to_le_bytes());
+            if let PatchOp::Add(_, bytes) | PatchOp::Replace(_, bytes) = op {
+                push_bytes(&mut data, bytes);
+            }
+        }
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, VdfrError> {
+        let mut reader = Reader { data };
+        let magic = reader.u32()?;
+        if magic != PATCH_MAGIC {
+            return Err(VdfrError::UnknownMagic(magic));
+        }
+        let format_version = reader.u32()?;
+        if format_version != PATCH_FORMAT_VERSION {
+            return Err(VdfrError::UnsupportedVersion(format_version));
+        }
+
+        let base = reader.sha1()?;
+        let result = reader.sha1()?;
+        let magic = reader.u32()?;
+        let universe = reader.u32()?;
+
+        let tail = match reader.u32()? {
+            ABSENT => None,
+            len => Some(reader.take(len as usize)?.to_vec()),
+        };
+        let order = match reader.u32()? {
+            ABSENT => None,
+            count => Some(
+                (0..count)
+                    .map(|_| reader.u32())
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+        };
+
+        let count = reader.u32()?;
+        let mut ops = Vec::new();
+        for _ in 0..count {
+            let tag = reader.take(1)?[0];
+            let app_id = reader.u32()?;
+            let op = match tag {
+                0 => PatchOp::Remove(app_id),
+                1 => PatchOp::Add(app_id, reader.bytes()?),
+                2 => PatchOp::Replace(app_id, reader.bytes()?),
+                _ => return Err(VdfrError::InvalidPatch("unknown operation")),
+            };
+            ops.push(op);
+        }
+        if !reader.data.is_empty() {
+            return Err(VdfrError::TrailingBytes(reader.data.len()));
+        }
+
+        Ok(Patch {
+            base,
+            result,
+            magic,
+            universe,
+            tail,
+            order,
+            ops,
+        })
+    }
+}
+
+/// The encoded apps of an app info file, in file order.
+struct Layout<'a> {
+    magic: u32,
+    universe: u32,
+    apps: Vec<(u32, &'a [u8])>,
+    /// Everything after the zero app ID terminator.
+    tail: &'a [u8],
+}
+
+impl<'a> Layout<'a> {
+    fn read(data: &'a [u8]) -> Result<Self, VdfrError> {
+        let header = parse_app_info_header(data, &KeyValueOptions::default())?;
+        let mut offset = header.payload.as_ptr() as usize - data.as_ptr() as usize;
+
+        let mut apps = Vec::new();
+        loop {
+            let app_id = read_u32_at(data, offset)?;
+            if app_id == 0 {
+                offset += 4;
+                break;
+            }
+            // The size field covers everything in the app after itself
+            let end = offset + 8 + read_u32_at(data, offset + 4)? as usize;
+            let bytes = data.get(offset..end).ok_or(VdfrError::Truncated)?;
+            apps.push((app_id, bytes));
+            offset = end;
+        }
+
+        Ok(Layout {
+            magic: read_u32_at(data, 0)?,
+            universe: header.universe,
+            apps,
+            tail: &data[offset..],
+        })
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VdfrError> {
+        if self.data.len() < len {
+            return Err(VdfrError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, VdfrError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn sha1(&mut self) -> Result<SHA1, VdfrError> {
+        Ok(SHA1::new(self.take(20)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, VdfrError> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+fn push_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+    data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    data.extend_from_slice(bytes);
+}
+
+fn read_u32_at(data: &[u8], offset: usize) -> Result<u32, VdfrError> {
+    let bytes = data.get(offset..offset + 4).ok_or(VdfrError::Truncated)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn sha1(data: &[u8]) -> SHA1 {
+    SHA1::new(sha1_smol::Sha1::from(data).digest().bytes())
+}
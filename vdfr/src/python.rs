@@ -0,0 +1,124 @@
+//! Python bindings, enabled by the `python` feature.
+//!
+//! The parsed data is returned as plain Python dicts and lists, so it can be used
+//! like the output of ValvePython's `vdf.binary_loads`.
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict, PyList},
+};
+
+use crate::{App, AppInfo, KeyValues, Package, PackageInfo, Pointer, Value, VdfrError};
+
+impl From<VdfrError> for PyErr {
+    fn from(e: VdfrError) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+fn value_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    let object = match value {
+        Value::StringType(s) => s.into_pyobject(py)?.into_any(),
+        Value::WideStringType(s) => s.to_string_lossy().into_pyobject(py)?.into_any(),
+        Value::BytesType(b) => PyBytes::new(py, b).into_any(),
+        Value::UnknownType { type_id, raw } => (type_id, PyBytes::new(py, raw))
+            .into_pyobject(py)?
+            .into_any(),
+        Value::Int32Type(i) | Value::PointerType(Pointer(i)) => i.into_pyobject(py)?.into_any(),
+        Value::ColorType(c) => c.to_i32().into_pyobject(py)?.into_any(),
+        Value::UInt64Type(i) => i.into_pyobject(py)?.into_any(),
+        Value::Int64Type(i) => i.into_pyobject(py)?.into_any(),
+        Value::Float32Type(f) => f.into_pyobject(py)?.into_any(),
+        Value::KeyValueType(kv) => keyvalues_to_py(py, kv)?.into_any(),
+        Value::ArrayType(array) => {
+            let items = array
+                .iter()
+                .map(|v| value_to_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any()
+        }
+    };
+    Ok(object)
+}
+
+fn keyvalues_to_py<'py>(py: Python<'py>, key_values: &KeyValues) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for (key, value) in key_values {
+        dict.set_item(&**key, value_to_py(py, value)?)?;
+    }
+    Ok(dict)
+}
+
+fn app_to_py<'py>(py: Python<'py>, app: &App) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", app.id)?;
+    dict.set_item("size", app.size)?;
+    dict.set_item("state", app.state)?;
+    dict.set_item("last_update", app.last_update)?;
+    dict.set_item("access_token", app.access_token)?;
+    dict.set_item("checksum_txt", app.checksum_sha1_txt())?;
+    dict.set_item("checksum_bin", app.checksum_sha1_bin())?;
+    dict.set_item("change_number", app.change_number)?;
+    dict.set_item("key_values", keyvalues_to_py(py, &app.key_values)?)?;
+    Ok(dict)
+}
+
+fn package_to_py<'py>(py: Python<'py>, package: &Package) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", package.id)?;
+    dict.set_item("checksum", package.checksum.to_string())?;
+    dict.set_item("change_number", package.change_number)?;
+    dict.set_item("token", package.token)?;
+    dict.set_item("key_values", keyvalues_to_py(py, &package.key_values)?)?;
+    Ok(dict)
+}
+
+/// Parse an `appinfo.vdf` file, the apps are keyed by their app ID.
+#[pyfunction]
+fn parse_app_info<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let app_info: AppInfo = crate::parser::parse_app_info(data)?;
+
+    let apps = PyDict::new(py);
+    for (id, app) in &app_info.apps {
+        apps.set_item(id, app_to_py(py, app)?)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("version", u32::from(app_info.version))?;
+    dict.set_item("universe", app_info.universe)?;
+    dict.set_item("apps", apps)?;
+    Ok(dict)
+}
+
+/// Parse a `packageinfo.vdf` file, the packages are keyed by their package ID.
+#[pyfunction]
+fn parse_package_info<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let package_info: PackageInfo = crate::parser::parse_package_info(data)?;
+
+    let packages = PyDict::new(py);
+    for (id, package) in &package_info.packages {
+        packages.set_item(id, package_to_py(py, package)?)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("version", u32::from(package_info.version))?;
+    dict.set_item("universe", package_info.universe)?;
+    dict.set_item("packages", packages)?;
+    Ok(dict)
+}
+
+/// Parse binary key-values into a dict.
+#[pyfunction]
+fn parse_keyvalues<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let key_values = crate::parser::parse_keyvalues(data)?;
+    keyvalues_to_py(py, &key_values)
+}
+
+/// Add the functions to the `vdfr` module, created by the `vdfr-python` extension crate.
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_app_info, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_package_info, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_keyvalues, m)?)?;
+    Ok(())
+}
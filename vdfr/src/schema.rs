@@ -0,0 +1,82 @@
+//! Schema inference, the key paths used across a catalog and the types seen at each.
+//!
+//! Items of arrays share the [`ARRAY_ITEM`] path segment, so a field of every item
+//! is merged into a single path.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+use crate::{AppInfo, Key, KeyValues, Value};
+
+/// Path segment standing for any item of an array.
+pub const ARRAY_ITEM: &str = "[]";
+
+/// What was seen at a key path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldSchema {
+    /// Type names seen at the path, like `string` or `section`.
+    pub types: BTreeSet<&'static str>,
+    /// Amount of times the path occurred.
+    pub count: usize,
+}
+
+/// Merged schema of any number of key-values, by key path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    pub fields: BTreeMap<Vec<Key>, FieldSchema>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// Merge the paths of the key-values into the schema.
+    pub fn add(&mut self, key_values: &KeyValues) {
+        let mut path = Vec::new();
+        self.add_node(&mut path, key_values);
+    }
+
+    /// What was seen at a key path, array items are matched by [`ARRAY_ITEM`].
+    pub fn field(&self, path: &[&str]) -> Option<&FieldSchema> {
+        let path: Vec<Key> = path.iter().map(|key| Key::from(*key)).collect();
+        self.fields.get(&path)
+    }
+
+    fn add_node(&mut self, path: &mut Vec<Key>, key_values: &KeyValues) {
+        for (key, value) in key_values {
+            path.push(key.clone());
+            self.add_value(path, value);
+            path.pop();
+        }
+    }
+
+    fn add_value(&mut self, path: &mut Vec<Key>, value: &Value) {
+        let field = self.fields.entry(path.clone()).or_default();
+        field.types.insert(value.kind_name());
+        field.count += 1;
+
+        match value {
+            Value::KeyValueType(key_values) => self.add_node(path, key_values),
+            Value::ArrayType(items) => {
+                path.push(Key::from(ARRAY_ITEM));
+                for item in items {
+                    self.add_value(path, item);
+                }
+                path.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Infer the schema of every app, from the root of their key-values.
+pub fn infer(app_info: &AppInfo) -> Schema {
+    let mut schema = Schema::new();
+    for app in app_info.apps.values() {
+        schema.add(&app.key_values);
+    }
+    schema
+}
@@ -0,0 +1,121 @@
+//! Regex search over key-values, like finding the apps referencing a host anywhere.
+
+use alloc::borrow::Cow;
+
+use regex::Regex;
+
+use crate::{AppInfo, KeyValues, Pointer, Value};
+
+pub trait Search {
+    /// Find the values below whose key matches `key`, and whose text matches `value` if
+    /// given, with their `/` separated paths like `depots/1/manifests`.
+    ///
+    /// The text of a value is its string, or its number in decimal. Sections and arrays
+    /// only match without a `value` regex, and are searched below either way.
+    fn search<'a>(&'a self, key: &Regex, value: Option<&Regex>) -> Vec<(String, &'a Value)>;
+}
+
+impl Search for KeyValues {
+    fn search<'a>(&'a self, key: &Regex, value: Option<&Regex>) -> Vec<(String, &'a Value)> {
+        let mut matches = Vec::new();
+        search_keyvalues(self, &mut String::new(), key, value, &mut matches);
+        matches
+    }
+}
+
+impl Search for Value {
+    fn search<'a>(&'a self, key: &Regex, value: Option<&Regex>) -> Vec<(String, &'a Value)> {
+        let mut matches = Vec::new();
+        search_children(self, &mut String::new(), key, value, &mut matches);
+        matches
+    }
+}
+
+impl AppInfo {
+    /// Search the key-values of every app, see [`Search::search`], in app ID order.
+    pub fn search<'a>(
+        &'a self,
+        key: &Regex,
+        value: Option<&Regex>,
+    ) -> Vec<(u32, String, &'a Value)> {
+        self.apps
+            .values()
+            .flat_map(|app| {
+                app.key_values
+                    .search(key, value)
+                    .into_iter()
+                    .map(|(path, found)| (app.id, path, found))
+            })
+            .collect()
+    }
+}
+
+fn search_keyvalues<'a>(
+    kv: &'a KeyValues,
+    path: &mut String,
+    key: &Regex,
+    value: Option<&Regex>,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    for (child_key, child) in kv {
+        visit(child_key, child, path, key, value, matches);
+    }
+}
+
+fn search_children<'a>(
+    found: &'a Value,
+    path: &mut String,
+    key: &Regex,
+    value: Option<&Regex>,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    match found {
+        Value::KeyValueType(kv) => search_keyvalues(kv, path, key, value, matches),
+        Value::ArrayType(array) => {
+            for (index, item) in array.iter().enumerate() {
+                visit(&index.to_string(), item, path, key, value, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit<'a>(
+    child_key: &str,
+    child: &'a Value,
+    path: &mut String,
+    key: &Regex,
+    value: Option<&Regex>,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('/');
+    }
+    path.push_str(child_key);
+
+    let value_matches = match value {
+        Some(value) => text(child).is_some_and(|text| value.is_match(&text)),
+        None => true,
+    };
+    if value_matches && key.is_match(child_key) {
+        matches.push((path.clone(), child));
+    }
+    search_children(child, path, key, value, matches);
+    path.truncate(len);
+}
+
+/// The text a value regex is matched against, like in the text format.
+fn text(value: &Value) -> Option<Cow<'_, str>> {
+    match value {
+        Value::StringType(s) => Some(Cow::Borrowed(s)),
+        Value::WideStringType(s) => Some(s.to_string_lossy().into()),
+        Value::BytesType(b) => Some(String::from_utf8_lossy(b)),
+        Value::Int32Type(v) | Value::PointerType(Pointer(v)) => Some(v.to_string().into()),
+        Value::ColorType(c) => Some(c.to_i32().to_string().into()),
+        Value::UInt64Type(v) => Some(v.to_string().into()),
+        Value::Int64Type(v) => Some(v.to_string().into()),
+        Value::Float32Type(v) => Some(v.to_string().into()),
+        Value::KeyValueType(_) | Value::ArrayType(_) | Value::UnknownType { .. } => None,
+    }
+}
@@ -0,0 +1,250 @@
+//! Non-Steam game shortcuts, from `userdata/<user>/config/shortcuts.vdf`.
+//!
+//! The file is binary key-values, but the key casing changed between client versions
+//! (`appname` and `AppName`) and the app ID is an unsigned value stored as int32.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "writer")]
+use std::io::Write;
+
+use crate::{
+    common::crc32,
+    lookup::{get, sequence},
+    parser::parse_keyvalues,
+    KeyValues, Value, VdfrError,
+};
+#[cfg(feature = "writer")]
+use crate::{
+    writer::{write_buffered, write_keyvalue_entry},
+    BinType,
+};
+
+/// Keys written for every shortcut, in the order used by the Steam client.
+#[cfg(feature = "writer")]
+const SHORTCUT_KEYS: [&str; 17] = [
+    "appid",
+    "AppName",
+    "Exe",
+    "StartDir",
+    "icon",
+    "ShortcutPath",
+    "LaunchOptions",
+    "IsHidden",
+    "AllowDesktopConfig",
+    "AllowOverlay",
+    "OpenVR",
+    "Devkit",
+    "DevkitGameID",
+    "DevkitOverrideAppID",
+    "LastPlayTime",
+    "FlatpakAppID",
+    "tags",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct Shortcut {
+    /// The shortcut app ID, shown by Steam as a 64-bit ID of `(app_id << 32) | 0x02000000`.
+    pub app_id: u32,
+    pub app_name: String,
+    pub exe: String,
+    pub start_dir: String,
+    pub icon: String,
+    pub shortcut_path: String,
+    pub launch_options: String,
+    pub is_hidden: bool,
+    pub allow_desktop_config: bool,
+    pub allow_overlay: bool,
+    pub open_vr: bool,
+    pub devkit: bool,
+    pub devkit_game_id: String,
+    pub devkit_override_app_id: u32,
+    pub last_play_time: u32,
+    pub flatpak_app_id: String,
+    pub tags: Vec<String>,
+    /// Every key of the entry, including the ones not covered above.
+    pub key_values: KeyValues,
+}
+
+impl Shortcut {
+    /// Create a shortcut with the app ID the Steam client would generate for it.
+    pub fn new(app_name: &str, exe: &str) -> Self {
+        Shortcut {
+            app_id: shortcut_app_id(app_name, exe),
+            app_name: app_name.to_string(),
+            exe: exe.to_string(),
+            allow_desktop_config: true,
+            allow_overlay: true,
+            ..Default::default()
+        }
+    }
+
+    /// The known fields as key-values, followed by any other key of the entry.
+    ///
+    /// The tags are left out, since they have to keep their order.
+    #[cfg(feature = "writer")]
+    fn entries(&self) -> Vec<(&str, Value)> {
+        let mut entries = Vec::from([
+            ("appid", Value::Int32Type(self.app_id as i32)),
+            ("AppName", Value::StringType(self.app_name.clone().into())),
+            ("Exe", Value::StringType(self.exe.clone().into())),
+            ("StartDir", Value::StringType(self.start_dir.clone().into())),
+            ("icon", Value::StringType(self.icon.clone().into())),
+            (
+                "ShortcutPath",
+                Value::StringType(self.shortcut_path.clone().into()),
+            ),
+            (
+                "LaunchOptions",
+                Value::StringType(self.launch_options.clone().into()),
+            ),
+            ("IsHidden", Value::Int32Type(self.is_hidden.into())),
+            (
+                "AllowDesktopConfig",
+                Value::Int32Type(self.allow_desktop_config.into()),
+            ),
+            ("AllowOverlay", Value::Int32Type(self.allow_overlay.into())),
+            ("OpenVR", Value::Int32Type(self.open_vr.into())),
+            ("Devkit", Value::Int32Type(self.devkit.into())),
+            (
+                "DevkitGameID",
+                Value::StringType(self.devkit_game_id.clone().into()),
+            ),
+            (
+                "DevkitOverrideAppID",
+                Value::Int32Type(self.devkit_override_app_id as i32),
+            ),
+            ("LastPlayTime", Value::Int32Type(self.last_play_time as i32)),
+            (
+                "FlatpakAppID",
+                Value::StringType(self.flatpak_app_id.clone().into()),
+            ),
+        ]);
+
+        let other = self.key_values.iter().filter(|(key, _)| {
+            !SHORTCUT_KEYS
+                .iter()
+                .any(|known| key.eq_ignore_ascii_case(known))
+        });
+        for (key, value) in other {
+            entries.push((key, value.clone()));
+        }
+        entries
+    }
+}
+
+/// The app ID the Steam client generates for a shortcut.
+pub fn shortcut_app_id(app_name: &str, exe: &str) -> u32 {
+    let mut data = Vec::from(exe.as_bytes());
+    data.extend_from_slice(app_name.as_bytes());
+    crc32(&data) | 0x8000_0000
+}
+
+/// Parse the contents of `shortcuts.vdf`.
+pub fn parse_shortcuts(data: &[u8]) -> Result<Vec<Shortcut>, VdfrError> {
+    let key_values = parse_keyvalues(data)?;
+    let Some(shortcuts) = get(&key_values, "shortcuts") else {
+        return Ok(Vec::new());
+    };
+
+    Ok(sequence(shortcuts)
+        .into_iter()
+        .filter_map(|(_, entry)| match entry {
+            Value::KeyValueType(entry) => Some(parse_shortcut(entry)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Write the shortcuts in the `shortcuts.vdf` format.
+#[cfg(feature = "writer")]
+pub fn write_shortcuts<W: std::io::Write>(
+    writer: &mut W,
+    shortcuts: &[Shortcut],
+) -> std::io::Result<()> {
+    write_buffered(writer, |writer| {
+        write_node_start(writer, "shortcuts")?;
+        for (index, shortcut) in shortcuts.iter().enumerate() {
+            write_node_start(writer, &index.to_string())?;
+            for (key, value) in shortcut.entries() {
+                write_keyvalue_entry(writer, key, &value)?;
+            }
+
+            write_node_start(writer, "tags")?;
+            for (index, tag) in shortcut.tags.iter().enumerate() {
+                write_keyvalue_entry(
+                    writer,
+                    &index.to_string(),
+                    &Value::StringType(tag.clone().into()),
+                )?;
+            }
+            writer.write_all(&[BinType::End.to_u8()])?;
+
+            writer.write_all(&[BinType::End.to_u8()])?;
+        }
+        // End of the shortcuts and the root node
+        writer.write_all(&[BinType::End.to_u8(), BinType::End.to_u8()])
+    })
+}
+
+#[cfg(feature = "writer")]
+fn write_node_start<W: std::io::Write>(writer: &mut W, key: &str) -> std::io::Result<()> {
+    writer.write_all(&[BinType::Map.to_u8()])?;
+    writer.write_all(key.as_bytes())?;
+    writer.write_all(&[0])
+}
+
+fn parse_shortcut(entry: &KeyValues) -> Shortcut {
+    let tags = get(entry, "tags")
+        .map(|tags| {
+            sequence(tags)
+                .into_iter()
+                .filter_map(|(_, tag)| match tag {
+                    Value::StringType(tag) => Some(tag.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Shortcut {
+        app_id: get_int(entry, "appid") as u32,
+        app_name: get_string(entry, "AppName"),
+        exe: get_string(entry, "Exe"),
+        start_dir: get_string(entry, "StartDir"),
+        icon: get_string(entry, "icon"),
+        shortcut_path: get_string(entry, "ShortcutPath"),
+        launch_options: get_string(entry, "LaunchOptions"),
+        is_hidden: get_int(entry, "IsHidden") != 0,
+        allow_desktop_config: get_int(entry, "AllowDesktopConfig") != 0,
+        allow_overlay: get_int(entry, "AllowOverlay") != 0,
+        open_vr: get_int(entry, "OpenVR") != 0,
+        devkit: get_int(entry, "Devkit") != 0,
+        devkit_game_id: get_string(entry, "DevkitGameID"),
+        devkit_override_app_id: get_int(entry, "DevkitOverrideAppID") as u32,
+        last_play_time: get_int(entry, "LastPlayTime") as u32,
+        flatpak_app_id: get_string(entry, "FlatpakAppID"),
+        tags,
+        key_values: entry.clone(),
+    }
+}
+
+fn get_string(key_values: &KeyValues, name: &str) -> String {
+    match get(key_values, name) {
+        Some(Value::StringType(value)) => value.to_string(),
+        Some(Value::WideStringType(value)) => value.to_string_lossy(),
+        Some(Value::Int32Type(value)) => value.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn get_int(key_values: &KeyValues, name: &str) -> i32 {
+    match get(key_values, name) {
+        Some(Value::Int32Type(value)) => *value,
+        Some(Value::StringType(value)) => value.parse().unwrap_or_default(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,207 @@
+//! Achievement and stat definitions, from `appcache/stats/UserGameStatsSchema_<appid>.bin`.
+//!
+//! The file is binary key-values ending its sections with the alternative end byte.
+//! Stats are grouped in numbered blocks, a block of achievements packs them as bits
+//! of a single stat.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    lookup::{get, get_section, sequence},
+    parser::parse_keyvalues_with_options,
+    KeyValueOptions, KeyValues, Value, VdfrError,
+};
+
+/// Stat block type of an int stat.
+const STAT_TYPE_INT: u32 = 1;
+/// Stat block type of a float stat.
+const STAT_TYPE_FLOAT: u32 = 2;
+/// Stat block type of an average rate stat.
+const STAT_TYPE_AVG_RATE: u32 = 3;
+/// Stat block types holding achievements as bits.
+const STAT_TYPE_ACHIEVEMENTS: u32 = 4;
+const STAT_TYPE_GROUP_ACHIEVEMENTS: u32 = 5;
+
+#[derive(Debug, Clone, Default)]
+pub struct StatsSchema {
+    pub app_id: u32,
+    pub game_name: String,
+    pub version: u32,
+    pub achievements: Vec<Achievement>,
+    pub stats: Vec<Stat>,
+    /// The whole section of the app, for the fields not covered above.
+    pub key_values: KeyValues,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Achievement {
+    /// The name used by the Steamworks API, like `ACH_WIN_ONE_GAME`.
+    pub api_name: String,
+    /// Display names, by language.
+    pub display_name: BTreeMap<String, String>,
+    /// Descriptions, by language.
+    pub description: BTreeMap<String, String>,
+    pub hidden: bool,
+    pub icon: String,
+    pub icon_gray: String,
+    /// ID of the stat holding the achievement, and the bit of it.
+    pub stat_id: u32,
+    pub bit: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatKind {
+    Int,
+    Float,
+    AvgRate,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stat {
+    pub id: u32,
+    pub kind: StatKind,
+    /// The name used by the Steamworks API.
+    pub api_name: String,
+    /// Display names, by language.
+    pub display_name: BTreeMap<String, String>,
+    pub default: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub increment_only: bool,
+}
+
+/// Parse the contents of `UserGameStatsSchema_<appid>.bin`.
+pub fn parse_stats_schema(data: &[u8]) -> Result<StatsSchema, VdfrError> {
+    let options = KeyValueOptions {
+        alt_format: true,
+        ..Default::default()
+    };
+    let key_values = parse_keyvalues_with_options(data, &options)?;
+
+    // The root has a single section, keyed by the app ID
+    let Some((app_id, Value::KeyValueType(app))) = key_values.iter().next() else {
+        return Ok(StatsSchema::default());
+    };
+
+    let mut schema = StatsSchema {
+        app_id: app_id.parse().unwrap_or_default(),
+        game_name: get_string(app, "gamename"),
+        version: get_number(app, "version").unwrap_or_default() as u32,
+        key_values: app.clone(),
+        ..Default::default()
+    };
+
+    let blocks = get(app, "stats").map(sequence).unwrap_or_default();
+    for (index, block) in blocks {
+        let Value::KeyValueType(block) = block else {
+            continue;
+        };
+        let stat_id = get_number(block, "id").map_or(index, |id| id as u32);
+        let stat_type = get_number(block, "type_int")
+            .or_else(|| get_number(block, "type"))
+            .unwrap_or_default() as u32;
+
+        let kind = match stat_type {
+            STAT_TYPE_INT => StatKind::Int,
+            STAT_TYPE_FLOAT => StatKind::Float,
+            STAT_TYPE_AVG_RATE => StatKind::AvgRate,
+            STAT_TYPE_ACHIEVEMENTS | STAT_TYPE_GROUP_ACHIEVEMENTS => {
+                let bits = get(block, "bits").map(sequence).unwrap_or_default();
+                for (bit, entry) in bits {
+                    if let Value::KeyValueType(entry) = entry {
+                        schema
+                            .achievements
+                            .push(parse_achievement(entry, stat_id, bit));
+                    }
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        let display = get_section(block, "display");
+        schema.stats.push(Stat {
+            id: stat_id,
+            kind,
+            api_name: get_string(block, "name"),
+            display_name: display
+                .and_then(|display| get(display, "name"))
+                .map(localized)
+                .unwrap_or_default(),
+            default: get_number(block, "default").unwrap_or_default(),
+            min: get_number(block, "min"),
+            max: get_number(block, "max"),
+            increment_only: get_number(block, "incrementonly").unwrap_or_default() != 0.0,
+        });
+    }
+
+    Ok(schema)
+}
+
+fn parse_achievement(entry: &KeyValues, stat_id: u32, bit: u32) -> Achievement {
+    let display = get_section(entry, "display");
+    let display_get = |name| display.and_then(|display| get(display, name));
+
+    Achievement {
+        api_name: get_string(entry, "name"),
+        display_name: display_get("name").map(localized).unwrap_or_default(),
+        description: display_get("desc").map(localized).unwrap_or_default(),
+        hidden: display
+            .and_then(|display| get_number(display, "hidden"))
+            .unwrap_or_default()
+            != 0.0,
+        icon: display.map(|d| get_string(d, "icon")).unwrap_or_default(),
+        icon_gray: display
+            .map(|d| get_string(d, "icon_gray"))
+            .unwrap_or_default(),
+        stat_id,
+        bit: get_number(entry, "bit").map_or(bit, |bit| bit as u32),
+    }
+}
+
+/// Texts by language, a plain string is taken as the English one.
+///
+/// The `token` key is the localization token, not a language, and is left out.
+fn localized(value: &Value) -> BTreeMap<String, String> {
+    match value {
+        Value::KeyValueType(languages) => languages
+            .iter()
+            .filter(|(language, _)| !language.eq_ignore_ascii_case("token"))
+            .filter_map(|(language, text)| match text {
+                Value::StringType(text) => Some((language.to_string(), text.to_string())),
+                Value::WideStringType(text) => Some((language.to_string(), text.to_string_lossy())),
+                _ => None,
+            })
+            .collect(),
+        Value::StringType(text) => BTreeMap::from([("english".to_string(), text.to_string())]),
+        Value::WideStringType(text) => {
+            BTreeMap::from([("english".to_string(), text.to_string_lossy())])
+        }
+        _ => BTreeMap::new(),
+    }
+}
+
+/// Get a string by its key, ignoring the case, or an empty one when it's missing.
+fn get_string(key_values: &KeyValues, name: &str) -> String {
+    match get(key_values, name) {
+        Some(Value::StringType(value)) => value.to_string(),
+        Some(Value::WideStringType(value)) => value.to_string_lossy(),
+        _ => String::new(),
+    }
+}
+
+/// Get a number by its key, the schema stores them either as numbers or strings.
+fn get_number(key_values: &KeyValues, name: &str) -> Option<f64> {
+    match get(key_values, name)? {
+        Value::Int32Type(value) => Some(*value as f64),
+        Value::UInt64Type(value) => Some(*value as f64),
+        Value::Int64Type(value) => Some(*value as f64),
+        Value::Float32Type(value) => Some(*value as f64),
+        Value::StringType(value) => value.trim().parse().ok(),
+        _ => None,
+    }
+}
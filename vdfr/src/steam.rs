@@ -0,0 +1,601 @@
+//! Locating the local Steam installation and its libraries.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    lookup::{get_section, get_str},
+    text::{parse_text, write_text},
+    Key, KeyValues, Value, VdfrError,
+};
+
+/// A Steam installation directory.
+#[derive(Debug, Clone)]
+pub struct SteamDir {
+    pub path: PathBuf,
+}
+
+/// A Steam library folder, from `libraryfolders.vdf`.
+#[derive(Debug, Clone)]
+pub struct Library {
+    pub path: PathBuf,
+    pub label: String,
+    /// Installed apps, mapped to their size on disk in bytes.
+    pub apps: BTreeMap<u32, u64>,
+}
+
+/// An installed app, from `steamapps/appmanifest_<appid>.acf`.
+#[derive(Debug, Clone)]
+pub struct AppManifest {
+    pub app_id: u32,
+    pub name: String,
+    pub install_dir: String,
+    /// Bit flags of the install state, see [`AppManifest::is_fully_installed`].
+    pub state_flags: u32,
+    pub build_id: u64,
+    pub size_on_disk: u64,
+    /// Installed depots, by depot ID.
+    pub installed_depots: BTreeMap<u32, InstalledDepot>,
+    /// The whole `AppState` section, for the fields not covered above.
+    pub key_values: KeyValues,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstalledDepot {
+    pub manifest: u64,
+    pub size: u64,
+}
+
+/// A Steam account that logged in on this machine, from `config/loginusers.vdf`.
+#[derive(Debug, Clone)]
+pub struct LoginUser {
+    pub steam_id: u64,
+    pub account_name: String,
+    pub persona_name: String,
+    /// Whether this is the last user that logged in.
+    pub most_recent: bool,
+    /// Unix timestamp of the last login.
+    pub timestamp: u64,
+    /// Every key of the user, including the ones not covered above.
+    pub key_values: KeyValues,
+}
+
+impl LoginUser {
+    /// The 32-bit account ID, used for the `userdata` directory names.
+    pub fn account_id(&self) -> u32 {
+        (self.steam_id & 0xffff_ffff) as u32
+    }
+}
+
+/// A per-user config, `localconfig.vdf` or `sharedconfig.vdf`.
+///
+/// Launch options are stored in `localconfig.vdf`, while the category tags are in
+/// `sharedconfig.vdf`. Both keep the per-app settings under `Software/Valve/Steam/apps`.
+#[derive(Debug, Clone, Default)]
+pub struct UserConfig {
+    pub key_values: KeyValues,
+}
+
+/// Steam's registry on Linux, `~/.steam/registry.vdf`.
+///
+/// It mirrors the Windows registry keys of the client, like the language under
+/// `Registry/HKCU/Software/Valve/Steam` and the state of each app under its `apps` section.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    pub key_values: KeyValues,
+}
+
+/// Install state of an app, from the `apps` section of the registry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryAppState {
+    pub name: String,
+    pub installed: bool,
+    pub running: bool,
+    pub updating: bool,
+}
+
+/// State flag set once every depot of the app is downloaded.
+const STATE_FULLY_INSTALLED: u32 = 4;
+
+impl AppManifest {
+    pub fn is_fully_installed(&self) -> bool {
+        self.state_flags & STATE_FULLY_INSTALLED != 0
+    }
+}
+
+impl UserConfig {
+    pub fn parse(input: &str) -> Result<Self, VdfrError> {
+        Ok(UserConfig {
+            key_values: parse_text(input)?,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, VdfrError> {
+        UserConfig::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), VdfrError> {
+        std::fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    pub fn to_text(&self) -> String {
+        write_text(&self.key_values)
+    }
+
+    pub fn launch_options(&self, app_id: u32) -> Option<&str> {
+        get_str(self.app(app_id)?, "LaunchOptions")
+    }
+
+    pub fn set_launch_options(&mut self, app_id: u32, options: &str) {
+        set_string(self.app_mut(app_id), "LaunchOptions", options);
+    }
+
+    /// Category tags of the app, in their order.
+    pub fn tags(&self, app_id: u32) -> Vec<String> {
+        let Some(tags) = self.app(app_id).and_then(|app| get_section(app, "tags")) else {
+            return Vec::new();
+        };
+
+        let mut indexed = tags
+            .iter()
+            .filter_map(|(key, value)| match value {
+                Value::StringType(tag) => Some((key.parse::<usize>().ok()?, tag.to_string())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        indexed.sort_by_key(|(index, _)| *index);
+        indexed.into_iter().map(|(_, tag)| tag).collect()
+    }
+
+    pub fn set_tags(&mut self, app_id: u32, tags: &[String]) {
+        let tags = tags
+            .iter()
+            .enumerate()
+            .map(|(index, tag)| {
+                (
+                    index.to_string().into(),
+                    Value::StringType(tag.clone().into()),
+                )
+            })
+            .collect();
+
+        let app = self.app_mut(app_id);
+        let key = find_key(app, "tags").unwrap_or_else(|| "tags".into());
+        app.insert(key, Value::KeyValueType(tags));
+    }
+
+    fn app(&self, app_id: u32) -> Option<&KeyValues> {
+        let root = self.key_values.values().find_map(|value| match value {
+            Value::KeyValueType(root) => Some(root),
+            _ => None,
+        })?;
+        let apps = ["Software", "Valve", "Steam", "apps"]
+            .iter()
+            .try_fold(root, |section, name| get_section(section, name))?;
+        get_section(apps, &app_id.to_string())
+    }
+
+    /// Get the settings of an app, creating the sections on the way when needed.
+    ///
+    /// An empty config gets a `UserLocalConfigStore` root.
+    fn app_mut(&mut self, app_id: u32) -> &mut KeyValues {
+        let root = self
+            .key_values
+            .iter()
+            .find(|(_, value)| matches!(value, Value::KeyValueType(_)))
+            .map(|(key, _)| key.clone())
+            .unwrap_or_else(|| "UserLocalConfigStore".into());
+        let root = get_section_mut(&mut self.key_values, &root);
+
+        let app_id = app_id.to_string();
+        ["Software", "Valve", "Steam", "apps", &app_id]
+            .iter()
+            .fold(root, |section, name| get_section_mut(section, name))
+    }
+}
+
+/// Sections from the root of the registry to the settings of the Steam client.
+const REGISTRY_STEAM_PATH: [&str; 5] = ["Registry", "HKCU", "Software", "Valve", "Steam"];
+
+impl Registry {
+    pub fn parse(input: &str) -> Result<Self, VdfrError> {
+        Ok(Registry {
+            key_values: parse_text(input)?,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, VdfrError> {
+        Registry::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), VdfrError> {
+        std::fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    pub fn to_text(&self) -> String {
+        write_text(&self.key_values)
+    }
+
+    /// Path of the registry of the current user, only used on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(Path::new(&home).join(".steam").join("registry.vdf"))
+    }
+
+    /// The language of the client, like `english`.
+    pub fn language(&self) -> Option<&str> {
+        get_str(self.steam()?, "language")
+    }
+
+    pub fn set_language(&mut self, language: &str) {
+        set_string(self.steam_mut(), "language", language);
+    }
+
+    /// The account name that logs in automatically when the client starts.
+    pub fn auto_login_user(&self) -> Option<&str> {
+        get_str(self.steam()?, "AutoLoginUser")
+    }
+
+    pub fn set_auto_login_user(&mut self, account_name: &str) {
+        set_string(self.steam_mut(), "AutoLoginUser", account_name);
+    }
+
+    pub fn app_state(&self, app_id: u32) -> Option<RegistryAppState> {
+        let app = get_section(get_section(self.steam()?, "apps")?, &app_id.to_string())?;
+        Some(RegistryAppState {
+            name: get_str(app, "name").unwrap_or_default().to_string(),
+            installed: get_number::<u32>(app, "Installed") != 0,
+            running: get_number::<u32>(app, "Running") != 0,
+            updating: get_number::<u32>(app, "Updating") != 0,
+        })
+    }
+
+    /// IDs of the apps the registry marks as installed.
+    pub fn installed_apps(&self) -> Vec<u32> {
+        let Some(apps) = self.steam().and_then(|steam| get_section(steam, "apps")) else {
+            return Vec::new();
+        };
+
+        let mut installed = apps
+            .iter()
+            .filter_map(|(key, value)| match value {
+                Value::KeyValueType(app) if get_number::<u32>(app, "Installed") != 0 => {
+                    key.parse().ok()
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        installed.sort_unstable();
+        installed
+    }
+
+    /// Update the state of an app, keeping its other keys.
+    pub fn set_app_state(&mut self, app_id: u32, state: &RegistryAppState) {
+        let app_id = app_id.to_string();
+        let app = get_section_mut(get_section_mut(self.steam_mut(), "apps"), &app_id);
+        if !state.name.is_empty() {
+            set_string(app, "name", &state.name);
+        }
+        set_string(app, "Installed", if state.installed { "1" } else { "0" });
+        set_string(app, "Running", if state.running { "1" } else { "0" });
+        set_string(app, "Updating", if state.updating { "1" } else { "0" });
+    }
+
+    fn steam(&self) -> Option<&KeyValues> {
+        REGISTRY_STEAM_PATH
+            .iter()
+            .try_fold(&self.key_values, |section, name| get_section(section, name))
+    }
+
+    /// Get the settings of the client, creating the sections on the way when needed.
+    fn steam_mut(&mut self) -> &mut KeyValues {
+        REGISTRY_STEAM_PATH
+            .iter()
+            .fold(&mut self.key_values, |section, name| {
+                get_section_mut(section, name)
+            })
+    }
+}
+
+impl SteamDir {
+    /// Find the Steam installation of the current user, checking the usual install locations.
+    ///
+    /// The `STEAM_DIR` environment variable takes priority when set.
+    pub fn locate() -> Option<SteamDir> {
+        candidate_dirs()
+            .into_iter()
+            .find(|path| path.join("steamapps").is_dir() || path.join("appcache").is_dir())
+            .map(|path| SteamDir { path })
+    }
+
+    pub fn appinfo_path(&self) -> PathBuf {
+        self.path.join("appcache").join("appinfo.vdf")
+    }
+
+    pub fn packageinfo_path(&self) -> PathBuf {
+        self.path.join("appcache").join("packageinfo.vdf")
+    }
+
+    /// Path of `libraryfolders.vdf`, older clients keep it in `config`.
+    pub fn library_folders_path(&self) -> PathBuf {
+        let path = self.path.join("steamapps").join("libraryfolders.vdf");
+        if path.is_file() {
+            path
+        } else {
+            self.path.join("config").join("libraryfolders.vdf")
+        }
+    }
+
+    pub fn login_users_path(&self) -> PathBuf {
+        self.path.join("config").join("loginusers.vdf")
+    }
+
+    /// Users that logged in on this installation.
+    pub fn login_users(&self) -> Result<Vec<LoginUser>, VdfrError> {
+        let contents = std::fs::read_to_string(self.login_users_path())?;
+        parse_login_users(&contents)
+    }
+
+    /// The user that logged in last, by the `MostRecent` flag or else the latest timestamp.
+    pub fn most_recent_user(&self) -> Result<Option<LoginUser>, VdfrError> {
+        let users = self.login_users()?;
+        let most_recent = users
+            .iter()
+            .position(|user| user.most_recent)
+            .or_else(|| (0..users.len()).max_by_key(|&index| users[index].timestamp));
+        Ok(most_recent.map(|index| users[index].clone()))
+    }
+
+    /// The `userdata` directory of a user, with their configs and shortcuts.
+    pub fn userdata_path(&self, user: &LoginUser) -> PathBuf {
+        self.path
+            .join("userdata")
+            .join(user.account_id().to_string())
+    }
+
+    /// Path of the user's `localconfig.vdf`, with the launch options.
+    pub fn local_config_path(&self, user: &LoginUser) -> PathBuf {
+        self.userdata_path(user)
+            .join("config")
+            .join("localconfig.vdf")
+    }
+
+    /// Path of the user's `sharedconfig.vdf`, with the category tags.
+    pub fn shared_config_path(&self, user: &LoginUser) -> PathBuf {
+        self.userdata_path(user)
+            .join("7")
+            .join("remote")
+            .join("sharedconfig.vdf")
+    }
+
+    /// Library folders of this installation, the installation itself included.
+    pub fn libraries(&self) -> Result<Vec<Library>, VdfrError> {
+        let contents = std::fs::read_to_string(self.library_folders_path())?;
+        let mut libraries = parse_library_folders(&contents)?;
+
+        if !libraries.iter().any(|library| library.path == self.path) {
+            libraries.insert(
+                0,
+                Library {
+                    path: self.path.clone(),
+                    label: String::new(),
+                    apps: BTreeMap::new(),
+                },
+            );
+        }
+        Ok(libraries)
+    }
+}
+
+impl Library {
+    pub fn steamapps_path(&self) -> PathBuf {
+        self.path.join("steamapps")
+    }
+
+    pub fn manifest_path(&self, app_id: u32) -> PathBuf {
+        self.steamapps_path()
+            .join(format!("appmanifest_{}.acf", app_id))
+    }
+
+    /// Parse the manifests of every app installed in the library.
+    pub fn app_manifests(&self) -> Result<Vec<AppManifest>, VdfrError> {
+        let mut manifests = Vec::new();
+        for entry in std::fs::read_dir(self.steamapps_path())? {
+            let path = entry?.path();
+            let is_manifest = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("appmanifest_") && name.ends_with(".acf"));
+            if is_manifest {
+                manifests.push(parse_app_manifest(&std::fs::read_to_string(path)?)?);
+            }
+        }
+        manifests.sort_by_key(|manifest| manifest.app_id);
+        Ok(manifests)
+    }
+}
+
+/// Parse the contents of an `appmanifest_<appid>.acf`.
+pub fn parse_app_manifest(input: &str) -> Result<AppManifest, VdfrError> {
+    let key_values = parse_text(input)?;
+    let state = get_section(&key_values, "AppState")
+        .ok_or(VdfrError::InvalidText(1, "missing AppState section"))?;
+
+    let installed_depots = get_section(state, "InstalledDepots")
+        .map(|depots| {
+            depots
+                .iter()
+                .filter_map(|(depot_id, depot)| {
+                    let Value::KeyValueType(depot) = depot else {
+                        return None;
+                    };
+                    let depot = InstalledDepot {
+                        manifest: get_number(depot, "manifest"),
+                        size: get_number(depot, "size"),
+                    };
+                    Some((depot_id.parse().ok()?, depot))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(AppManifest {
+        app_id: get_number(state, "appid"),
+        name: get_str(state, "name").unwrap_or_default().to_string(),
+        install_dir: get_str(state, "installdir").unwrap_or_default().to_string(),
+        state_flags: get_number(state, "StateFlags"),
+        build_id: get_number(state, "buildid"),
+        size_on_disk: get_number(state, "SizeOnDisk"),
+        installed_depots,
+        key_values: state.clone(),
+    })
+}
+
+/// Parse the contents of `config/loginusers.vdf`.
+pub fn parse_login_users(input: &str) -> Result<Vec<LoginUser>, VdfrError> {
+    let key_values = parse_text(input)?;
+    let Some(users) = get_section(&key_values, "users") else {
+        return Ok(Vec::new());
+    };
+
+    let users = users
+        .iter()
+        .filter_map(|(steam_id, user)| {
+            let Value::KeyValueType(user) = user else {
+                return None;
+            };
+            Some(LoginUser {
+                steam_id: steam_id.parse().ok()?,
+                account_name: get_str(user, "AccountName").unwrap_or_default().to_string(),
+                persona_name: get_str(user, "PersonaName").unwrap_or_default().to_string(),
+                most_recent: get_number::<u32>(user, "MostRecent") != 0,
+                timestamp: get_number(user, "Timestamp"),
+                key_values: user.clone(),
+            })
+        })
+        .collect();
+
+    Ok(users)
+}
+
+/// Parse the contents of `libraryfolders.vdf`.
+///
+/// Both the current format with a section per library and the older one,
+/// where each numbered key is just the library path, are supported.
+pub fn parse_library_folders(input: &str) -> Result<Vec<Library>, VdfrError> {
+    let key_values = parse_text(input)?;
+    let Some(folders) = get_section(&key_values, "libraryfolders") else {
+        return Ok(Vec::new());
+    };
+
+    let mut indexed = folders
+        .iter()
+        .filter_map(|(key, value)| key.parse::<usize>().ok().map(|index| (index, value)))
+        .collect::<Vec<_>>();
+    indexed.sort_by_key(|(index, _)| *index);
+
+    let libraries = indexed
+        .into_iter()
+        .filter_map(|(_, value)| match value {
+            Value::StringType(path) => Some(Library {
+                path: PathBuf::from(&**path),
+                label: String::new(),
+                apps: BTreeMap::new(),
+            }),
+            Value::KeyValueType(folder) => {
+                let path = get_str(folder, "path")?;
+                let apps = get_section(folder, "apps")
+                    .map(|apps| {
+                        apps.keys()
+                            .filter_map(|app_id| {
+                                Some((app_id.parse().ok()?, get_number(apps, app_id)))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(Library {
+                    path: PathBuf::from(path),
+                    label: get_str(folder, "label").unwrap_or_default().to_string(),
+                    apps,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    Ok(libraries)
+}
+
+/// Find the existing key matching the name, ignoring the case.
+fn find_key(key_values: &KeyValues, name: &str) -> Option<Key> {
+    key_values
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+/// Get a section by its key ignoring the case, creating it when it's missing.
+fn get_section_mut<'a>(key_values: &'a mut KeyValues, name: &str) -> &'a mut KeyValues {
+    let key = find_key(key_values, name).unwrap_or_else(|| name.into());
+    let value = key_values
+        .entry(key)
+        .or_insert_with(|| Value::KeyValueType(KeyValues::new()));
+    if !matches!(value, Value::KeyValueType(_)) {
+        *value = Value::KeyValueType(KeyValues::new());
+    }
+    match value {
+        Value::KeyValueType(section) => section,
+        _ => unreachable!(),
+    }
+}
+
+/// Set a string by its key, keeping the casing of the existing key.
+fn set_string(key_values: &mut KeyValues, name: &str, value: &str) {
+    let key = find_key(key_values, name).unwrap_or_else(|| name.into());
+    key_values.insert(key, Value::StringType(value.to_string().into()));
+}
+
+/// Get a number by its key, ignoring the case, or zero when it's missing or invalid.
+pub(crate) fn get_number<T>(key_values: &KeyValues, name: &str) -> T
+where
+    T: std::str::FromStr + Default,
+{
+    get_str(key_values, name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+fn candidate_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = std::env::var_os("STEAM_DIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    if cfg!(target_os = "windows") {
+        if let Some(program_files) = std::env::var_os("ProgramFiles(x86)") {
+            dirs.push(Path::new(&program_files).join("Steam"));
+        }
+        if let Some(program_files) = std::env::var_os("ProgramFiles") {
+            dirs.push(Path::new(&program_files).join("Steam"));
+        }
+        dirs.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+    } else if cfg!(target_os = "macos") {
+        if let Some(home) = &home {
+            dirs.push(home.join("Library/Application Support/Steam"));
+        }
+    } else if let Some(home) = &home {
+        dirs.push(home.join(".steam/steam"));
+        dirs.push(home.join(".local/share/Steam"));
+        // Flatpak and Snap installs
+        dirs.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+        dirs.push(home.join("snap/steam/common/.local/share/Steam"));
+    }
+
+    dirs
+}
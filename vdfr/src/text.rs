@@ -0,0 +1,237 @@
+//! Text key-values, the format of `libraryfolders.vdf`, `appmanifest_*.acf` and most Steam configs.
+//!
+//! Every leaf is parsed as a [`Value::StringType`], nested sections as [`Value::KeyValueType`].
+//! Sequences are kept as-is, the keys `"0"`, `"1"`, ... are not folded into arrays.
+//! Sections are nested at most [`ParseLimits::DEFAULT_MAX_DEPTH`] deep.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{KeyValues, ParseLimits, Pointer, Value, VdfrError};
+
+pub fn parse_text(input: &str) -> Result<KeyValues, VdfrError> {
+    let mut reader = TextReader { input, pos: 0 };
+    reader.parse_node(1)
+}
+
+/// Write key-values in the text format, indented with tabs like the Steam client does.
+///
+/// Numbers are written as strings and arrays as `"0"`, `"1"`, ... sections.
+/// Values of unknown types have no text form and are left out.
+pub fn write_text(key_values: &KeyValues) -> String {
+    let mut output = String::new();
+    write_node(&mut output, &map_entries(key_values), 0);
+    output
+}
+
+fn map_entries(key_values: &KeyValues) -> Vec<(&str, &Value)> {
+    key_values.iter().map(|(k, v)| (&**k, v)).collect()
+}
+
+fn write_node(output: &mut String, entries: &[(&str, &Value)], depth: usize) {
+    for &(key, value) in entries {
+        if let Value::UnknownType { .. } = value {
+            continue;
+        }
+        push_indent(output, depth);
+        push_quoted(output, key);
+
+        match value {
+            Value::KeyValueType(kv) => {
+                output.push('\n');
+                write_section(output, &map_entries(kv), depth);
+            }
+            Value::ArrayType(array) => {
+                output.push('\n');
+                let keys = (0..array.len())
+                    .map(|index| index.to_string())
+                    .collect::<Vec<_>>();
+                let items = keys
+                    .iter()
+                    .map(String::as_str)
+                    .zip(array)
+                    .collect::<Vec<_>>();
+                write_section(output, &items, depth);
+            }
+            Value::StringType(s) => push_leaf(output, s),
+            Value::WideStringType(s) => push_leaf(output, &s.to_string_lossy()),
+            Value::BytesType(b) => push_leaf(output, &String::from_utf8_lossy(b)),
+            Value::Int32Type(v) | Value::PointerType(Pointer(v)) => {
+                push_leaf(output, &v.to_string())
+            }
+            Value::ColorType(c) => push_leaf(output, &c.to_i32().to_string()),
+            Value::UInt64Type(v) => push_leaf(output, &v.to_string()),
+            Value::Int64Type(v) => push_leaf(output, &v.to_string()),
+            Value::Float32Type(v) => push_leaf(output, &v.to_string()),
+            Value::UnknownType { .. } => {}
+        }
+    }
+}
+
+fn write_section(output: &mut String, entries: &[(&str, &Value)], depth: usize) {
+    push_indent(output, depth);
+    output.push_str("{\n");
+    write_node(output, entries, depth + 1);
+    push_indent(output, depth);
+    output.push_str("}\n");
+}
+
+fn push_leaf(output: &mut String, value: &str) {
+    output.push_str("\t\t");
+    push_quoted(output, value);
+    output.push('\n');
+}
+
+fn push_indent(output: &mut String, depth: usize) {
+    for _ in 0..depth {
+        output.push('\t');
+    }
+}
+
+fn push_quoted(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\r' => output.push_str("\\r"),
+            _ => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+struct TextReader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+enum Token {
+    String(String),
+    Open,
+    Close,
+}
+
+impl TextReader<'_> {
+    /// Parse the entries of a node, the root node is depth 1.
+    fn parse_node(&mut self, depth: usize) -> Result<KeyValues, VdfrError> {
+        if depth > ParseLimits::DEFAULT_MAX_DEPTH {
+            return Err(VdfrError::DepthLimitExceeded(
+                ParseLimits::DEFAULT_MAX_DEPTH,
+            ));
+        }
+
+        let nested = depth > 1;
+        let mut node = KeyValues::new();
+
+        loop {
+            let key = match self.next_token()? {
+                Some(Token::String(key)) => key,
+                Some(Token::Close) if nested => return Ok(node),
+                None if !nested => return Ok(node),
+                Some(Token::Close) => return Err(self.error("unexpected '}'")),
+                Some(Token::Open) => return Err(self.error("expected a key, found '{'")),
+                None => return Err(self.error("missing '}'")),
+            };
+
+            let value = match self.next_token()? {
+                Some(Token::String(value)) => Value::StringType(value.into()),
+                Some(Token::Open) => Value::KeyValueType(self.parse_node(depth + 1)?),
+                Some(Token::Close) => return Err(self.error("expected a value, found '}'")),
+                None => return Err(self.error("missing value")),
+            };
+            self.skip_conditional();
+
+            node.insert(key.into(), value);
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, VdfrError> {
+        self.skip_whitespace();
+
+        let rest = &self.input[self.pos..];
+        let Some(c) = rest.chars().next() else {
+            return Ok(None);
+        };
+
+        match c {
+            '{' => {
+                self.pos += 1;
+                Ok(Some(Token::Open))
+            }
+            '}' => {
+                self.pos += 1;
+                Ok(Some(Token::Close))
+            }
+            '"' => self.read_quoted().map(|s| Some(Token::String(s))),
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | '"'))
+                    .unwrap_or(rest.len());
+                self.pos += end;
+                Ok(Some(Token::String(String::from(&rest[..end]))))
+            }
+        }
+    }
+
+    fn read_quoted(&mut self) -> Result<String, VdfrError> {
+        // Skip the opening quote
+        self.pos += 1;
+
+        let mut value = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+
+        Err(self.error("unterminated string"))
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = &self.input[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+
+            // Line comments
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Skip a platform conditional like `[$WIN32]` after a value.
+    fn skip_conditional(&mut self) {
+        let rest = &self.input[self.pos..];
+        let trimmed = rest.trim_start_matches([' ', '\t']);
+        if trimmed.starts_with('[') {
+            if let Some(end) = trimmed.find(']') {
+                self.pos += rest.len() - trimmed.len() + end + 1;
+            }
+        }
+    }
+
+    fn error(&self, message: &'static str) -> VdfrError {
+        let line = self.input[..self.pos].matches('\n').count() + 1;
+        VdfrError::InvalidText(line, message)
+    }
+}
@@ -0,0 +1,97 @@
+//! Bulk transformations of key-values, without writing the recursion by hand.
+//!
+//! Values can be changed in place, removed or replaced while walking, like normalizing
+//! strings, rewriting URLs or dropping sections.
+
+use alloc::string::{String, ToString};
+
+use crate::{KeyValues, Value};
+
+/// What to do with a value visited by [`WalkMut::walk_mut`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Keep the value, as changed in place, and walk below it.
+    Keep,
+    /// Remove the value from its section or array.
+    Remove,
+    /// Replace the value, the new one isn't walked.
+    Replace(Value),
+}
+
+pub trait WalkMut {
+    /// Visit every value below, parents before their children, with its `/` separated
+    /// path like `depots/1/manifests`. Array items are visited with their index as key.
+    fn walk_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(&str, &mut Value) -> Action;
+}
+
+impl WalkMut for KeyValues {
+    fn walk_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &mut Value) -> Action,
+    {
+        walk_keyvalues(self, &mut String::new(), &mut f);
+    }
+}
+
+impl WalkMut for Value {
+    /// The value itself isn't visited, only its children if it's a section or array.
+    fn walk_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &mut Value) -> Action,
+    {
+        walk_children(self, &mut String::new(), &mut f);
+    }
+}
+
+fn walk_keyvalues<F>(kv: &mut KeyValues, path: &mut String, f: &mut F)
+where
+    F: FnMut(&str, &mut Value) -> Action,
+{
+    kv.retain(|key, value| visit(key, value, path, f));
+}
+
+fn walk_children<F>(value: &mut Value, path: &mut String, f: &mut F)
+where
+    F: FnMut(&str, &mut Value) -> Action,
+{
+    match value {
+        Value::KeyValueType(kv) => walk_keyvalues(kv, path, f),
+        Value::ArrayType(array) => {
+            let mut index = 0usize;
+            array.retain_mut(|item| {
+                let keep = visit(&index.to_string(), item, path, f);
+                index += 1;
+                keep
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Visit a value with its key appended to the path, returning whether it's kept.
+fn visit<F>(key: &str, value: &mut Value, path: &mut String, f: &mut F) -> bool
+where
+    F: FnMut(&str, &mut Value) -> Action,
+{
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('/');
+    }
+    path.push_str(key);
+
+    let keep = match f(path, value) {
+        Action::Keep => {
+            walk_children(value, path, f);
+            true
+        }
+        Action::Remove => false,
+        Action::Replace(new) => {
+            *value = new;
+            true
+        }
+    };
+    path.truncate(len);
+    keep
+}
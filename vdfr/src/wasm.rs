@@ -0,0 +1,32 @@
+//! JavaScript bindings for `wasm32-unknown-unknown`, enabled by the `wasm` feature.
+//!
+//! The parsed data is converted through the same JSON representation as the `serde` output,
+//! so the JavaScript objects match what the CLI dumps.
+
+use wasm_bindgen::prelude::*;
+
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let json = serde_json::to_string(value)?;
+    js_sys::JSON::parse(&json).map_err(|_| JsError::new("Failed to convert parsed data"))
+}
+
+/// Parse an `appinfo.vdf` file into a JavaScript object.
+#[wasm_bindgen(js_name = parseAppInfo)]
+pub fn parse_app_info(data: &[u8]) -> Result<JsValue, JsError> {
+    let app_info = crate::parser::parse_app_info(data)?;
+    to_js(&app_info)
+}
+
+/// Parse a `packageinfo.vdf` file into a JavaScript object.
+#[wasm_bindgen(js_name = parsePackageInfo)]
+pub fn parse_package_info(data: &[u8]) -> Result<JsValue, JsError> {
+    let package_info = crate::parser::parse_package_info(data)?;
+    to_js(&package_info)
+}
+
+/// Parse binary key-values into a JavaScript object.
+#[wasm_bindgen(js_name = parseKeyValues)]
+pub fn parse_keyvalues(data: &[u8]) -> Result<JsValue, JsError> {
+    let key_values = crate::parser::parse_keyvalues(data)?;
+    to_js(&key_values)
+}
@@ -1,11 +1,60 @@
 //! Writer for the VDF binary format.
 
-use std::collections::HashSet;
+use std::io::Write;
 
 use crate::{
-    common::KeyValues, App, AppInfo, AppInfoVersion, Package, PackageInfo, Value, BIN_END,
+    common::{crc32, Key, KeyValues, VBKV_MAGIC},
+    App, AppInfo, AppInfoVersion, BinType, Package, PackageInfo, PkgInfoVersion, Pointer, Value,
+    WideString,
 };
 
+/// Strings of the v29 string pool, written sorted.
+#[cfg(not(feature = "preserve_order"))]
+pub type StringPool = std::collections::BTreeSet<String>;
+/// Strings of the v29 string pool, written in the order the keys first occur.
+#[cfg(feature = "preserve_order")]
+pub type StringPool = indexmap::IndexSet<String>;
+
+/// Options of the writer functions, the default writes files like Steam does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriterOptions {
+    /// Write the keys of v29 app info as indices into the string pool.
+    ///
+    /// Without it keys are written as strings with an empty pool, which this crate
+    /// can parse but Steam can't.
+    pub use_string_pool: bool,
+    /// End maps with the alternative end byte, like `UserGameStatsSchema_*.bin` files.
+    pub alt_end_byte: bool,
+    /// Write the keys of every map in sorted order, instead of the iteration order of the map.
+    ///
+    /// Folded arrays are always written in index order.
+    pub sort_keys: bool,
+    /// Write the size of each app from its written bytes, instead of the parsed `size`.
+    pub recompute_sizes: bool,
+    /// Write the binary checksum of each app from its written key-values,
+    /// instead of the parsed `checksum_bin`.
+    pub recompute_checksums: bool,
+    /// Threads encoding the apps of an app info, each into its own buffer before they're
+    /// written in order, which pays off when recomputing the checksums of many apps.
+    ///
+    /// With more than one thread, the whole body is buffered. `0` and `1` write the apps
+    /// one after another.
+    pub threads: usize,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            use_string_pool: true,
+            alt_end_byte: false,
+            sort_keys: false,
+            recompute_sizes: true,
+            recompute_checksums: true,
+            threads: 1,
+        }
+    }
+}
+
 enum KeyFormat {
     // v29 format with string pools
     Index(u32),
@@ -13,19 +62,35 @@ enum KeyFormat {
     String(String),
 }
 
+/// Write through a buffer, flushed once done.
+///
+/// The writers issue a few small writes per node, which are slow on unbuffered writers
+/// like a [`std::fs::File`]. Writers that are already buffered get large writes, which a
+/// [`std::io::BufWriter`] passes through.
+pub(crate) fn write_buffered<W, F>(writer: &mut W, write: F) -> std::io::Result<()>
+where
+    W: std::io::Write,
+    F: FnOnce(&mut std::io::BufWriter<&mut W>) -> std::io::Result<()>,
+{
+    let mut buffered = std::io::BufWriter::new(writer);
+    write(&mut buffered)?;
+    buffered.flush()
+}
+
 fn write_utf8<W: std::io::Write>(writer: &mut W, string: &str) -> std::io::Result<()> {
-    writer.write_all(string.as_bytes())?;
+    write_bytes(writer, string.as_bytes())
+}
+
+fn write_bytes<W: std::io::Write>(writer: &mut W, bytes: &[u8]) -> std::io::Result<()> {
+    writer.write_all(bytes)?;
     // Null terminator
     writer.write_all(&[0])
 }
 
-/// Write a UTF-16 string (wide string) to the writer.
-/// Uses little-endian encoding.
-fn write_utf16<W: std::io::Write>(writer: &mut W, string: &str) -> std::io::Result<()> {
-    for c in string.encode_utf16() {
-        writer.write_all(&c.to_le_bytes())?;
-    }
-    // There's 2 bytes for the null terminator + 1 extra byte
+/// Write a wide string with the code units and byte order it was parsed with.
+fn write_utf16<W: std::io::Write>(writer: &mut W, string: &WideString) -> std::io::Result<()> {
+    writer.write_all(&string.to_bytes())?;
+    // Null code unit terminator
     writer.write_all(&[0, 0])
 }
 
@@ -33,7 +98,8 @@ fn write_keyvalue<W: std::io::Write>(
     writer: &mut W,
     key: KeyFormat,
     value: &Value,
-    string_pools: &mut HashSet<String>,
+    key_indices: &KeyIndices,
+    options: &WriterOptions,
 ) -> std::io::Result<()> {
     // Write the bin format
     value.save_bin(writer)?;
@@ -57,9 +123,19 @@ fn write_keyvalue<W: std::io::Write>(
         Value::WideStringType(string) => {
             write_utf16(writer, string)?;
         }
-        Value::Int32Type(i) | Value::PointerType(i) | Value::ColorType(i) => {
+        Value::BytesType(bytes) => {
+            write_bytes(writer, bytes)?;
+        }
+        Value::UnknownType { raw, .. } => {
+            // Everything up to the end of the section, without a terminator
+            writer.write_all(raw)?;
+        }
+        Value::Int32Type(i) | Value::PointerType(Pointer(i)) => {
             writer.write_all(&i.to_le_bytes())?;
         }
+        Value::ColorType(color) => {
+            writer.write_all(&color.to_i32().to_le_bytes())?;
+        }
         Value::UInt64Type(ui) => {
             writer.write_all(&ui.to_le_bytes())?;
         }
@@ -70,72 +146,136 @@ fn write_keyvalue<W: std::io::Write>(
             writer.write_all(&f.to_le_bytes())?;
         }
         Value::KeyValueType(kv) => {
-            write_keyvalues_internal(writer, kv, string_pools)?;
-            // writer.write_all(&[BIN_END])?;
+            write_keyvalues_internal(writer, kv, key_indices, options)?;
         }
         Value::ArrayType(array) => {
             // Array is our custom type, it's parsed back into KeyValues like:
             // "key" { "0" "value" "1" "value" }
             // So we need to write it as a KeyValues
-            let keymaps: KeyValues = array
+            let entries: Vec<(Key, &Value)> = array
                 .iter()
                 .enumerate()
-                .map(|(idx, kv_arr)| {
-                    let key = idx.to_string();
-                    (key, kv_arr.clone())
-                })
+                .map(|(idx, value)| (idx.to_string().into(), value))
                 .collect();
-            write_keyvalues_internal(writer, &keymaps, string_pools)?;
+            write_entries(writer, entries, key_indices, options)?;
         }
     }
 
     Ok(())
 }
 
-fn find_key_index(key: &str, string_pools: &mut HashSet<String>) -> Option<u32> {
+/// Indices of the string pool entries by key, empty to write keys as strings.
+type KeyIndices<'a> = std::collections::HashMap<&'a str, u32>;
+
+/// Index every entry of the pool once, it's written in the iteration order of the set.
+fn key_indices(string_pools: &StringPool) -> KeyIndices<'_> {
     string_pools
         .iter()
         .enumerate()
-        .filter_map(
-            |(idx, name)| {
-                if name == key {
-                    Some(idx as u32)
-                } else {
-                    None
-                }
-            },
-        )
-        .next()
+        .map(|(index, key)| (key.as_str(), index as u32))
+        .collect()
 }
 
 fn write_keyvalues_internal<W: std::io::Write>(
     writer: &mut W,
     keyvalues: &KeyValues,
-    string_pools: &mut HashSet<String>,
+    key_indices: &KeyIndices,
+    options: &WriterOptions,
+) -> std::io::Result<()> {
+    let mut entries: Vec<(Key, &Value)> = keyvalues
+        .iter()
+        .map(|(key, value)| (key.clone(), value))
+        .collect();
+    if options.sort_keys {
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    write_entries(writer, entries, key_indices, options)
+}
+
+/// Write the entries of a map in the given order, followed by the end byte.
+///
+/// Values of unknown types and the maps holding them go last, since their raw bytes
+/// run up to the end of the map.
+fn write_entries<W: std::io::Write>(
+    writer: &mut W,
+    mut entries: Vec<(Key, &Value)>,
+    key_indices: &KeyIndices,
+    options: &WriterOptions,
 ) -> std::io::Result<()> {
-    for (key, value) in keyvalues {
-        let key_data = if string_pools.is_empty() {
-            KeyFormat::String(key.clone())
+    entries.sort_by_key(|(_, value)| ends_with_unknown(value));
+    for (key, value) in entries {
+        let key_data = if key_indices.is_empty() {
+            KeyFormat::String(key.to_string())
         } else {
-            let key_idx = find_key_index(key, string_pools).unwrap();
+            let key_idx = key_indices[&*key];
             KeyFormat::Index(key_idx)
         };
 
-        write_keyvalue(writer, key_data, value, string_pools)?;
+        write_keyvalue(writer, key_data, value, key_indices, options)?;
     }
-    writer.write_all(&[BIN_END])?;
+    writer.write_all(&[BinType::end(options.alt_end_byte).to_u8()])?;
 
     Ok(())
 }
 
+fn ends_with_unknown(value: &Value) -> bool {
+    match value {
+        Value::UnknownType { .. } => true,
+        Value::KeyValueType(kv) => kv.values().any(ends_with_unknown),
+        Value::ArrayType(array) => array.iter().any(ends_with_unknown),
+        _ => false,
+    }
+}
+
+/// Write a single key-value pair, without string pools.
+pub(crate) fn write_keyvalue_entry<W: std::io::Write>(
+    writer: &mut W,
+    key: &str,
+    value: &Value,
+) -> std::io::Result<()> {
+    write_keyvalue(
+        writer,
+        KeyFormat::String(key.to_string()),
+        value,
+        &KeyIndices::new(),
+        &WriterOptions::default(),
+    )
+}
+
 pub fn write_keyvalues<W: std::io::Write>(
     writer: &mut W,
     keyvalues: &KeyValues,
 ) -> std::io::Result<()> {
-    write_keyvalues_internal(writer, keyvalues, &mut HashSet::new())
+    write_keyvalues_with_options(writer, keyvalues, &WriterOptions::default())
+}
+
+/// Write key-values with the given options, keys are always written as strings.
+pub fn write_keyvalues_with_options<W: std::io::Write>(
+    writer: &mut W,
+    keyvalues: &KeyValues,
+    options: &WriterOptions,
+) -> std::io::Result<()> {
+    write_buffered(writer, |writer| {
+        write_keyvalues_internal(writer, keyvalues, &KeyIndices::new(), options)
+    })
 }
 
-fn collect_string_pools_from_value(string_pools: &mut HashSet<String>, value: &Value) {
+/// Write key-values in the `VBKV` container, prefixed with the CRC-32 of the payload.
+pub fn write_vbkv<W: std::io::Write>(writer: &mut W, keyvalues: &KeyValues) -> std::io::Result<()> {
+    let mut payload = Vec::new();
+    write_keyvalues_internal(
+        &mut payload,
+        keyvalues,
+        &KeyIndices::new(),
+        &WriterOptions::default(),
+    )?;
+
+    writer.write_all(&VBKV_MAGIC.to_le_bytes())?;
+    writer.write_all(&crc32(&payload).to_le_bytes())?;
+    writer.write_all(&payload)
+}
+
+fn collect_string_pools_from_value(string_pools: &mut StringPool, value: &Value) {
     match value {
         Value::KeyValueType(kv) => {
             collect_string_pools(string_pools, kv);
@@ -151,118 +291,339 @@ fn collect_string_pools_from_value(string_pools: &mut HashSet<String>, value: &V
     }
 }
 
-pub fn collect_string_pools(string_pools: &mut HashSet<String>, key_values: &KeyValues) {
+pub fn collect_string_pools(string_pools: &mut StringPool, key_values: &KeyValues) {
     for (key, value) in key_values {
-        string_pools.insert(key.clone());
+        string_pools.insert(key.to_string());
         collect_string_pools_from_value(string_pools, value);
     }
 }
 
-fn write_app<W: std::io::Write + std::io::Seek>(
+fn write_app<W: std::io::Write>(
     writer: &mut W,
     app: &App,
     version: &AppInfoVersion,
-    string_pools: &mut HashSet<String>,
+    key_indices: &KeyIndices,
+    options: &WriterOptions,
 ) -> std::io::Result<()> {
+    let mut key_values = Vec::new();
+    write_keyvalues_internal(&mut key_values, &app.key_values, key_indices, options)?;
+
+    let checksum_bin = match version {
+        AppInfoVersion::V24 | AppInfoVersion::V26 | AppInfoVersion::V27 => None,
+        _ if options.recompute_checksums => {
+            let mut checksum = sha1_smol::Sha1::new();
+            checksum.update(&key_values);
+            Some(checksum.digest().bytes())
+        }
+        _ => Some(*app.checksum_bin.unwrap_or_default().as_bytes()),
+    };
+
+    let size = if options.recompute_sizes {
+        // The size counts everything after itself
+        (version.app_header_size() - 8 + key_values.len()) as u32
+    } else {
+        app.size
+    };
+
     // Write the app info
     writer.write_all(&app.id.to_le_bytes())?;
-    writer.write_all(&app.size.to_le_bytes())?;
+    writer.write_all(&size.to_le_bytes())?;
     writer.write_all(&app.state.to_le_bytes())?;
     writer.write_all(&app.last_update.to_le_bytes())?;
     writer.write_all(&app.access_token.to_le_bytes())?;
-    writer.write_all(&*app.checksum_txt)?;
+    if *version != AppInfoVersion::V26 {
+        writer.write_all(&*app.checksum_txt)?;
+    }
     writer.write_all(&app.change_number.to_le_bytes())?;
+    if let Some(checksum_bin) = checksum_bin {
+        writer.write_all(&checksum_bin)?;
+    }
+    writer.write_all(&key_values)
+}
 
-    match version {
-        AppInfoVersion::V27 => write_keyvalues_internal(writer, &app.key_values, string_pools),
-        _ => {
-            let mut temp_writer = std::io::Cursor::new(Vec::new());
-            write_keyvalues_internal(&mut temp_writer, &app.key_values, string_pools)?;
-            temp_writer.set_position(0);
+/// Writer only counting the bytes written into it.
+struct ByteCounter(usize);
 
-            let buffer = temp_writer.into_inner();
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
 
-            let mut checksum = sha1_smol::Sha1::new();
-            checksum.update(&buffer);
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Bytes an app takes in an app info file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppSize {
+    pub id: u32,
+    /// Size of the header, from the app ID up to the key-values.
+    pub header: usize,
+    /// Size of the binary key-values.
+    pub key_values: usize,
+}
 
-            let digest = checksum.digest().bytes();
-            writer.write_all(&digest)?;
-            writer.write_all(&buffer)
+impl AppSize {
+    pub fn total(&self) -> usize {
+        self.header + self.key_values
+    }
+}
+
+impl App {
+    /// Bytes the app takes when written into an app info file of the version,
+    /// with the default [`WriterOptions`].
+    pub fn serialized_size(&self, version: AppInfoVersion) -> usize {
+        self.size_of(&version).total()
+    }
+
+    fn size_of(&self, version: &AppInfoVersion) -> AppSize {
+        // v29 keys are indices of the same size, whatever else is in the string pool
+        let mut string_pools = StringPool::new();
+        if *version == AppInfoVersion::V29 {
+            collect_string_pools(&mut string_pools, &self.key_values);
+        }
+        let mut counter = ByteCounter(0);
+        write_keyvalues_internal(
+            &mut counter,
+            &self.key_values,
+            &key_indices(&string_pools),
+            &WriterOptions::default(),
+        )
+        .expect("counting bytes can't fail");
+
+        AppSize {
+            id: self.id,
+            header: version.app_header_size(),
+            key_values: counter.0,
         }
     }
 }
 
-pub fn write_app_info<W: std::io::Write + std::io::Seek>(
+impl AppInfo {
+    /// Bytes each app takes in the file, largest first.
+    ///
+    /// The v29 string pool is shared by every app, so it's not counted in any of them.
+    pub fn size_report(&self) -> Vec<AppSize> {
+        let mut report: Vec<AppSize> = self
+            .apps
+            .values()
+            .map(|app| app.size_of(&self.version))
+            .collect();
+        report.sort_by(|a, b| b.total().cmp(&a.total()).then(a.id.cmp(&b.id)));
+        report
+    }
+}
+
+pub fn write_app_info<W: std::io::Write>(
+    writer: &mut W,
+    app_info: &AppInfo,
+) -> std::io::Result<()> {
+    write_app_info_with_options(writer, app_info, &WriterOptions::default())
+}
+
+/// Write the app info with the given options, in a single pass without seeking.
+///
+/// The body of v29 files is buffered, since the string pool offset in the header
+/// comes before it. Like every writer function, the writes go through a
+/// [`std::io::BufWriter`], so a plain [`std::fs::File`] can be passed.
+pub fn write_app_info_with_options<W: std::io::Write>(
+    writer: &mut W,
+    app_info: &AppInfo,
+    options: &WriterOptions,
+) -> std::io::Result<()> {
+    let apps: Vec<&App> = app_info.apps.values().collect();
+    write_buffered(writer, |writer| {
+        write_selected_apps(writer, app_info, &apps, options)
+    })
+}
+
+pub fn write_app_info_filtered<W: std::io::Write>(
+    writer: &mut W,
+    app_info: &AppInfo,
+    app_ids: &[u32],
+) -> std::io::Result<()> {
+    write_app_info_filtered_with_options(writer, app_info, app_ids, &WriterOptions::default())
+}
+
+/// Write an app info with only the given apps, in the order they're in the app info.
+///
+/// The v29 string pool only holds the keys of these apps. Fails with
+/// [`std::io::ErrorKind::NotFound`] if one of the app IDs isn't in the app info.
+pub fn write_app_info_filtered_with_options<W: std::io::Write>(
+    writer: &mut W,
+    app_info: &AppInfo,
+    app_ids: &[u32],
+    options: &WriterOptions,
+) -> std::io::Result<()> {
+    if let Some(missing) = app_ids.iter().find(|id| !app_info.apps.contains_key(*id)) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("app {} is not in the app info", missing),
+        ));
+    }
+    let apps: Vec<&App> = app_info
+        .apps
+        .values()
+        .filter(|app| app_ids.contains(&app.id))
+        .collect();
+    write_buffered(writer, |writer| {
+        write_selected_apps(writer, app_info, &apps, options)
+    })
+}
+
+fn write_selected_apps<W: std::io::Write>(
     writer: &mut W,
     app_info: &AppInfo,
+    apps: &[&App],
+    options: &WriterOptions,
 ) -> std::io::Result<()> {
+    // The sections of v24 can't be rebuilt from the merged key-values
+    if app_info.version == AppInfoVersion::V24 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "writing app info v24 is not supported",
+        ));
+    }
+
     // Write the app info
     let version_magic: u32 = app_info.version.into();
     writer.write_all(&version_magic.to_le_bytes())?;
     // Write universe
     writer.write_all(&app_info.universe.to_le_bytes())?;
 
-    // If v29, let's do the string pools
-    let mut string_pools = HashSet::new();
-    let offset_back = if app_info.version == AppInfoVersion::V29 {
-        app_info.apps.iter().for_each(|(_, app)| {
-            collect_string_pools(&mut string_pools, &app.key_values);
-        });
-
-        // Temporarily write the offset and size of the string pools
-        let current_pos = writer.seek(std::io::SeekFrom::Current(0))?;
-        let temp = 0i64;
-        writer.write(&temp.to_le_bytes())?;
-        Some(current_pos)
-    } else {
-        None
-    };
-
-    for (_, app) in &app_info.apps {
-        write_app(writer, app, &app_info.version, &mut string_pools)?;
+    if app_info.version != AppInfoVersion::V29 {
+        return write_apps(writer, apps, app_info.version, &KeyIndices::new(), options);
     }
 
-    // Get the current position, this is what we write later back in the offset
-    let current_pos = writer.seek(std::io::SeekFrom::Current(0))?;
-
-    // Write the string pools first
-    writer.write(&string_pools.len().to_le_bytes())?;
-    for string in string_pools {
-        write_utf8(writer, &string)?;
+    // v29 keys are indices into the string pool at the end of the file
+    let mut string_pools = StringPool::new();
+    if options.use_string_pool {
+        for app in apps {
+            collect_string_pools(&mut string_pools, &app.key_values);
+        }
     }
 
-    // Write the offset back
-    if let Some(offset) = offset_back {
-        writer.seek(std::io::SeekFrom::Start(offset))?;
-        writer.write(&(current_pos as i64).to_le_bytes())?;
+    let mut body = Vec::new();
+    write_apps(
+        &mut body,
+        apps,
+        app_info.version,
+        &key_indices(&string_pools),
+        options,
+    )?;
+    // magic + universe + the offset itself
+    let pool_offset = (4 + 4 + 8 + body.len()) as i64;
+    writer.write_all(&pool_offset.to_le_bytes())?;
+    writer.write_all(&body)?;
+
+    writer.write_all(&(string_pools.len() as u32).to_le_bytes())?;
+    for string in &string_pools {
+        write_utf8(writer, string)?;
     }
 
     Ok(())
 }
 
-fn write_package<W: std::io::Write>(writer: &mut W, package_info: &Package) -> std::io::Result<()> {
+fn write_apps<W: std::io::Write>(
+    writer: &mut W,
+    apps: &[&App],
+    version: AppInfoVersion,
+    key_indices: &KeyIndices,
+    options: &WriterOptions,
+) -> std::io::Result<()> {
+    if options.threads > 1 && apps.len() > 1 {
+        for encoded in encode_apps(apps, version, key_indices, options) {
+            writer.write_all(&encoded?)?;
+        }
+    } else {
+        for app in apps {
+            write_app(writer, app, &version, key_indices, options)?;
+        }
+    }
+    // End of apps
+    writer.write_all(&0u32.to_le_bytes())
+}
+
+/// Encode the apps on `options.threads` threads, each taking a contiguous run of apps, and
+/// return their buffers in the order of the apps.
+fn encode_apps(
+    apps: &[&App],
+    version: AppInfoVersion,
+    key_indices: &KeyIndices,
+    options: &WriterOptions,
+) -> Vec<std::io::Result<Vec<u8>>> {
+    let run = apps.len().div_ceil(options.threads);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = apps
+            .chunks(run)
+            .map(|run| {
+                scope.spawn(move || {
+                    let mut buffer = Vec::new();
+                    for app in run {
+                        write_app(&mut buffer, app, &version, key_indices, options)?;
+                    }
+                    Ok(buffer)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|_| Err(std::io::Error::other("app encoding thread panicked")))
+            })
+            .collect()
+    })
+}
+
+fn write_package<W: std::io::Write>(
+    writer: &mut W,
+    package_info: &Package,
+    version: &PkgInfoVersion,
+    options: &WriterOptions,
+) -> std::io::Result<()> {
     // Write the package
     writer.write_all(&package_info.id.to_le_bytes())?;
     writer.write_all(&*package_info.checksum)?;
     writer.write_all(&package_info.change_number.to_le_bytes())?;
-    if let Some(pics) = &package_info.pics {
-        writer.write_all(&pics.to_le_bytes())?;
+    // The layout follows the version, a missing token is written as zero
+    if version.has_token() {
+        writer.write_all(&package_info.token.unwrap_or_default().to_le_bytes())?;
     }
 
-    write_keyvalues_internal(writer, &package_info.key_values, &mut HashSet::new())
+    write_keyvalues_internal(
+        writer,
+        &package_info.key_values,
+        &KeyIndices::new(),
+        options,
+    )
 }
 
 pub fn write_package_info<W: std::io::Write>(
     writer: &mut W,
     package_info: &PackageInfo,
 ) -> std::io::Result<()> {
-    // Write the package info
-    let version_magic: u32 = package_info.version.into();
-    writer.write_all(&version_magic.to_le_bytes())?;
-    writer.write_all(&package_info.universe.to_le_bytes())?;
-    for (_, package) in &package_info.packages {
-        write_package(writer, package)?;
-    }
+    write_package_info_with_options(writer, package_info, &WriterOptions::default())
+}
 
-    Ok(())
+/// Write the package info with the given options.
+pub fn write_package_info_with_options<W: std::io::Write>(
+    writer: &mut W,
+    package_info: &PackageInfo,
+    options: &WriterOptions,
+) -> std::io::Result<()> {
+    write_buffered(writer, |writer| {
+        // Write the package info
+        let version_magic: u32 = package_info.version.into();
+        writer.write_all(&version_magic.to_le_bytes())?;
+        writer.write_all(&package_info.universe.to_le_bytes())?;
+        for package in package_info.packages.values() {
+            write_package(writer, package, &package_info.version, options)?;
+        }
+        // End of packages
+        writer.write_all(&0xffffffffu32.to_le_bytes())
+    })
 }
@@ -30,16 +30,2033 @@ fn read_input_output(test_name: &str) -> (Vec<u8>, String) {
 fn compare_standard_kv(test_name: &str) {
     let (input, expected_output) = read_input_output(test_name);
 
+    // Compared as JSON values, the key order differs with the `preserve_order` feature
     let serde_parsed: serde_json::Value = serde_json::from_str(&expected_output).unwrap();
     let vdf_parsed = vdfr::parser::parse_keyvalues(&input).unwrap();
 
-    let unserde_expect = serde_json::to_string(&serde_parsed).unwrap();
-    let unserde_vdf = serde_json::to_string(&vdf_parsed).unwrap();
+    assert_eq!(serde_parsed, serde_json::to_value(&vdf_parsed).unwrap());
 
-    assert_eq!(unserde_expect, unserde_vdf);
+    let legacy_parsed = vdfr::legacy_parser::parse_keyvalues(
+        &mut std::io::Cursor::new(&input),
+        vdfr::KeyValueOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(serde_parsed, serde_json::to_value(&legacy_parsed).unwrap());
 }
 
 #[test]
 fn test_widestring() {
     compare_standard_kv("widestring");
 }
+
+fn make_app(id: u32, name: &str) -> vdfr::App {
+    let mut common = vdfr::KeyValues::new();
+    common.insert(
+        "name".into(),
+        vdfr::Value::StringType(name.to_string().into()),
+    );
+    let mut key_values = vdfr::KeyValues::new();
+    key_values.insert("common".into(), vdfr::Value::KeyValueType(common));
+
+    let mut kv_data = Vec::new();
+    vdfr::writer::write_keyvalues(&mut kv_data, &key_values).unwrap();
+    // state + last_update + access_token + checksum_txt + change_number + checksum_bin
+    let size = 4 + 4 + 8 + 20 + 4 + 20 + kv_data.len() as u32;
+
+    vdfr::App {
+        id,
+        size,
+        state: 2,
+        last_update: 0,
+        access_token: 0,
+        checksum_txt: vdfr::SHA1::default(),
+        checksum_bin: Some(vdfr::SHA1::default()),
+        change_number: id,
+        key_values,
+        raw: None,
+    }
+}
+
+fn make_app_info(apps: &[(u32, &str)]) -> (vdfr::AppInfo, Vec<u8>) {
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V28,
+        universe: 1,
+        apps: apps
+            .iter()
+            .map(|&(id, name)| (id, make_app(id, name)))
+            .collect(),
+        trailing_bytes: 0,
+    };
+
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+    (app_info, cursor_writer.into_inner())
+}
+
+fn section(entries: Vec<(&str, vdfr::Value)>) -> vdfr::Value {
+    vdfr::Value::KeyValueType(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect::<vdfr::KeyValues>(),
+    )
+}
+
+#[test]
+fn test_resilient_skips_corrupt_app() {
+    for version in [vdfr::AppInfoVersion::V28, vdfr::AppInfoVersion::V29] {
+        let mut apps = vdfr::Map::new();
+        for (id, name) in [(10, "First"), (20, "Second"), (30, "Third")] {
+            apps.insert(id, make_app(id, name));
+        }
+        let app_info = vdfr::AppInfo {
+            version,
+            universe: 1,
+            apps,
+            trailing_bytes: 0,
+        };
+
+        let mut cursor_writer = std::io::Cursor::new(Vec::new());
+        vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+        let mut data = cursor_writer.into_inner();
+
+        // Corrupt the type byte of the "common" node of the second app
+        let second_app = data
+            .windows(4)
+            .position(|w| w == 20u32.to_le_bytes())
+            .unwrap();
+        let kv_start = second_app + 4 + 4 + 4 + 4 + 8 + 20 + 4 + 20;
+        data[kv_start] = 0x42;
+
+        assert!(vdfr::parser::parse_app_info(&data).is_err());
+
+        let (parsed, skipped) = vdfr::parser::parse_app_info_resilient(&data).unwrap();
+        assert_eq!(
+            parsed.apps.keys().copied().collect::<Vec<_>>(),
+            vec![10, 30]
+        );
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].id, 20);
+        assert_eq!(skipped[0].offset, second_app, "{:?}", version);
+    }
+}
+
+#[test]
+fn test_filtered_app_info() {
+    let (app_info, data) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+
+    let parsed = vdfr::parser::parse_app_info_filtered(&data, |id| id != 20).unwrap();
+    assert_eq!(
+        parsed.apps.keys().copied().collect::<Vec<_>>(),
+        vec![10, 30]
+    );
+    assert_eq!(
+        serde_json::to_string(&parsed.apps[&30].key_values).unwrap(),
+        serde_json::to_string(&app_info.apps[&30].key_values).unwrap()
+    );
+}
+
+#[test]
+fn test_parse_app_at() {
+    let (app_info, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+
+    // Skip the version and universe header, then the whole first app
+    let second_app = 4 + 4 + 4 + 4 + app_info.apps[&10].size as usize;
+    let options = vdfr::KeyValueOptions::default();
+    let app =
+        vdfr::parser::parse_app_at(&data, second_app, &options, vdfr::AppInfoVersion::V28).unwrap();
+    assert_eq!(app.id, 20);
+    assert_eq!(app.change_number, 20);
+
+    assert!(matches!(
+        vdfr::parser::parse_app_at(&data, data.len() + 1, &options, vdfr::AppInfoVersion::V28),
+        Err(vdfr::VdfrError::InvalidOffset(_))
+    ));
+}
+
+#[test]
+fn test_sidecar_index() {
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+
+    let index = vdfr::index::AppInfoIndex::build(&data).unwrap();
+    assert_eq!(index.entries.len(), 3);
+    assert_eq!(index.entries[&30].change_number, 30);
+
+    let mut index_data = Vec::new();
+    index.write(&mut index_data).unwrap();
+    let index = vdfr::index::AppInfoIndex::read(&mut index_data.as_slice()).unwrap();
+
+    let indexed = vdfr::index::IndexedAppInfo::open(&data, index.clone()).unwrap();
+    let app = indexed.get(20).unwrap().unwrap();
+    assert_eq!(app.id, 20);
+    assert!(indexed.get(40).unwrap().is_none());
+
+    assert!(matches!(
+        vdfr::index::IndexedAppInfo::open(&data[..data.len() - 1], index),
+        Err(vdfr::VdfrError::StaleIndex)
+    ));
+    assert_eq!(
+        vdfr::index::sidecar_path(std::path::Path::new("appinfo.vdf")),
+        std::path::PathBuf::from("appinfo.vdf.idx")
+    );
+}
+
+#[test]
+fn test_app_info_cache() {
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+
+    let mut cache = Vec::new();
+    parsed.save_cache(&mut cache).unwrap();
+    let loaded = vdfr::AppInfo::load_cache(&mut cache.as_slice()).unwrap();
+    assert_eq!(
+        serde_json::to_string(&loaded).unwrap(),
+        serde_json::to_string(&parsed).unwrap()
+    );
+
+    assert!(matches!(
+        vdfr::AppInfo::load_cache(&mut data.as_slice()),
+        Err(vdfr::VdfrError::UnknownMagic(_))
+    ));
+}
+
+#[test]
+fn test_app_info_archive() {
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+
+    let archive = parsed.to_archive().unwrap();
+    let archived = vdfr::archive::access(&archive).unwrap();
+    assert_eq!(archived.apps.len(), 2);
+    assert!(matches!(
+        archived.app(20).unwrap().get(&["common", "name"]),
+        Some(vdfr::ArchivedValue::StringType(name)) if name.as_str() == "Second"
+    ));
+    assert!(archived.app(30).is_none());
+
+    let loaded = vdfr::AppInfo::from_archive(&archive).unwrap();
+    assert_eq!(
+        serde_json::to_string(&loaded).unwrap(),
+        serde_json::to_string(&parsed).unwrap()
+    );
+
+    assert!(vdfr::archive::access(&archive[..archive.len() / 2]).is_err());
+}
+
+#[test]
+fn test_compressed_input() {
+    use std::io::Write;
+
+    let (app_info, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&data).unwrap();
+    let gzip = gzip.finish().unwrap();
+    let zstd = zstd::stream::encode_all(data.as_slice(), 0).unwrap();
+
+    for (compressed, compression) in [
+        (&gzip, vdfr::compression::Compression::Gzip),
+        (&zstd, vdfr::compression::Compression::Zstd),
+        (&data, vdfr::compression::Compression::None),
+    ] {
+        assert_eq!(
+            vdfr::compression::Compression::detect(compressed),
+            compression
+        );
+        let decompressed = vdfr::compression::decompress(compressed).unwrap();
+        assert_eq!(*decompressed, *data);
+        let parsed = vdfr::parser::parse_app_info(&decompressed).unwrap();
+        assert_eq!(parsed.apps.len(), app_info.apps.len());
+    }
+
+    assert!(matches!(
+        vdfr::compression::decompress(&zstd[..zstd.len() / 2]),
+        Err(vdfr::VdfrError::Truncated | vdfr::VdfrError::ReadError(_))
+    ));
+}
+
+#[test]
+fn test_update_from() {
+    let (mut app_info, data) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+    let mut parsed = vdfr::parser::parse_app_info(&data).unwrap();
+
+    let mut changed = make_app(20, "Second v2");
+    changed.change_number = 21;
+    app_info.apps.insert(20, changed);
+    app_info.apps.retain(|&id, _| id != 30);
+    app_info.apps.insert(40, make_app(40, "Fourth"));
+
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+    let new_data = cursor_writer.into_inner();
+
+    let updated = parsed.update_from(&new_data).unwrap();
+    assert_eq!(updated, vec![20, 40]);
+    assert_eq!(
+        serde_json::to_string(&parsed).unwrap(),
+        serde_json::to_string(&vdfr::parser::parse_app_info(&new_data).unwrap()).unwrap()
+    );
+}
+
+fn make_package(id: u32, app_ids: &[i32]) -> vdfr::Package {
+    let mut package = vdfr::KeyValues::new();
+    package.insert(
+        "appids".into(),
+        vdfr::Value::ArrayType(
+            app_ids
+                .iter()
+                .map(|&id| vdfr::Value::Int32Type(id))
+                .collect(),
+        ),
+    );
+    let mut key_values = vdfr::KeyValues::new();
+    key_values.insert(id.to_string().into(), vdfr::Value::KeyValueType(package));
+
+    vdfr::Package {
+        id,
+        checksum: vdfr::SHA1::default(),
+        change_number: id,
+        token: Some(0),
+        key_values,
+    }
+}
+
+#[test]
+fn test_catalog() {
+    let (app_info, _) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+    let package_info = vdfr::PackageInfo {
+        version: vdfr::PkgInfoVersion::V28,
+        universe: 1,
+        packages: [
+            (1, make_package(1, &[10, 20])),
+            (2, make_package(2, &[20, 30])),
+        ]
+        .into_iter()
+        .collect(),
+        trailing_bytes: 0,
+    };
+
+    let catalog = vdfr::catalog::Catalog::new(&app_info, &package_info);
+    assert_eq!(catalog.packages_for_app(20), &[1, 2]);
+    assert_eq!(catalog.packages_for_app(40), &[] as &[u32]);
+    assert_eq!(catalog.apps_in_package(2), &[20, 30]);
+    assert_eq!(
+        catalog.owned_apps([1]).into_iter().collect::<Vec<_>>(),
+        vec![10, 20]
+    );
+    assert_eq!(catalog.app(30).unwrap().id, 30);
+}
+
+#[test]
+fn test_limits() {
+    let (input, _) = read_input_output("widestring");
+
+    let mut options = vdfr::KeyValueOptions::default();
+    assert!(vdfr::parser::parse_keyvalues_with_options(&input, &options).is_ok());
+
+    options.limits.max_depth = 1;
+    let result = vdfr::parser::parse_keyvalues_with_options(&input, &options);
+    assert!(matches!(
+        result,
+        Err(vdfr::VdfrError::DepthLimitExceeded(1))
+    ));
+
+    options.limits = vdfr::ParseLimits {
+        max_string_len: 5,
+        ..Default::default()
+    };
+    let result = vdfr::parser::parse_keyvalues_with_options(&input, &options);
+    assert!(matches!(
+        result,
+        Err(vdfr::VdfrError::LimitExceeded("max_string_len", 5))
+    ));
+
+    options.limits = vdfr::ParseLimits {
+        max_nodes: 2,
+        ..Default::default()
+    };
+    let result = vdfr::parser::parse_keyvalues_with_options(&input, &options);
+    assert!(matches!(
+        result,
+        Err(vdfr::VdfrError::LimitExceeded("max_nodes", 2))
+    ));
+}
+
+#[test]
+fn test_malformed_input_does_not_panic() {
+    let (input, _) = read_input_output("widestring");
+
+    // Every truncation of a valid file
+    for len in 0..input.len() {
+        let _ = vdfr::parser::parse_keyvalues(&input[..len]);
+    }
+
+    // Every single-byte corruption of a valid file
+    for pos in 0..input.len() {
+        for byte in [0x00, 0x01, 0x05, 0x08, 0x7f, 0xff] {
+            let mut data = input.clone();
+            data[pos] = byte;
+            let _ = vdfr::parser::parse_keyvalues(&data);
+        }
+    }
+
+    // Deeply nested key-values must not overflow the stack
+    let nested = [0x00, b'a', 0x00].repeat(100_000);
+    assert!(matches!(
+        vdfr::parser::parse_keyvalues(&nested),
+        Err(vdfr::VdfrError::DepthLimitExceeded(_))
+    ));
+
+    // v29 header with a string pool offset pointing before the payload
+    let mut header = Vec::new();
+    header.extend_from_slice(&0x07_56_44_29u32.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&4i64.to_le_bytes());
+    assert!(matches!(
+        vdfr::parser::parse_app_info(&header),
+        Err(vdfr::VdfrError::InvalidOffset(4))
+    ));
+}
+
+#[test]
+fn test_numeric_like_keys() {
+    // { "list" { "0" "a" "01" "b" } } folds into an array without panicking
+    let mut data = vec![0x00];
+    data.extend_from_slice(b"list\0");
+    data.extend_from_slice(b"\x010\0a\0");
+    data.extend_from_slice(b"\x0101\0b\0");
+    data.extend_from_slice(&[0x08, 0x08]);
+
+    let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
+    let unserde_vdf = serde_json::to_string(&parsed).unwrap();
+    assert_eq!(unserde_vdf, r#"{"list":["a","b"]}"#);
+}
+
+#[test]
+fn test_keep_sequences() {
+    // { "list" { "1" "b" "0" { "0" "x" } } "gap" { "1" "c" } }
+    let mut data = vec![0x00];
+    data.extend_from_slice(b"list\0");
+    data.extend_from_slice(b"\x011\0b\0");
+    data.extend_from_slice(b"\x000\0\x010\0x\0\x08");
+    data.extend_from_slice(b"\x08\x00gap\0\x011\0c\0\x08");
+    data.push(0x08);
+
+    let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
+    assert_eq!(
+        serde_json::to_value(&parsed).unwrap(),
+        serde_json::json!({"gap": {"1": "c"}, "list": [["x"], "b"]})
+    );
+    let legacy =
+        vdfr::legacy_parser::parse_keyvalues(&mut std::io::Cursor::new(&data), Default::default())
+            .unwrap();
+    assert_eq!(parsed, legacy);
+
+    let options = vdfr::KeyValueOptions {
+        keep_sequences: true,
+        ..Default::default()
+    };
+    let parsed = vdfr::parser::parse_keyvalues_with_options(&data, &options).unwrap();
+    assert_eq!(
+        serde_json::to_value(&parsed).unwrap(),
+        serde_json::json!({"gap": {"1": "c"}, "list": {"0": {"0": "x"}, "1": "b"}})
+    );
+}
+
+#[test]
+fn test_intern_keys() {
+    // { "a" { "name" "x" } "b" { "name" "y" } }
+    let mut data = vec![0x00];
+    data.extend_from_slice(b"a\0\x01name\0x\0\x08");
+    data.extend_from_slice(b"\x00b\0\x01name\0y\0\x08");
+    data.push(0x08);
+
+    let options = vdfr::KeyValueOptions {
+        intern_keys: true,
+        ..Default::default()
+    };
+    let parsed = vdfr::parser::parse_keyvalues_with_options(&data, &options).unwrap();
+    let name_key = |section: &str| match &parsed[section] {
+        vdfr::Value::KeyValueType(kv) => kv.keys().next().unwrap().clone(),
+        _ => panic!("expected a section"),
+    };
+    assert!(std::sync::Arc::ptr_eq(&name_key("a"), &name_key("b")));
+
+    let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
+    let name_key = |section: &str| match &parsed[section] {
+        vdfr::Value::KeyValueType(kv) => kv.keys().next().unwrap().clone(),
+        _ => panic!("expected a section"),
+    };
+    assert!(!std::sync::Arc::ptr_eq(&name_key("a"), &name_key("b")));
+}
+
+#[test]
+fn test_approx_heap_size() {
+    assert_eq!(vdfr::Value::StringType("Game".into()).approx_heap_size(), 0);
+    let owned = vdfr::Value::StringType(String::with_capacity(32).into());
+    assert_eq!(owned.approx_heap_size(), 32);
+
+    let mut section = vdfr::KeyValues::new();
+    section.insert("name".into(), owned.clone());
+    let section = vdfr::Value::KeyValueType(section);
+    assert!(section.approx_heap_size() > owned.approx_heap_size() + "name".len());
+
+    // Interned keys are only counted once
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+    let options = vdfr::KeyValueOptions {
+        intern_keys: true,
+        ..Default::default()
+    };
+    let interned = vdfr::parser::parse_app_info_with_options(&data, &options).unwrap();
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert!(interned.approx_heap_size() < parsed.approx_heap_size());
+    let apps: usize = parsed.apps.values().map(vdfr::App::approx_heap_size).sum();
+    assert!(parsed.approx_heap_size() > apps);
+}
+
+#[test]
+fn test_pool_keys_shared() {
+    let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second")]);
+    app_info.version = vdfr::AppInfoVersion::V29;
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+
+    // Keys from the pool share their storage even without interning
+    let common_key = |app_info: &vdfr::AppInfo, id: u32| {
+        app_info.apps[&id].key_values.keys().next().unwrap().clone()
+    };
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert!(std::sync::Arc::ptr_eq(
+        &common_key(&parsed, 10),
+        &common_key(&parsed, 20)
+    ));
+    let legacy = vdfr::legacy_parser::parse_app_info(&mut std::io::Cursor::new(&data)).unwrap();
+    assert!(std::sync::Arc::ptr_eq(
+        &common_key(&legacy, 10),
+        &common_key(&legacy, 20)
+    ));
+}
+
+#[test]
+fn test_reused_parser() {
+    let (input, _) = read_input_output("widestring");
+    let expected = vdfr::parser::parse_keyvalues(&input).unwrap();
+
+    let mut parser = vdfr::parser::Parser::with_options(vdfr::KeyValueOptions {
+        intern_keys: true,
+        ..Default::default()
+    });
+    let first = parser.parse_keyvalues(&input).unwrap();
+    let second = parser.parse_keyvalues(&input).unwrap();
+    assert_eq!(
+        serde_json::to_value(&first).unwrap(),
+        serde_json::to_value(&expected).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&second).unwrap(),
+        serde_json::to_value(&expected).unwrap()
+    );
+
+    // Interned keys are shared between the blobs until cleared
+    let first_key = first.keys().next().unwrap();
+    assert!(std::sync::Arc::ptr_eq(
+        first_key,
+        second.keys().next().unwrap()
+    ));
+    parser.clear();
+    let third = parser.parse_keyvalues(&input).unwrap();
+    assert!(!std::sync::Arc::ptr_eq(
+        first_key,
+        third.keys().next().unwrap()
+    ));
+
+    // Pool keys are only created once
+    // { pool[0] = 1 }
+    let data = [0x02, 0, 0, 0, 0, 1, 0, 0, 0, 0x08];
+    let mut parser = vdfr::parser::Parser::with_options(vdfr::KeyValueOptions {
+        string_pool: vec!["name".to_string()],
+        ..Default::default()
+    });
+    let first = parser.parse_keyvalues(&data).unwrap();
+    let second = parser.parse_keyvalues(&data).unwrap();
+    assert_eq!(first["name"], vdfr::Value::Int32Type(1));
+    assert!(std::sync::Arc::ptr_eq(
+        first.keys().next().unwrap(),
+        second.keys().next().unwrap()
+    ));
+}
+
+#[test]
+fn test_strict_trailing_bytes() {
+    let (mut input, _) = read_input_output("widestring");
+    input.extend_from_slice(b"junk");
+
+    assert!(vdfr::parser::parse_keyvalues(&input).is_ok());
+
+    let options = vdfr::KeyValueOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let result = vdfr::parser::parse_keyvalues_with_options(&input, &options);
+    assert!(matches!(result, Err(vdfr::VdfrError::TrailingBytes(4))));
+}
+
+#[test]
+fn test_legacy_from_stream() {
+    let mut apps = vdfr::Map::new();
+    for (id, name) in [(10, "First"), (20, "Second")] {
+        apps.insert(id, make_app(id, name));
+    }
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V28,
+        universe: 1,
+        apps,
+        trailing_bytes: 0,
+    };
+
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+    let mut data = cursor_writer.into_inner();
+    // Apps terminator
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    // A byte slice reader can't seek
+    let mut reader = &data[..];
+    let parsed = vdfr::legacy_parser::parse_app_info_from_stream(
+        &mut reader,
+        vdfr::KeyValueOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed.apps.keys().copied().collect::<Vec<_>>(),
+        vec![10, 20]
+    );
+    assert_eq!(
+        parsed.apps[&20]
+            .get(&["common", "name"])
+            .map(|v| format!("{:?}", v)),
+        Some("\"Second\"".to_string())
+    );
+}
+
+#[test]
+fn test_widestring_non_ascii_end() {
+    // { "w" W"AŁ" "k" "v" }, the last wide character has a non-zero high byte
+    let mut data = vec![0x05];
+    data.extend_from_slice(b"w\0");
+    data.extend_from_slice(&[0x41, 0x00, 0x41, 0x01, 0x00, 0x00]);
+    data.extend_from_slice(b"\x01k\0v\0");
+    data.push(0x08);
+
+    let expected = serde_json::json!({ "k": "v", "w": "AŁ" });
+
+    let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), expected);
+
+    // Small buffer to split the wide string across multiple reads
+    let mut reader = std::io::BufReader::with_capacity(3, std::io::Cursor::new(&data));
+    let parsed =
+        vdfr::legacy_parser::parse_keyvalues(&mut reader, vdfr::KeyValueOptions::default())
+            .unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), expected);
+}
+
+#[test]
+fn test_widestring_unaligned_nul_pair() {
+    // { "w" W"A\u{4100}" }, the zero high byte of `A` and the zero low byte of the next
+    // character make a pair that isn't the end
+    let mut data = vec![0x05];
+    data.extend_from_slice(b"w\0");
+    data.extend_from_slice(&[0x41, 0x00, 0x00, 0x41, 0x00, 0x00]);
+    data.push(0x08);
+
+    let expected = serde_json::json!({ "w": "A\u{4100}" });
+
+    let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), expected);
+
+    let mut reader = std::io::BufReader::with_capacity(3, std::io::Cursor::new(&data));
+    let parsed =
+        vdfr::legacy_parser::parse_keyvalues(&mut reader, vdfr::KeyValueOptions::default())
+            .unwrap();
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), expected);
+}
+
+#[test]
+fn test_shortcuts() {
+    compare_standard_kv("shortcuts");
+
+    let (input, _) = read_input_output("shortcuts");
+    let shortcuts = vdfr::shortcuts::parse_shortcuts(&input).unwrap();
+    assert_eq!(shortcuts.len(), 2);
+
+    assert_eq!(shortcuts[0].app_id, 3060399406);
+    assert_eq!(shortcuts[0].app_name, "Heroic");
+    assert_eq!(shortcuts[0].launch_options, "--no-sandbox");
+    assert!(shortcuts[0].allow_overlay);
+    assert_eq!(shortcuts[0].tags, vec!["Launchers", "favorite"]);
+
+    // Older clients use lowercase keys
+    assert_eq!(shortcuts[1].app_name, "Old Game");
+    assert_eq!(shortcuts[1].exe, r"C:\Games\old.exe");
+    assert!(shortcuts[1].is_hidden);
+    assert!(shortcuts[1].tags.is_empty());
+
+    // Entries left after removing some aren't folded, and are ordered by their number
+    let mut input = b"\0shortcuts\0".to_vec();
+    input.extend(b"\x0010\0\x01AppName\0Ten\0\x08");
+    input.extend(b"\x002\0\x01AppName\0Two\0\x08");
+    input.extend(b"\x08\x08");
+    let shortcuts = vdfr::shortcuts::parse_shortcuts(&input).unwrap();
+    let names: Vec<&str> = shortcuts.iter().map(|s| &*s.app_name).collect();
+    assert_eq!(names, ["Two", "Ten"]);
+}
+
+#[test]
+fn test_stats_schema() {
+    let input = std::fs::read(get_tests_dir().join("input/UserGameStatsSchema_440.bin")).unwrap();
+    let schema = vdfr::stats::parse_stats_schema(&input).unwrap();
+
+    assert_eq!(schema.app_id, 440);
+    assert_eq!(schema.game_name, "Team Fortress 2");
+    assert_eq!(schema.version, 12);
+
+    assert_eq!(schema.achievements.len(), 2);
+    let first = &schema.achievements[0];
+    assert_eq!(first.api_name, "TF_PLAY_GAME_EVERYCLASS");
+    assert_eq!(first.display_name["english"], "Head of the Class");
+    assert_eq!(first.display_name["german"], "Klassenbester");
+    assert!(!first.display_name.contains_key("token"));
+    assert_eq!(first.icon_gray, "a_gray.jpg");
+    assert!(!first.hidden);
+    assert!(schema.achievements[1].hidden);
+    assert_eq!(
+        (schema.achievements[1].stat_id, schema.achievements[1].bit),
+        (1, 1)
+    );
+
+    assert_eq!(schema.stats.len(), 2);
+    let kills = &schema.stats[0];
+    assert_eq!(kills.id, 2);
+    assert_eq!(kills.kind, vdfr::stats::StatKind::Int);
+    assert_eq!(kills.display_name["english"], "Kills");
+    assert_eq!((kills.min, kills.max), (Some(0.0), Some(1000.0)));
+    assert!(kills.increment_only);
+
+    let distance = &schema.stats[1];
+    assert_eq!(distance.kind, vdfr::stats::StatKind::Float);
+    assert_eq!(distance.default, 1.5);
+    assert_eq!(distance.display_name["french"], "Distance parcourue");
+    assert_eq!(distance.max, None);
+}
+
+#[test]
+fn test_pre_v27_app_info() {
+    // v26 is v27 without the text checksum
+    let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second")]);
+    app_info.version = vdfr::AppInfoVersion::V26;
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+    let mut data = cursor_writer.into_inner();
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert_eq!(parsed.version, vdfr::AppInfoVersion::V26);
+    assert_eq!(parsed.apps[&20].change_number, 20);
+    assert!(parsed.apps[&20].checksum_bin.is_none());
+    assert_eq!(
+        serde_json::to_string(&parsed.apps[&20].key_values).unwrap(),
+        serde_json::to_string(&app_info.apps[&20].key_values).unwrap()
+    );
+
+    // v24 splits each app in sections prefixed by their type
+    let section = |name: &str, key: &str, value: &str| {
+        let mut inner = vdfr::KeyValues::new();
+        inner.insert(
+            key.into(),
+            vdfr::Value::StringType(value.to_string().into()),
+        );
+        let mut key_values = vdfr::KeyValues::new();
+        key_values.insert(name.into(), vdfr::Value::KeyValueType(inner));
+        let mut data = Vec::new();
+        vdfr::writer::write_keyvalues(&mut data, &key_values).unwrap();
+        data
+    };
+    let mut sections = vec![2u8];
+    sections.extend(section("common", "name", "Old Game"));
+    sections.push(3);
+    sections.extend(section("extended", "developer", "Valve"));
+    sections.push(0);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&0x07_56_44_24u32.to_le_bytes());
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&10u32.to_le_bytes());
+    // state + last_update + change_number + sections
+    data.extend_from_slice(&(4 + 4 + 4 + sections.len() as u32).to_le_bytes());
+    data.extend_from_slice(&2u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&7u32.to_le_bytes());
+    data.extend_from_slice(&sections);
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    let legacy = vdfr::legacy_parser::parse_app_info(&mut std::io::Cursor::new(&data)).unwrap();
+    for app_info in [&parsed, &legacy] {
+        let app = &app_info.apps[&10];
+        assert_eq!(app.change_number, 7);
+        assert_eq!(
+            serde_json::to_string(&app.key_values).unwrap(),
+            r#"{"appinfo":{"appid":10,"common":{"name":"Old Game"},"extended":{"developer":"Valve"}}}"#
+        );
+    }
+
+    let index = vdfr::index::AppInfoIndex::build(&data).unwrap();
+    assert_eq!(index.entries[&10].change_number, 7);
+
+    // The sections can't be written back
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    assert!(vdfr::writer::write_app_info(&mut cursor_writer, &parsed).is_err());
+}
+
+#[test]
+fn test_parse_app_info_with_hint() {
+    let (_, mut data) = make_app_info(&[(10, "First"), (20, "Second")]);
+    // A future version with an unknown magic, but the same layout
+    data[..4].copy_from_slice(&0x07_56_44_30u32.to_le_bytes());
+
+    assert!(matches!(
+        vdfr::parser::parse_app_info(&data),
+        Err(vdfr::VdfrError::UnsupportedVersion(0x07_56_44_30))
+    ));
+
+    let parsed = vdfr::parser::parse_app_info_with_hint(&data, vdfr::AppInfoVersion::V28).unwrap();
+    assert_eq!(parsed.version, vdfr::AppInfoVersion::V28);
+    assert_eq!(
+        parsed.apps.keys().copied().collect::<Vec<_>>(),
+        vec![10, 20]
+    );
+}
+
+#[test]
+fn test_semantic_eq() {
+    use vdfr::{KeyValues, Value};
+
+    let mut numbered = KeyValues::new();
+    numbered.insert("0".into(), Value::Int32Type(7));
+    numbered.insert("1".into(), Value::StringType("b".into()));
+    let mut a = KeyValues::new();
+    a.insert("items".into(), Value::KeyValueType(numbered));
+    a.insert("size".into(), Value::Int32Type(42));
+
+    let mut b = KeyValues::new();
+    b.insert(
+        "items".into(),
+        Value::ArrayType(vec![Value::Int64Type(7), Value::StringType("b".into())]),
+    );
+    b.insert("size".into(), Value::UInt64Type(42));
+
+    assert!(a != b);
+    assert!(vdfr::keyvalues_semantic_eq(&a, &b));
+
+    let strict = vdfr::SemanticEqOptions {
+        sequences: false,
+        integers: false,
+    };
+    assert!(!vdfr::keyvalues_semantic_eq_with_options(&a, &b, &strict));
+    let sequences_only = vdfr::SemanticEqOptions {
+        integers: false,
+        ..Default::default()
+    };
+    assert!(!vdfr::keyvalues_semantic_eq_with_options(
+        &a,
+        &b,
+        &sequences_only
+    ));
+
+    b.insert("size".into(), Value::Int32Type(43));
+    assert!(!vdfr::keyvalues_semantic_eq(&a, &b));
+    assert_eq!(a.clone(), a);
+}
+
+#[test]
+fn test_parse_app_info_with_spans() {
+    let (app_info, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+
+    let (parsed, spans) = vdfr::parser::parse_app_info_with_spans(&data, true).unwrap();
+    assert_eq!(parsed.apps[&20].key_values, app_info.apps[&20].key_values);
+
+    let app = spans.app(20).unwrap();
+    assert_eq!(&data[app.start..app.start + 4], &20u32.to_le_bytes());
+    assert_eq!(app.end - app.start, 8 + parsed.apps[&20].size as usize);
+    assert_eq!(spans.app(10).unwrap().end, app.start);
+
+    let name = spans.node(20, &["common", "name"]).unwrap();
+    assert_eq!(&data[name], b"\x01name\0Second\0");
+    let common = spans.node(20, &["common"]).unwrap();
+    assert_eq!(data[common.start], 0x00);
+    assert_eq!(data[common.end - 1], 0x08);
+    assert!(spans.node(20, &["missing"]).is_none());
+
+    let (_, apps_only) = vdfr::parser::parse_app_info_with_spans(&data, false).unwrap();
+    assert_eq!(apps_only.apps, spans.apps);
+    assert!(apps_only.nodes.is_empty());
+}
+
+#[test]
+fn test_keep_raw() {
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert!(parsed.apps[&10].raw_bytes().is_none());
+
+    let options = vdfr::KeyValueOptions {
+        keep_raw: true,
+        ..Default::default()
+    };
+    let parsed = vdfr::parser::parse_app_info_with_options(&data, &options).unwrap();
+    let (_, spans) = vdfr::parser::parse_app_info_with_spans(&data, false).unwrap();
+    for id in [10, 20] {
+        let raw = parsed.apps[&id].raw_bytes().unwrap();
+        assert_eq!(raw, &data[spans.app(id).unwrap()]);
+    }
+}
+
+#[test]
+fn test_parse_progress() {
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+
+    let mut reports = Vec::new();
+    let mut callback = |progress: vdfr::ParsedSoFar| reports.push(progress);
+    let app_info = vdfr::parser::parse_app_info_with_progress(
+        &data,
+        &vdfr::KeyValueOptions::default(),
+        Some(&mut callback),
+    )
+    .unwrap();
+
+    assert_eq!(reports.len(), app_info.apps.len());
+    assert_eq!(reports[0].items, 1);
+    assert!(reports
+        .windows(2)
+        .all(|w| w[0].bytes_consumed < w[1].bytes_consumed));
+    assert!(reports[2].bytes_consumed <= data.len());
+    assert!(reports.iter().all(|r| r.total_bytes == data.len()));
+}
+
+#[test]
+fn test_parse_stats() {
+    let (_, data) = make_app_info(&[(10, "First"), (20, "Second")]);
+
+    let options = vdfr::KeyValueOptions {
+        intern_keys: true,
+        ..Default::default()
+    };
+    let (_, stats) = vdfr::parser::parse_app_info_with_stats(&data, &options).unwrap();
+    assert_eq!(stats.sections, 2);
+    assert_eq!(stats.strings, 2);
+    assert_eq!(stats.values(), 4);
+    assert_eq!(stats.string_bytes, "First".len() + "Second".len());
+    assert_eq!(stats.max_depth, 2);
+    // "common" and "name" are new in the first app, and pooled in the second
+    assert_eq!(stats.key_lookups, 4);
+    assert_eq!(stats.key_hits, 2);
+    assert_eq!(stats.pool_hit_rate(), 0.5);
+
+    // Kept unknown values count too
+    let (mut app_info, _) = make_app_info(&[(10, "First")]);
+    let app = app_info.apps.get_mut(&10).unwrap();
+    let Some(vdfr::Value::KeyValueType(common)) = app.key_values.get_mut("common") else {
+        unreachable!()
+    };
+    common.insert(
+        "x".into(),
+        vdfr::Value::UnknownType {
+            type_id: 0x09,
+            raw: b"\xde\xad".to_vec(),
+        },
+    );
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+    let options = vdfr::KeyValueOptions {
+        tolerate_unknown_types: true,
+        ..Default::default()
+    };
+    let (_, stats) = vdfr::parser::parse_app_info_with_stats(&data, &options).unwrap();
+    assert_eq!(stats.unknowns, 1);
+    assert_eq!(stats.values(), 3);
+}
+
+#[test]
+fn test_schema_infer() {
+    let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second")]);
+    let app = app_info.apps.get_mut(&20).unwrap();
+    app.key_values.insert(
+        "common".into(),
+        vdfr::Value::KeyValueType(
+            [
+                ("name".into(), vdfr::Value::Int32Type(1)),
+                (
+                    "tags".into(),
+                    vdfr::Value::ArrayType(vec![
+                        vdfr::Value::StringType("a".into()),
+                        vdfr::Value::StringType("b".into()),
+                    ]),
+                ),
+            ]
+            .into(),
+        ),
+    );
+
+    let schema = vdfr::schema::infer(&app_info);
+    let common = schema.field(&["common"]).unwrap();
+    assert_eq!(common.count, 2);
+    assert_eq!(common.types, ["section"].into());
+    let name = schema.field(&["common", "name"]).unwrap();
+    assert_eq!(name.types, ["int32", "string"].into());
+    let tags = schema
+        .field(&["common", "tags", vdfr::schema::ARRAY_ITEM])
+        .unwrap();
+    assert_eq!(tags.count, 2);
+    assert!(schema.field(&["extended"]).is_none());
+}
+
+#[test]
+fn test_analyze_keys() {
+    let pool = ["common", "name", "unused"];
+    // common { name "First" }
+    let mut kv = vec![0x00];
+    kv.extend(0u32.to_le_bytes());
+    kv.push(0x01);
+    kv.extend(1u32.to_le_bytes());
+    kv.extend(b"First\0\x08\x08");
+
+    let mut data = Vec::new();
+    data.extend(0x07_56_44_29u32.to_le_bytes());
+    data.extend(1u32.to_le_bytes());
+    let pool_offset_at = data.len();
+    data.extend(0u64.to_le_bytes());
+    data.extend(10u32.to_le_bytes());
+    data.extend((4 + 4 + 8 + 20 + 4 + 20 + kv.len() as u32).to_le_bytes());
+    data.extend([0; 4 + 4 + 8 + 20 + 4 + 20]);
+    data.extend(&kv);
+    data.extend(0u32.to_le_bytes());
+    let pool_offset = data.len() as u64;
+    data[pool_offset_at..pool_offset_at + 8].copy_from_slice(&pool_offset.to_le_bytes());
+    data.extend((pool.len() as u32).to_le_bytes());
+    for entry in pool {
+        data.extend(entry.as_bytes());
+        data.push(0);
+    }
+
+    let report = vdfr::analysis::analyze_keys(&data).unwrap();
+    assert_eq!(report.pool_size, 3);
+    assert_eq!(report.unused_pool, vec!["unused".to_string()]);
+    assert_eq!(report.keys.len(), 2);
+    let name = report.keys.iter().find(|k| &*k.key == "name").unwrap();
+    assert_eq!((name.count, name.bytes), (1, 1 + 4 + "First\0".len()));
+    assert_eq!(&*report.largest(1)[0].key, "common");
+}
+
+#[test]
+fn test_histograms() {
+    let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+    let dates = [
+        (
+            10,
+            "steam_release_date",
+            vdfr::Value::StringType("1104537600".into()),
+        ),
+        // The last second of 2008
+        (
+            20,
+            "original_release_date",
+            vdfr::Value::Int32Type(1230767999),
+        ),
+    ];
+    for (id, key, date) in dates {
+        let app = app_info.apps.get_mut(&id).unwrap();
+        let Some(vdfr::Value::KeyValueType(common)) = app.key_values.get_mut("common") else {
+            unreachable!()
+        };
+        common.insert(key.into(), date);
+    }
+
+    let histograms = vdfr::analysis::histograms(&app_info, 2);
+    assert_eq!(
+        histograms.release_years,
+        [(2005, 1), (2008, 1)].into_iter().collect()
+    );
+    assert_eq!(histograms.undated, 1);
+    let buckets: Vec<_> = histograms
+        .change_numbers
+        .iter()
+        .map(|bucket| (bucket.start, bucket.end, bucket.count))
+        .collect();
+    assert_eq!(buckets, vec![(10, 20, 2), (21, 30, 1)]);
+    assert_eq!(histograms.depths, [(2, 3)].into_iter().collect());
+    assert_eq!(
+        histograms.value_types,
+        [("int32", 1), ("section", 3), ("string", 4)]
+            .into_iter()
+            .collect()
+    );
+}
+
+#[test]
+fn test_find() {
+    let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+    for id in [10, 30] {
+        let app = app_info.apps.get_mut(&id).unwrap();
+        let Some(vdfr::Value::KeyValueType(common)) = app.key_values.get_mut("common") else {
+            unreachable!()
+        };
+        common.insert("type".into(), vdfr::Value::StringType("Tool".into()));
+    }
+
+    let tools: Vec<u32> = app_info
+        .find(|app| matches!(app.get(&["common", "type"]), Some(vdfr::Value::StringType(t)) if t == "Tool"))
+        .map(|app| app.id)
+        .collect();
+    assert_eq!(tools, vec![10, 30]);
+
+    let root = vdfr::Value::KeyValueType(app_info.apps[&10].key_values.clone());
+    let names: Vec<_> = root.find_all("*/name").collect();
+    assert_eq!(names, vec![&vdfr::Value::StringType("First".into())]);
+    assert_eq!(root.find_all("**/type").count(), 1);
+    assert_eq!(root.find_all("**").count(), 4);
+    assert_eq!(root.find_all("common/missing").count(), 0);
+}
+
+#[test]
+fn test_apps_of_type() {
+    let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+    for (id, app_type) in [(10, "game"), (20, "DLC"), (30, "Game")] {
+        let app = app_info.apps.get_mut(&id).unwrap();
+        let Some(vdfr::Value::KeyValueType(common)) = app.key_values.get_mut("common") else {
+            unreachable!()
+        };
+        common.insert(
+            "type".into(),
+            vdfr::Value::StringType(app_type.to_string().into()),
+        );
+        let sections = std::mem::take(&mut app.key_values);
+        app.key_values
+            .insert("appinfo".into(), vdfr::Value::KeyValueType(sections));
+    }
+
+    let games: Vec<u32> = app_info.games().map(|app| app.id).collect();
+    assert_eq!(games, vec![10, 30]);
+    assert_eq!(app_info.dlcs().count(), 1);
+    assert_eq!(app_info.tools().count(), 0);
+    assert_eq!(app_info.apps[&20].app_type(), Some("DLC"));
+}
+
+#[test]
+fn test_strip_paths() {
+    use vdfr::Value;
+
+    let depot = || {
+        section(vec![
+            ("manifests", Value::Int32Type(1)),
+            ("encryptedmanifests", Value::Int32Type(2)),
+        ])
+    };
+    let appinfo = section(vec![
+        ("depots", section(vec![("1", depot()), ("2", depot())])),
+        (
+            "extended",
+            section(vec![
+                ("developer", Value::StringType("A".into())),
+                ("developer_url", Value::StringType("B".into())),
+                ("homepage", Value::StringType("C".into())),
+            ]),
+        ),
+    ]);
+
+    let (mut app_info, _) = make_app_info(&[(10, "First")]);
+    let app = app_info.apps.get_mut(&10).unwrap();
+    app.key_values = [("appinfo".into(), appinfo)].into_iter().collect();
+    app.raw = Some(Vec::new());
+
+    let removed = app_info.strip_paths(&["depots/*/encryptedmanifests", "extended/developer*"]);
+    assert_eq!(removed, 4);
+    let app = &app_info.apps[&10];
+    assert!(app.raw.is_none());
+    assert_eq!(
+        app.get(&["appinfo", "depots", "2", "manifests"]),
+        Some(&Value::Int32Type(1))
+    );
+    assert!(app
+        .get(&["appinfo", "depots", "2", "encryptedmanifests"])
+        .is_none());
+    let Some(Value::KeyValueType(extended)) = app.get(&["appinfo", "extended"]) else {
+        unreachable!()
+    };
+    assert_eq!(
+        extended.keys().map(|k| &**k).collect::<Vec<_>>(),
+        ["homepage"]
+    );
+
+    assert_eq!(app_info.strip_paths(&["**/manifests"]), 2);
+    assert_eq!(app_info.strip_paths(&["**/manifests", "missing/*"]), 0);
+}
+
+#[test]
+fn test_get_glob() {
+    use vdfr::Value;
+
+    let depot = |gid: &str| {
+        section(vec![(
+            "manifests",
+            section(vec![("public", Value::StringType(gid.to_string().into()))]),
+        )])
+    };
+    let appinfo = section(vec![(
+        "depots",
+        section(vec![
+            ("731", depot("1")),
+            ("732", depot("2")),
+            ("branches", section(vec![])),
+        ]),
+    )]);
+
+    let (mut app_info, _) = make_app_info(&[(10, "First")]);
+    let app = app_info.apps.get_mut(&10).unwrap();
+    app.key_values = [("appinfo".into(), appinfo.clone())].into_iter().collect();
+
+    let found = app.get_glob("depots/*/manifests/public");
+    assert_eq!(
+        found,
+        [
+            (
+                "depots/731/manifests/public".to_string(),
+                &Value::StringType("1".into())
+            ),
+            (
+                "depots/732/manifests/public".to_string(),
+                &Value::StringType("2".into())
+            ),
+        ]
+    );
+    let paths =
+        |found: Vec<(String, &Value)>| found.into_iter().map(|(p, _)| p).collect::<Vec<_>>();
+    assert_eq!(
+        paths(app.get_glob("depots/73*")),
+        ["depots/731", "depots/732"]
+    );
+    assert_eq!(paths(app.get_glob("**/public")).len(), 2);
+    assert!(app.get_glob("depots/*/encryptedmanifests").is_empty());
+
+    // Below a value, `**` also matches the value itself
+    assert_eq!(
+        paths(appinfo.get_glob("depots/branches/**")),
+        ["depots/branches"]
+    );
+}
+
+#[test]
+fn test_name_index() {
+    let (mut app_info, _) = make_app_info(&[
+        (10, "Half-Life 2\u{2122}"),
+        (20, "half life 2"),
+        (30, "Portal"),
+    ]);
+    for app in app_info.apps.values_mut() {
+        let sections = std::mem::take(&mut app.key_values);
+        app.key_values
+            .insert("appinfo".into(), vdfr::Value::KeyValueType(sections));
+    }
+
+    let index = app_info.build_name_index();
+    assert_eq!(index.get("HALF-LIFE 2"), &[10, 20]);
+    assert!(index.get("Portal 2").is_empty());
+    assert_eq!(
+        vdfr::names::normalize_name("  Half-Life:  2 "),
+        "half life 2"
+    );
+
+    assert_eq!(index.fuzzy("portl", 1), vec![(30, 1)]);
+    assert_eq!(index.fuzzy("half life", 2), vec![(10, 2), (20, 2)]);
+    assert!(index.fuzzy("team fortress", 2).is_empty());
+}
+
+#[test]
+fn test_localization() {
+    use vdfr::{KeyValues, Value};
+
+    let tokens = |pairs: &[(&str, &str)]| -> Value {
+        let tokens: KeyValues = pairs
+            .iter()
+            .map(|&(k, v)| (k.into(), Value::StringType(v.to_string().into())))
+            .collect();
+        Value::KeyValueType([("tokens".into(), Value::KeyValueType(tokens))].into())
+    };
+    let localization: KeyValues = [
+        (
+            "english".into(),
+            tokens(&[("#App_Name", "Example"), ("#Mode", "Campaign")]),
+        ),
+        ("french".into(), tokens(&[("#app_name", "Exemple")])),
+    ]
+    .into();
+
+    let french = vdfr::localization::Localizer::new(&localization, "French");
+    assert_eq!(french.token("#APP_NAME").as_deref(), Some("Exemple"));
+    assert_eq!(french.token("mode").as_deref(), Some("Campaign"));
+    assert_eq!(
+        french.resolve("#app_name: #mode, #unknown #"),
+        "Exemple: Campaign, #unknown #"
+    );
+
+    let missing = vdfr::localization::Localizer::new(&localization, "german");
+    assert_eq!(missing.resolve("#app_name"), "Example");
+}
+
+#[test]
+fn test_color() {
+    let value = vdfr::Value::ColorType(i32::from_le_bytes([0xff, 0x80, 0x00, 0x7f]).into());
+    let color = value.as_color().unwrap();
+    assert_eq!(
+        (color.r, color.g, color.b, color.a),
+        (0xff, 0x80, 0x00, 0x7f)
+    );
+    assert_eq!(color.to_string(), "#FF80007F");
+    assert_eq!(vdfr::Value::ColorType(color.to_i32().into()), value);
+    assert!(vdfr::Value::Int32Type(1).as_color().is_none());
+
+    let mut app = make_app(10, "First");
+    app.key_values.insert("color".into(), value);
+    app.key_values.insert(
+        "pointer".into(),
+        vdfr::Value::PointerType(vdfr::Pointer(42)),
+    );
+    let json = app.as_serde_keyvalues();
+    assert_eq!(json["color"], "#FF80007F");
+    assert_eq!(json["pointer"], "*42");
+    assert_eq!(json["common"]["name"], "First");
+    assert_eq!(
+        serde_json::to_value(&app.key_values).unwrap()["pointer"],
+        "*42"
+    );
+
+    let options = vdfr::JsonOptions { raw_integers: true };
+    let json = app.as_serde_keyvalues_with_options(&options);
+    assert_eq!(json["color"], 0x7f0080ff);
+    assert_eq!(json["pointer"], 42);
+}
+
+#[test]
+fn test_json_conversion() {
+    use vdfr::Value;
+
+    let json = serde_json::json!({
+        "name": "A",
+        "small": -5,
+        "large": 76561197960265728u64,
+        "negative": -4294967296i64,
+        "ratio": 0.5,
+        "flag": true,
+        "list": [1, "two"],
+    });
+    let value = Value::try_from(&json).unwrap();
+    let Value::KeyValueType(key_values) = &value else {
+        panic!("expected a map, got {:?}", value);
+    };
+    assert_eq!(key_values["small"], Value::Int32Type(-5));
+    assert_eq!(key_values["large"], Value::UInt64Type(76561197960265728));
+    assert_eq!(key_values["negative"], Value::Int64Type(-4294967296));
+    assert_eq!(key_values["ratio"], Value::Float32Type(0.5));
+    assert_eq!(key_values["flag"], Value::Int32Type(1));
+    assert_eq!(
+        key_values["list"],
+        Value::ArrayType(vec![Value::Int32Type(1), Value::StringType("two".into())])
+    );
+
+    // Back to JSON, only the boolean changes
+    let mut expected = json.clone();
+    expected["flag"] = serde_json::json!(1);
+    assert_eq!(serde_json::Value::from(&value), expected);
+
+    let null = serde_json::json!({ "common": { "name": null } });
+    assert!(matches!(
+        Value::try_from(&null),
+        Err(vdfr::VdfrError::InvalidJson(path)) if path == "common/name"
+    ));
+}
+
+#[test]
+fn test_value_kind() {
+    use vdfr::{Value, ValueKind};
+
+    assert_eq!(Value::ColorType(0.into()).kind(), ValueKind::Color);
+    assert_eq!(Value::ArrayType(vec![]).kind(), ValueKind::Array);
+    assert_eq!(
+        Value::KeyValueType(vdfr::KeyValues::new()).kind(),
+        ValueKind::Map
+    );
+    assert_eq!(Value::WideStringType("".into()).kind_name(), "wstring");
+    assert_eq!(ValueKind::UInt64.to_string(), "uint64");
+}
+
+#[test]
+fn test_bin_type() {
+    use vdfr::BinType;
+
+    for byte in 0..=u8::MAX {
+        if let Some(bin) = BinType::from_u8(byte) {
+            assert_eq!(bin.to_u8(), byte);
+        }
+    }
+    assert_eq!(BinType::from_u8(0x09), None);
+    assert_eq!(BinType::end(true), BinType::EndAlt);
+    assert_eq!(
+        vdfr::Value::ArrayType(vec![]).bin_type(),
+        Some(BinType::Map)
+    );
+}
+
+#[test]
+fn test_sha1() {
+    use vdfr::SHA1;
+
+    let hex = "00112233445566778899aabbccddeeff00112233";
+    let sha1: SHA1 = hex.parse().unwrap();
+    assert_eq!(sha1.to_string(), hex);
+    assert_eq!(format!("{:?}", sha1), hex);
+    assert_eq!(hex.to_uppercase().parse::<SHA1>(), Ok(sha1));
+    assert!(!sha1.is_zero());
+
+    assert!(SHA1::zero().is_zero());
+    assert_eq!(SHA1::zero(), SHA1::default());
+    assert!("0011".parse::<SHA1>().is_err());
+    assert!("+0112233445566778899aabbccddeeff00112233"
+        .parse::<SHA1>()
+        .is_err());
+}
+
+#[test]
+fn test_structured_errors() {
+    use vdfr::VdfrError;
+
+    let (_, data) = make_app_info(&[(10, "First")]);
+    assert!(matches!(
+        vdfr::parser::parse_app_at(
+            &data[..data.len() - 10],
+            16,
+            &Default::default(),
+            vdfr::AppInfoVersion::V28
+        ),
+        Err(VdfrError::Truncated)
+    ));
+    assert!(matches!(
+        vdfr::parser::parse_keyvalues(b"\x01name\0First"),
+        Err(VdfrError::Truncated)
+    ));
+
+    // A string value with an invalid UTF-8 byte
+    let kv = b"\x01name\0\xff\0\x08";
+    assert!(matches!(
+        vdfr::parser::parse_keyvalues(kv),
+        Err(VdfrError::InvalidUtf8)
+    ));
+
+    let options = vdfr::KeyValueOptions {
+        string_pool: vec!["name".to_string()],
+        ..Default::default()
+    };
+    let kv = b"\x01\x05\0\0\0value\0\x08";
+    assert!(matches!(
+        vdfr::parser::parse_keyvalues_with_options(kv, &options),
+        Err(VdfrError::StringPoolIndexOutOfBounds(5, 1))
+    ));
+
+    // A string pool of 5 strings, cut short after the first one
+    let (mut app_info, _) = make_app_info(&[]);
+    app_info.version = vdfr::AppInfoVersion::V29;
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+    let pool_at = i64::from_le_bytes(data[8..16].try_into().unwrap()) as usize;
+    data.truncate(pool_at);
+    data.extend(5u32.to_le_bytes());
+    data.extend(b"name\0");
+    assert!(matches!(
+        vdfr::parser::parse_app_info(&data),
+        Err(VdfrError::Truncated)
+    ));
+
+    let mut pkg = 0x06_56_55_30u32.to_le_bytes().to_vec();
+    pkg.extend(1u32.to_le_bytes());
+    assert!(matches!(
+        vdfr::parser::parse_package_info(&pkg),
+        Err(VdfrError::UnsupportedVersion(0x06_56_55_30))
+    ));
+}
+
+#[test]
+fn test_string_decoding() {
+    use vdfr::{KeyValueOptions, StringDecoding, Value, VdfrError};
+
+    // { "k\xff" "a\xffb" "ok" "fine" }
+    let kv = b"\x01k\xff\0a\xffb\0\x01ok\0fine\0\x08";
+    let parse_both = |string_decoding| {
+        let options = KeyValueOptions {
+            string_decoding,
+            ..Default::default()
+        };
+        let parsed = vdfr::parser::parse_keyvalues_with_options(kv, &options);
+        let legacy = vdfr::legacy_parser::parse_keyvalues(&mut std::io::Cursor::new(kv), options);
+        match (parsed, legacy) {
+            (Ok(parsed), Ok(legacy)) => {
+                assert_eq!(parsed, legacy);
+                Ok(parsed)
+            }
+            (Err(parsed), Err(_)) => Err(parsed),
+            (parsed, legacy) => panic!("parsers disagree: {:?} / {:?}", parsed, legacy),
+        }
+    };
+
+    assert!(matches!(
+        parse_both(StringDecoding::Strict),
+        Err(VdfrError::InvalidUtf8)
+    ));
+
+    let lossy = parse_both(StringDecoding::Lossy).unwrap();
+    assert_eq!(
+        lossy.get("k\u{FFFD}"),
+        Some(&Value::StringType("a\u{FFFD}b".into()))
+    );
+    assert_eq!(lossy.get("ok"), Some(&Value::StringType("fine".into())));
+
+    let raw = parse_both(StringDecoding::Raw).unwrap();
+    let value = &raw["k\u{FFFD}"];
+    assert_eq!(value, &Value::BytesType(b"a\xffb".to_vec()));
+    assert_eq!(value.kind_name(), "bytes");
+    assert_eq!(format!("{:?}", value), "b\"a\\xffb\"");
+    assert_eq!(raw.get("ok"), Some(&Value::StringType("fine".into())));
+
+    let mut written = Vec::new();
+    vdfr::writer::write_keyvalues(&mut written, &raw).unwrap();
+    let reparsed = vdfr::parser::parse_keyvalues_with_options(
+        &written,
+        &KeyValueOptions {
+            string_decoding: StringDecoding::Raw,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(reparsed, raw);
+}
+
+#[test]
+fn test_unknown_types() {
+    use vdfr::{KeyValueOptions, Value};
+
+    // { "a" 1 "s" { "x" <0x09 payload> "y" "z" } }
+    let kv = b"\x02a\0\x01\0\0\0\x00s\0\x09x\0\xde\xad\x01y\0z\0\x08\x08";
+    // Both parsers fail the same way without tolerating unknown types
+    assert!(matches!(
+        vdfr::parser::parse_keyvalues(kv),
+        Err(vdfr::VdfrError::InvalidType(0x09))
+    ));
+    assert!(matches!(
+        vdfr::legacy_parser::parse_keyvalues(
+            &mut std::io::Cursor::new(&kv[..]),
+            KeyValueOptions::default()
+        ),
+        Err(vdfr::VdfrError::InvalidType(0x09))
+    ));
+
+    let options = KeyValueOptions {
+        tolerate_unknown_types: true,
+        ..Default::default()
+    };
+    let parsed = vdfr::parser::parse_keyvalues_with_options(kv, &options).unwrap();
+    assert_eq!(parsed.get("a"), Some(&Value::Int32Type(1)));
+    let Some(Value::KeyValueType(section)) = parsed.get("s") else {
+        panic!("expected a section");
+    };
+    let unknown = &section["x"];
+    assert_eq!(
+        unknown,
+        &Value::UnknownType {
+            type_id: 0x09,
+            raw: b"\xde\xad\x01y\0z\0".to_vec(),
+        }
+    );
+    // The sibling after the unknown value is part of its raw bytes, not a value of its own
+    assert!(!section.contains_key("y"));
+    assert_eq!(section.len(), 1);
+    assert_eq!(unknown.type_byte(), 0x09);
+    assert_eq!(unknown.bin_type(), None);
+    assert_eq!(unknown.kind_name(), "unknown");
+
+    let mut written = Vec::new();
+    vdfr::writer::write_keyvalues(&mut written, &parsed).unwrap();
+    assert_eq!(written, kv);
+
+    // Sections still need their end byte when no unknown value took the rest of the data
+    let truncated = b"\x02a\0\x01\0\0\0\x00s\0\x01y\0z\0";
+    assert!(matches!(
+        vdfr::parser::parse_keyvalues_with_options(truncated, &options),
+        Err(vdfr::VdfrError::Truncated)
+    ));
+}
+
+#[test]
+fn test_iter_app_info() {
+    let (app_info, data) = make_app_info(&[(10, "First"), (20, "Second"), (30, "Third")]);
+
+    let apps = vdfr::parser::iter_app_info(&data).unwrap();
+    assert_eq!(apps.version(), app_info.version);
+    let parsed: Vec<vdfr::App> = apps.map(Result::unwrap).collect();
+    assert_eq!(parsed.len(), 3);
+    for (parsed, app) in parsed.iter().zip(app_info.apps.values()) {
+        assert_eq!(parsed.id, app.id);
+        assert_eq!(parsed.key_values, app.key_values);
+    }
+
+    // Like a full parse, a truncated app is left as trailing bytes
+    let truncated = &data[..data.len() - 10];
+    let full = vdfr::parser::parse_app_info(truncated).unwrap();
+    let ids: Vec<u32> = vdfr::parser::iter_app_info(truncated)
+        .unwrap()
+        .map(|app| app.unwrap().id)
+        .collect();
+    assert_eq!(ids, full.apps.keys().copied().collect::<Vec<_>>());
+    assert_eq!(ids, vec![10, 20]);
+}
+
+#[test]
+fn test_iter_package_info() {
+    let package_info = vdfr::PackageInfo {
+        version: vdfr::PkgInfoVersion::V28,
+        universe: 1,
+        packages: [
+            (1, make_package(1, &[10, 20])),
+            (2, make_package(2, &[20, 30])),
+        ]
+        .into_iter()
+        .collect(),
+        trailing_bytes: 0,
+    };
+    let mut data = Vec::new();
+    vdfr::writer::write_package_info(&mut data, &package_info).unwrap();
+
+    let packages = vdfr::parser::iter_package_info(&data).unwrap();
+    assert_eq!(packages.universe(), 1);
+    let parsed: Vec<vdfr::Package> = packages.map(Result::unwrap).collect();
+    assert_eq!(parsed.len(), 2);
+    for (parsed, package) in parsed.iter().zip(package_info.packages.values()) {
+        assert_eq!(parsed.id, package.id);
+        assert_eq!(parsed.token, package.token);
+        assert_eq!(parsed.key_values, package.key_values);
+    }
+}
+
+#[test]
+fn test_lint_check() {
+    use vdfr::{lint::Rule, Value};
+
+    let string = |value: &str| Value::StringType(value.to_string().into());
+    let depots = section(vec![
+        (
+            "branches",
+            section(vec![("public", section(vec![("buildid", string("1"))]))]),
+        ),
+        (
+            "71",
+            section(vec![(
+                "manifests",
+                section(vec![
+                    ("public", section(vec![("gid", string("10"))])),
+                    ("beta", section(vec![("gid", string("11"))])),
+                ]),
+            )]),
+        ),
+    ]);
+    let (mut app_info, _) = make_app_info(&[(70, "First")]);
+    app_info.version = vdfr::AppInfoVersion::V29;
+    let appinfo = section(vec![
+        ("depots", depots),
+        ("color", Value::ColorType(0.into())),
+    ]);
+    app_info.apps.get_mut(&70).unwrap().key_values =
+        [("appinfo".into(), appinfo)].into_iter().collect();
+
+    let findings = vdfr::lint::check(&app_info);
+    let mut rules: Vec<Rule> = findings.iter().map(|finding| finding.rule).collect();
+    rules.sort();
+    assert_eq!(rules, [Rule::UnexpectedValueType, Rule::DanglingBranch]);
+
+    let dangling = findings
+        .iter()
+        .find(|finding| finding.rule == Rule::DanglingBranch)
+        .unwrap();
+    assert_eq!(dangling.app_id, 70);
+    let path: Vec<&str> = dangling.path.iter().map(|key| &**key).collect();
+    assert_eq!(path, ["depots", "71", "manifests", "beta"]);
+}
+
+#[test]
+fn test_lint_check_data() {
+    use vdfr::lint::Rule;
+
+    let (mut app_info, _) = make_app_info(&[(7, "A")]);
+    app_info.version = vdfr::AppInfoVersion::V29;
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+    assert_eq!(vdfr::lint::check_data(&data).unwrap(), []);
+
+    // magic + universe + pool offset + app ID, then the size field
+    let mut bad_size = data.clone();
+    bad_size[20] += 1;
+    let findings = vdfr::lint::check_data(&bad_size).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule, Rule::SizeMismatch);
+
+    // The value of `name`, covered by the binary checksum
+    let mut bad_value = data.clone();
+    let value_at = data
+        .windows(2)
+        .rposition(|window| window == b"A\0")
+        .unwrap();
+    bad_value[value_at] = b'B';
+    let findings = vdfr::lint::check_data(&bad_value).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule, Rule::ChecksumMismatch);
+}
+
+#[test]
+fn test_appinfo_changes() {
+    use vdfr::Value;
+
+    // Apps wrapped in an `appinfo` section, with a named section for each entry
+    fn app(id: u32, change_number: u32, sections: &[(&str, &str)]) -> vdfr::App {
+        let sections = sections
+            .iter()
+            .map(|&(key, name)| {
+                let name = Value::StringType(name.to_string().into());
+                (key, section(vec![("name", name)]))
+            })
+            .collect();
+        let mut app = make_app(id, "");
+        app.change_number = change_number;
+        app.key_values = [("appinfo".into(), section(sections))]
+            .into_iter()
+            .collect();
+        app
+    }
+    let app_info = |apps: Vec<vdfr::App>| {
+        let (mut app_info, _) = make_app_info(&[]);
+        app_info.apps = apps.into_iter().map(|app| (app.id, app)).collect();
+        app_info
+    };
+
+    let old = app_info(vec![
+        app(10, 1, &[("common", "A"), ("depots", "x")]),
+        app(20, 1, &[("common", "B")]),
+        app(30, 1, &[("common", "C")]),
+        app(40, 1, &[("common", "D")]),
+    ]);
+    let new = app_info(vec![
+        app(10, 2, &[("common", "A2"), ("extended", "y")]),
+        app(20, 1, &[("common", "B")]),
+        app(40, 2, &[("common", "D")]),
+        app(50, 1, &[("common", "E")]),
+    ]);
+
+    let changes = vdfr::diff::appinfo_changes(&old, &new);
+    assert_eq!(changes.added, [50]);
+    assert_eq!(changes.removed, [30]);
+    assert_eq!(changes.changed.len(), 2);
+
+    let change = &changes.changed[0];
+    assert_eq!(change.app_id, 10);
+    assert_eq!((change.old_change_number, change.new_change_number), (1, 2));
+    let sections: Vec<&str> = change.sections.iter().map(|key| &**key).collect();
+    assert_eq!(sections, ["common", "depots", "extended"]);
+
+    // Only the change number differs
+    assert_eq!(changes.changed[1].app_id, 40);
+    assert!(changes.changed[1].sections.is_empty());
+
+    assert!(vdfr::diff::appinfo_changes(&new, &new).is_empty());
+}
+
+#[test]
+fn test_patch_roundtrip() {
+    use vdfr::{
+        patch::{Patch, PatchOp},
+        AppInfoVersion, VdfrError,
+    };
+
+    let write = |version, apps: &[(u32, &str)]| {
+        let (mut app_info, _) = make_app_info(apps);
+        app_info.version = version;
+        let mut data = Vec::new();
+        vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+        data
+    };
+
+    for version in [AppInfoVersion::V28, AppInfoVersion::V29] {
+        let old = write(version, &[(10, "A"), (20, "B"), (30, "C")]);
+        let new = write(version, &[(10, "A"), (20, "Changed"), (40, "D")]);
+
+        let patch = Patch::create(&old, &new).unwrap();
+        let ops: Vec<_> = patch
+            .ops
+            .iter()
+            .map(|op| match op {
+                PatchOp::Add(app_id, _) => ("add", *app_id),
+                PatchOp::Remove(app_id) => ("remove", *app_id),
+                PatchOp::Replace(app_id, _) => ("replace", *app_id),
+            })
+            .collect();
+        assert_eq!(ops, [("replace", 20), ("remove", 30), ("add", 40)]);
+        assert!(patch.order.is_none());
+
+        let bytes = patch.to_bytes();
+        let read = Patch::from_bytes(&bytes).unwrap();
+        assert_eq!(read, patch);
+        assert_eq!(read.apply(&old).unwrap(), new);
+
+        assert!(matches!(patch.apply(&new), Err(VdfrError::InvalidPatch(_))));
+    }
+
+    let old = write(AppInfoVersion::V28, &[(10, "A")]);
+    let patch = Patch::create(&old, &old).unwrap();
+    assert!(patch.ops.is_empty() && patch.tail.is_none());
+    assert_eq!(patch.apply(&old).unwrap(), old);
+}
+
+#[test]
+fn test_walk_mut() {
+    use vdfr::{
+        walk::{Action, WalkMut},
+        Value,
+    };
+
+    let Value::KeyValueType(mut kv) = section(vec![
+        (
+            "common",
+            section(vec![
+                ("homepage", Value::StringType("http://example.com".into())),
+                ("name", Value::StringType("  Name ".into())),
+            ]),
+        ),
+        (
+            "depots",
+            section(vec![("1", section(vec![("secret", Value::Int32Type(1))]))]),
+        ),
+        (
+            "list",
+            Value::ArrayType(vec![Value::Int32Type(1), Value::Int32Type(2)]),
+        ),
+    ]) else {
+        unreachable!()
+    };
+
+    let mut paths = Vec::new();
+    kv.walk_mut(|path, value| {
+        paths.push(path.to_string());
+        match value {
+            _ if path.ends_with("/secret") || path == "list/0" => Action::Remove,
+            Value::StringType(s) if s.starts_with("http:") => {
+                Action::Replace(Value::StringType(s.replacen("http:", "https:", 1).into()))
+            }
+            Value::StringType(s) => {
+                *s = s.trim().to_string().into();
+                Action::Keep
+            }
+            _ => Action::Keep,
+        }
+    });
+
+    assert_eq!(
+        paths,
+        [
+            "common",
+            "common/homepage",
+            "common/name",
+            "depots",
+            "depots/1",
+            "depots/1/secret",
+            "list",
+            "list/0",
+            "list/1",
+        ]
+    );
+    let expected = section(vec![
+        (
+            "common",
+            section(vec![
+                ("homepage", Value::StringType("https://example.com".into())),
+                ("name", Value::StringType("Name".into())),
+            ]),
+        ),
+        ("depots", section(vec![("1", section(vec![]))])),
+        ("list", Value::ArrayType(vec![Value::Int32Type(2)])),
+    ]);
+    assert_eq!(Value::KeyValueType(kv), expected);
+
+    // Replacements aren't walked, and the value itself isn't visited
+    let mut value = section(vec![("a", section(vec![("b", Value::Int32Type(1))]))]);
+    let mut visited = 0;
+    value.walk_mut(|_, _| {
+        visited += 1;
+        Action::Replace(section(vec![("c", Value::Int32Type(2))]))
+    });
+    assert_eq!(visited, 1);
+    assert_eq!(
+        value,
+        section(vec![("a", section(vec![("c", Value::Int32Type(2))]))])
+    );
+}
+
+#[test]
+fn test_flatten() {
+    use vdfr::{flatten::Flatten, Value};
+
+    let Value::KeyValueType(kv) = section(vec![
+        (
+            "common",
+            section(vec![
+                ("empty", section(vec![])),
+                ("name", Value::StringType("Example".into())),
+            ]),
+        ),
+        (
+            "list",
+            Value::ArrayType(vec![
+                section(vec![("c", Value::Int32Type(1))]),
+                Value::Int32Type(2),
+            ]),
+        ),
+    ]) else {
+        unreachable!()
+    };
+
+    let flat = kv.flatten();
+    let entries: Vec<(&str, &Value)> = flat.iter().map(|(k, v)| (&**k, v)).collect();
+    assert_eq!(
+        entries,
+        [
+            ("common/empty", &section(vec![])),
+            ("common/name", &Value::StringType("Example".into())),
+            ("list/0/c", &Value::Int32Type(1)),
+            ("list/1", &Value::Int32Type(2)),
+        ]
+    );
+
+    // Arrays come back as numbered sections
+    let expected = section(vec![
+        (
+            "common",
+            section(vec![
+                ("empty", section(vec![])),
+                ("name", Value::StringType("Example".into())),
+            ]),
+        ),
+        (
+            "list",
+            section(vec![
+                ("0", section(vec![("c", Value::Int32Type(1))])),
+                ("1", Value::Int32Type(2)),
+            ]),
+        ),
+    ]);
+    assert_eq!(Value::KeyValueType(flat.unflatten()), expected);
+    assert!(vdfr::keyvalues_semantic_eq(&flat.unflatten(), &kv));
+}
+
+#[test]
+fn test_search() {
+    use vdfr::{regex::Regex, search::Search, Value};
+
+    let Value::KeyValueType(kv) = section(vec![
+        (
+            "common",
+            section(vec![
+                ("name", Value::StringType("Example".into())),
+                (
+                    "icon",
+                    Value::StringType("https://cdn.example.com/a.png".into()),
+                ),
+            ]),
+        ),
+        (
+            "extended",
+            section(vec![(
+                "urls",
+                Value::ArrayType(vec![Value::StringType("https://cdn.example.com/b".into())]),
+            )]),
+        ),
+        ("url_count", Value::Int32Type(2)),
+    ]) else {
+        unreachable!()
+    };
+
+    let any = Regex::new("").unwrap();
+    let cdn = Regex::new(r"cdn\.example\.com").unwrap();
+    let paths = |matches: Vec<(String, &Value)>| {
+        matches
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        paths(kv.search(&any, Some(&cdn))),
+        ["common/icon", "extended/urls/0"]
+    );
+    // Sections match by key, numbers by their decimal text
+    assert_eq!(
+        paths(kv.search(&Regex::new("^url").unwrap(), None)),
+        ["extended/urls", "url_count"]
+    );
+    assert_eq!(
+        paths(kv.search(&any, Some(&Regex::new("^2$").unwrap()))),
+        ["url_count"]
+    );
+
+    let (path, found) = &kv.search(&Regex::new("^name$").unwrap(), None)[0];
+    assert_eq!(path, "common/name");
+    assert_eq!(*found, &Value::StringType("Example".into()));
+
+    let (mut app_info, _) = make_app_info(&[(10, "First")]);
+    app_info.apps.get_mut(&10).unwrap().key_values = [("appinfo".into(), Value::KeyValueType(kv))]
+        .into_iter()
+        .collect();
+    let matches = app_info.search(&any, Some(&cdn));
+    assert_eq!(matches.len(), 2);
+    assert_eq!((matches[0].0, &*matches[0].1), (10, "appinfo/common/icon"));
+}
+
+#[test]
+fn test_stream_json() {
+    for version in [vdfr::AppInfoVersion::V28, vdfr::AppInfoVersion::V29] {
+        let (mut app_info, _) = make_app_info(&[(10, "First"), (20, "Second")]);
+        app_info.version = version;
+        let app = app_info.apps.get_mut(&10).unwrap();
+        app.key_values.insert(
+            "tags".into(),
+            vdfr::Value::ArrayType(vec![vdfr::Value::Int32Type(1), vdfr::Value::Int32Type(2)]),
+        );
+        let mut data = Vec::new();
+        vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+
+        let mut streamed = Vec::new();
+        vdfr::export::stream_json(&mut streamed, &data).unwrap();
+        let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&streamed).unwrap(),
+            serde_json::to_value(&parsed).unwrap()
+        );
+    }
+
+    // A broken header fails before anything is written
+    let mut streamed = Vec::new();
+    assert!(vdfr::export::stream_json(&mut streamed, b"\x27\x44\x56\x07").is_err());
+    assert!(streamed.is_empty());
+}
@@ -0,0 +1,213 @@
+use std::path::PathBuf;
+
+fn get_tests_dir() -> PathBuf {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is not set");
+    let tests_dir = std::path::Path::new(&manifest_dir).join("tests");
+
+    assert!(
+        tests_dir.exists(),
+        "tests directory does not exist: {}",
+        tests_dir.display()
+    );
+
+    tests_dir
+}
+
+fn read_input_output(test_name: &str, extension: &str) -> (String, String) {
+    let tests_dir = get_tests_dir();
+    let input_dir = tests_dir.join("input");
+    let output_dir = tests_dir.join("output");
+
+    let input_file = input_dir.join(format!("{}.{}", test_name, extension));
+    let output_file = output_dir.join(format!("{}.json", test_name));
+
+    let input = std::fs::read_to_string(&input_file).unwrap();
+    let output = std::fs::read_to_string(&output_file).unwrap();
+
+    (input, output)
+}
+
+fn compare_text_kv(test_name: &str, extension: &str) {
+    let (input, expected_output) = read_input_output(test_name, extension);
+
+    // Compared as JSON values, the key order differs with the `preserve_order` feature
+    let serde_parsed: serde_json::Value = serde_json::from_str(&expected_output).unwrap();
+    let vdf_parsed = vdfr::text::parse_text(&input).unwrap();
+
+    assert_eq!(serde_parsed, serde_json::to_value(&vdf_parsed).unwrap());
+}
+
+#[test]
+fn test_libraryfolders() {
+    compare_text_kv("libraryfolders", "vdf");
+
+    let (input, _) = read_input_output("libraryfolders", "vdf");
+    let libraries = vdfr::steam::parse_library_folders(&input).unwrap();
+    assert_eq!(libraries.len(), 2);
+    assert_eq!(libraries[1].path, PathBuf::from(r"D:\SteamLibrary"));
+    assert_eq!(libraries[1].label, "Games");
+    assert_eq!(libraries[0].apps[&440], 25373818716);
+}
+
+#[test]
+fn test_text_syntax() {
+    let parsed = vdfr::text::parse_text(
+        "// comment\nroot { unquoted value \"esc\" \"a\\\"b\" \"win\" \"1\" [$WIN32] }",
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_value(&parsed).unwrap(),
+        serde_json::json!({ "root": { "esc": "a\"b", "unquoted": "value", "win": "1" } })
+    );
+
+    assert!(matches!(
+        vdfr::text::parse_text("\"root\"\n{\n\"key\" \"value\""),
+        Err(vdfr::VdfrError::InvalidText(3, _))
+    ));
+
+    let nested = |depth: usize| "k {".repeat(depth) + &"}".repeat(depth);
+    let max_depth = vdfr::ParseLimits::DEFAULT_MAX_DEPTH;
+    assert!(vdfr::text::parse_text(&nested(max_depth - 1)).is_ok());
+    assert!(matches!(
+        vdfr::text::parse_text(&nested(100_000)),
+        Err(vdfr::VdfrError::DepthLimitExceeded(max)) if max == max_depth
+    ));
+}
+
+#[test]
+fn test_app_manifest() {
+    let input = std::fs::read_to_string(get_tests_dir().join("input/appmanifest_440.acf")).unwrap();
+    let manifest = vdfr::steam::parse_app_manifest(&input).unwrap();
+
+    assert_eq!(manifest.app_id, 440);
+    assert_eq!(manifest.name, "Team Fortress 2");
+    assert_eq!(manifest.install_dir, "Team Fortress 2");
+    assert!(manifest.is_fully_installed());
+    assert_eq!(manifest.build_id, 13950381);
+    assert_eq!(manifest.size_on_disk, 25373818716);
+    assert_eq!(manifest.installed_depots.len(), 2);
+    assert_eq!(manifest.installed_depots[&441].size, 24903409437);
+}
+
+#[test]
+fn test_login_users() {
+    let input = std::fs::read_to_string(get_tests_dir().join("input/loginusers.vdf")).unwrap();
+    let users = vdfr::steam::parse_login_users(&input).unwrap();
+
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].steam_id, 76561197960287930);
+    assert_eq!(users[0].account_id(), 22202);
+    assert_eq!(users[0].persona_name, "Rabscuttle");
+    assert!(!users[0].most_recent);
+    assert_eq!(users[1].account_name, "second_account");
+    assert!(users[1].most_recent);
+    assert_eq!(users[1].timestamp, 1700000000);
+}
+
+#[test]
+fn test_most_recent_user() {
+    let steam_path = std::env::temp_dir().join(format!("vdfr_steam_{}", std::process::id()));
+    std::fs::create_dir_all(steam_path.join("config")).unwrap();
+    std::fs::copy(
+        get_tests_dir().join("input/loginusers.vdf"),
+        steam_path.join("config/loginusers.vdf"),
+    )
+    .unwrap();
+
+    let steam = vdfr::steam::SteamDir {
+        path: steam_path.clone(),
+    };
+    let user = steam.most_recent_user().unwrap().unwrap();
+    assert_eq!(user.account_name, "second_account");
+    assert_eq!(
+        steam.userdata_path(&user),
+        steam_path.join("userdata").join("39734273")
+    );
+
+    std::fs::remove_dir_all(&steam_path).unwrap();
+}
+
+#[test]
+fn test_user_config() {
+    let input = std::fs::read_to_string(get_tests_dir().join("input/sharedconfig.vdf")).unwrap();
+    let mut config = vdfr::steam::UserConfig::parse(&input).unwrap();
+
+    assert_eq!(config.tags(440), vec!["favorite", "Shooters"]);
+    assert_eq!(config.launch_options(570), Some("-novid -console"));
+    assert_eq!(config.launch_options(440), None);
+
+    config.set_launch_options(440, "-windowed \"quoted\"");
+    config.set_tags(570, &["MOBA".to_string()]);
+    config.set_tags(730, &["Shooters".to_string()]);
+
+    let config = vdfr::steam::UserConfig::parse(&config.to_text()).unwrap();
+    assert_eq!(config.launch_options(440), Some("-windowed \"quoted\""));
+    assert_eq!(config.tags(440), vec!["favorite", "Shooters"]);
+    assert_eq!(config.tags(570), vec!["MOBA"]);
+    assert_eq!(config.tags(730), vec!["Shooters"]);
+
+    let mut empty = vdfr::steam::UserConfig::default();
+    empty.set_launch_options(440, "-novid");
+    assert!(empty
+        .to_text()
+        .starts_with("\"UserLocalConfigStore\"\n{\n\t\"Software\"\n"));
+}
+
+#[test]
+fn test_write_text() {
+    let (input, _) = read_input_output("libraryfolders", "vdf");
+    let parsed = vdfr::text::parse_text(&input).unwrap();
+    let reparsed = vdfr::text::parse_text(&vdfr::text::write_text(&parsed)).unwrap();
+
+    assert_eq!(
+        serde_json::to_string(&parsed).unwrap(),
+        serde_json::to_string(&reparsed).unwrap()
+    );
+
+    // Escaped characters round-trip
+    let mut escaped = vdfr::KeyValues::new();
+    escaped.insert(
+        "line\r\n".into(),
+        vdfr::Value::StringType("a\r\n\t\"\\b".into()),
+    );
+    let written = vdfr::text::write_text(&escaped);
+    assert!(!written.contains('\r'));
+    assert_eq!(vdfr::text::parse_text(&written).unwrap(), escaped);
+}
+
+#[test]
+fn test_registry() {
+    let input = std::fs::read_to_string(get_tests_dir().join("input/registry.vdf")).unwrap();
+    let mut registry = vdfr::steam::Registry::parse(&input).unwrap();
+
+    assert_eq!(registry.language(), Some("english"));
+    assert_eq!(registry.auto_login_user(), Some("gaben"));
+    assert_eq!(registry.installed_apps(), vec![440, 730]);
+
+    let tf2 = registry.app_state(440).unwrap();
+    assert_eq!(tf2.name, "Team Fortress 2");
+    assert!(tf2.installed && !tf2.running && !tf2.updating);
+    assert!(registry.app_state(730).unwrap().updating);
+    assert!(registry.app_state(620).is_none());
+
+    registry.set_language("schinese");
+    registry.set_auto_login_user("robin");
+    registry.set_app_state(
+        570,
+        &vdfr::steam::RegistryAppState {
+            name: "Dota 2".to_string(),
+            installed: true,
+            ..Default::default()
+        },
+    );
+
+    let registry = vdfr::steam::Registry::parse(&registry.to_text()).unwrap();
+    assert_eq!(registry.language(), Some("schinese"));
+    assert_eq!(registry.auto_login_user(), Some("robin"));
+    assert_eq!(registry.installed_apps(), vec![440, 570, 730]);
+    assert_eq!(registry.app_state(570).unwrap().name, "Dota 2");
+
+    let mut empty = vdfr::steam::Registry::default();
+    empty.set_language("english");
+    assert!(empty.to_text().starts_with("\"Registry\"\n{\n\t\"HKCU\"\n"));
+}
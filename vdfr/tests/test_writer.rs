@@ -47,3 +47,563 @@ fn compare_standard_kv_write(test_name: &str) {
 fn test_widestring_write() {
     compare_standard_kv_write("widestring");
 }
+
+#[test]
+fn test_widestring_units_roundtrip() {
+    // { "a" W"A\u{D800}" "b" W"AB" }, an unpaired surrogate and a big-endian string with a BOM
+    let mut data = vec![0x05];
+    data.extend_from_slice(b"a\0");
+    data.extend_from_slice(&[0x41, 0x00, 0x00, 0xd8, 0x00, 0x00]);
+    data.push(0x05);
+    data.extend_from_slice(b"b\0");
+    data.extend_from_slice(&[0xfe, 0xff, 0x00, 0x41, 0x00, 0x42, 0x00, 0x00]);
+    data.push(0x08);
+
+    let parsed = vdfr::parser::parse_keyvalues(&data).unwrap();
+    let mut reader = std::io::Cursor::new(&data);
+    let legacy =
+        vdfr::legacy_parser::parse_keyvalues(&mut reader, vdfr::KeyValueOptions::default())
+            .unwrap();
+    assert_eq!(parsed, legacy);
+    // Code units split across reads are decoded the same way
+    let mut reader = std::io::BufReader::with_capacity(3, std::io::Cursor::new(&data));
+    let legacy =
+        vdfr::legacy_parser::parse_keyvalues(&mut reader, vdfr::KeyValueOptions::default())
+            .unwrap();
+    assert_eq!(parsed, legacy);
+
+    let Some(vdfr::Value::WideStringType(a)) = parsed.get("a") else {
+        panic!("expected a wide string");
+    };
+    assert_eq!(a.units(), [0x41, 0xd800]);
+    assert_eq!(a.to_string_lossy(), "A\u{FFFD}");
+
+    let Some(vdfr::Value::WideStringType(b)) = parsed.get("b") else {
+        panic!("expected a wide string");
+    };
+    assert!(b.is_big_endian());
+    assert_eq!(b.to_string_lossy(), "AB");
+
+    let mut written = Vec::new();
+    vdfr::writer::write_keyvalues(&mut written, &parsed).unwrap();
+    assert_eq!(written, data);
+}
+
+#[test]
+fn test_shortcuts_write() {
+    let (input, _) = read_input_output("shortcuts");
+    let mut shortcuts = vdfr::shortcuts::parse_shortcuts(&input).unwrap();
+
+    let mut added = vdfr::shortcuts::Shortcut::new("Heroic", "\"/usr/bin/heroic\"");
+    added.tags.push("Launchers".to_string());
+    assert_eq!(added.app_id, 2559287007);
+    shortcuts.remove(0);
+    shortcuts[0].launch_options = "-windowed".to_string();
+    shortcuts.push(added);
+
+    let mut output = Vec::new();
+    vdfr::shortcuts::write_shortcuts(&mut output, &shortcuts).unwrap();
+    let reparsed = vdfr::shortcuts::parse_shortcuts(&output).unwrap();
+
+    assert_eq!(reparsed.len(), 2);
+    assert_eq!(reparsed[0].app_name, "Old Game");
+    assert_eq!(reparsed[0].launch_options, "-windowed");
+    assert!(reparsed[0].is_hidden);
+    assert_eq!(reparsed[1].app_id, 2559287007);
+    assert_eq!(reparsed[1].tags, vec!["Launchers"]);
+    assert!(reparsed[1].allow_overlay);
+}
+
+#[test]
+fn test_vbkv_write() {
+    let (input, expected_output) = read_input_output("widestring");
+    let vdf_parsed = vdfr::parser::parse_keyvalues(&input).unwrap();
+
+    let mut data = Vec::new();
+    vdfr::writer::write_vbkv(&mut data, &vdf_parsed).unwrap();
+    assert_eq!(&data[..4], b"VBKV");
+
+    let parsed = vdfr::parser::parse_vbkv(&data).unwrap();
+    let serde_parsed: serde_json::Value = serde_json::from_str(&expected_output).unwrap();
+    assert_eq!(
+        serde_json::to_string(&serde_parsed).unwrap(),
+        serde_json::to_string(&parsed).unwrap()
+    );
+
+    // Corrupt the payload
+    let last = data.len() - 3;
+    data[last] ^= 0xFF;
+    assert!(matches!(
+        vdfr::parser::parse_vbkv(&data),
+        Err(vdfr::VdfrError::ChecksumMismatch(_, _))
+    ));
+
+    // Standard key-values have no VBKV header
+    assert!(matches!(
+        vdfr::parser::parse_vbkv(&input),
+        Err(vdfr::VdfrError::UnknownMagic(_))
+    ));
+}
+
+#[test]
+fn test_package_info_token_write() {
+    let mut key_values = vdfr::KeyValues::new();
+    key_values.insert("billingtype".into(), vdfr::Value::Int32Type(10));
+    let packages = [(1, Some(0x1122_3344_5566_7788)), (2, None)]
+        .into_iter()
+        .map(|(id, token)| {
+            let package = vdfr::Package {
+                id,
+                checksum: vdfr::SHA1::default(),
+                change_number: id,
+                token,
+                key_values: key_values.clone(),
+            };
+            (id, package)
+        })
+        .collect();
+    let mut package_info = vdfr::PackageInfo {
+        version: vdfr::PkgInfoVersion::V28,
+        universe: 1,
+        packages,
+        trailing_bytes: 0,
+    };
+
+    let mut data = Vec::new();
+    vdfr::writer::write_package_info(&mut data, &package_info).unwrap();
+    let parsed = vdfr::parser::parse_package_info(&data).unwrap();
+    assert_eq!(parsed.packages[&1].token, Some(0x1122_3344_5566_7788));
+    assert_eq!(parsed.packages[&2].token, Some(0));
+    assert_eq!(parsed.packages[&2].change_number, 2);
+
+    // v27 has no token, the writer leaves it out even when set
+    package_info.version = vdfr::PkgInfoVersion::V27;
+    let mut data = Vec::new();
+    vdfr::writer::write_package_info(&mut data, &package_info).unwrap();
+    let parsed = vdfr::parser::parse_package_info(&data).unwrap();
+    assert_eq!(parsed.packages.len(), 2);
+    assert_eq!(parsed.packages[&1].token, None);
+    assert_eq!(parsed.packages[&2].change_number, 2);
+}
+
+fn golden_app(id: u32) -> vdfr::App {
+    let mut key_values = vdfr::KeyValues::new();
+    key_values.insert("name".into(), vdfr::Value::StringType("A".into()));
+    vdfr::App {
+        id,
+        size: 0,
+        state: 2,
+        last_update: 3,
+        access_token: 4,
+        checksum_txt: vdfr::SHA1::zero(),
+        checksum_bin: None,
+        change_number: 5,
+        key_values,
+        raw: None,
+    }
+}
+
+#[test]
+fn test_app_info_golden_bytes() {
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V27,
+        universe: 1,
+        apps: [(7, golden_app(7))].into(),
+        trailing_bytes: 0,
+    };
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+
+    let mut expected = Vec::new();
+    expected.extend(0x07_56_44_27u32.to_le_bytes());
+    expected.extend(1u32.to_le_bytes());
+    expected.extend(7u32.to_le_bytes());
+    // Size of everything after the size field
+    expected.extend(49u32.to_le_bytes());
+    expected.extend([2, 0, 0, 0, 3, 0, 0, 0]);
+    expected.extend(4u64.to_le_bytes());
+    expected.extend([0; 20]);
+    expected.extend(5u32.to_le_bytes());
+    expected.extend(b"\x01name\0A\0\x08");
+    // End of apps
+    expected.extend(0u32.to_le_bytes());
+    assert_eq!(cursor_writer.into_inner(), expected);
+}
+
+#[test]
+fn test_app_info_v29_golden_bytes() {
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V29,
+        universe: 1,
+        apps: [(7, golden_app(7))].into(),
+        trailing_bytes: 0,
+    };
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+    let data = cursor_writer.into_inner();
+
+    let kv = b"\x01\0\0\0\0A\0\x08";
+    let mut expected = Vec::new();
+    expected.extend(0x07_56_44_29u32.to_le_bytes());
+    expected.extend(1u32.to_le_bytes());
+    let pool_offset = 16 + 4 + 4 + 4 + 4 + 8 + 20 + 4 + 20 + kv.len() + 4;
+    expected.extend((pool_offset as u64).to_le_bytes());
+    expected.extend(7u32.to_le_bytes());
+    expected.extend(68u32.to_le_bytes());
+    expected.extend([2, 0, 0, 0, 3, 0, 0, 0]);
+    expected.extend(4u64.to_le_bytes());
+    expected.extend([0; 20]);
+    expected.extend(5u32.to_le_bytes());
+    // The binary checksum is the SHA-1 of the key-values
+    let checksum_at = expected.len();
+    expected.extend(&data[checksum_at..checksum_at + 20]);
+    expected.extend(kv);
+    expected.extend(0u32.to_le_bytes());
+    // String pool, with a u32 count
+    expected.extend(1u32.to_le_bytes());
+    expected.extend(b"name\0");
+    assert_eq!(data, expected);
+
+    let parsed = vdfr::parser::parse_app_info_with_options(
+        &data,
+        &vdfr::KeyValueOptions {
+            strict: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(parsed.apps[&7].key_values, app_info.apps[&7].key_values);
+}
+
+#[test]
+fn test_app_size_report() {
+    let app = golden_app(7);
+    // The size field counts everything after the app ID and itself
+    assert_eq!(app.serialized_size(vdfr::AppInfoVersion::V27), 8 + 49);
+    assert_eq!(app.serialized_size(vdfr::AppInfoVersion::V29), 8 + 68);
+
+    let mut large = golden_app(8);
+    large.key_values.insert(
+        "description".into(),
+        vdfr::Value::StringType("A long description".into()),
+    );
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V27,
+        universe: 1,
+        apps: [(7, app), (8, large)].into(),
+        trailing_bytes: 0,
+    };
+    let report = app_info.size_report();
+    assert_eq!(
+        report.iter().map(|size| size.id).collect::<Vec<_>>(),
+        [8, 7]
+    );
+    assert_eq!(report[1].key_values, b"\x01name\0A\0\x08".len());
+
+    // magic + universe, the apps and the end of apps
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+    let total: usize = report.iter().map(|size| size.total()).sum();
+    assert_eq!(data.len(), 8 + total + 4);
+}
+
+#[test]
+fn test_package_info_roundtrip() {
+    let mut key_values = vdfr::KeyValues::new();
+    key_values.insert("billingtype".into(), vdfr::Value::Int32Type(10));
+    let package_info = vdfr::PackageInfo {
+        version: vdfr::PkgInfoVersion::V28,
+        universe: 1,
+        packages: [(
+            3,
+            vdfr::Package {
+                id: 3,
+                checksum: vdfr::SHA1::zero(),
+                change_number: 3,
+                token: Some(0),
+                key_values,
+            },
+        )]
+        .into(),
+        trailing_bytes: 0,
+    };
+
+    let mut data = Vec::new();
+    vdfr::writer::write_package_info(&mut data, &package_info).unwrap();
+    assert_eq!(data[data.len() - 4..], 0xffffffffu32.to_le_bytes());
+
+    let strict = vdfr::KeyValueOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let parsed = vdfr::parser::parse_package_info_with_options(&data, &strict).unwrap();
+    assert_eq!(parsed.packages.len(), 1);
+    assert_eq!(parsed.trailing_bytes, 0);
+    assert_eq!(
+        parsed.packages[&3].key_values,
+        package_info.packages[&3].key_values
+    );
+
+    let mut cursor = std::io::Cursor::new(&data);
+    let legacy = vdfr::legacy_parser::parse_package_info(&mut cursor).unwrap();
+    assert_eq!(legacy.packages.len(), 1);
+}
+
+#[test]
+fn test_app_info_write_without_seek() {
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V29,
+        universe: 1,
+        apps: [(7, golden_app(7)), (8, golden_app(8))].into(),
+        trailing_bytes: 0,
+    };
+
+    // Vec<u8> is only Write, not Seek
+    let mut data: Vec<u8> = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+
+    let mut cursor_writer = std::io::Cursor::new(Vec::new());
+    vdfr::writer::write_app_info(&mut cursor_writer, &app_info).unwrap();
+    assert_eq!(data, cursor_writer.into_inner());
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert_eq!(parsed.apps.len(), 2);
+    assert_eq!(parsed.trailing_bytes, 0);
+}
+
+#[test]
+fn test_app_info_write_filtered() {
+    let mut other = golden_app(9);
+    other
+        .key_values
+        .insert("extra".into(), vdfr::Value::Int32Type(1));
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V29,
+        universe: 1,
+        apps: [(7, golden_app(7)), (8, golden_app(8)), (9, other)].into(),
+        trailing_bytes: 0,
+    };
+
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info_filtered(&mut data, &app_info, &[8, 7]).unwrap();
+
+    // Same as writing an app info with only those apps, so `extra` isn't in the pool
+    let subset = vdfr::AppInfo {
+        apps: [(7, golden_app(7)), (8, golden_app(8))].into(),
+        ..app_info.clone()
+    };
+    let mut expected = Vec::new();
+    vdfr::writer::write_app_info(&mut expected, &subset).unwrap();
+    assert_eq!(data, expected);
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert_eq!(parsed.apps.keys().copied().collect::<Vec<_>>(), [7, 8]);
+
+    let err =
+        vdfr::writer::write_app_info_filtered(&mut Vec::new(), &app_info, &[7, 10]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_writer_options() {
+    let mut app = golden_app(7);
+    app.size = 1234;
+    app.checksum_bin = Some("0123456789abcdef0123456789abcdef01234567".parse().unwrap());
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V29,
+        universe: 1,
+        apps: [(7, app)].into(),
+        trailing_bytes: 0,
+    };
+
+    let options = vdfr::writer::WriterOptions {
+        use_string_pool: false,
+        recompute_sizes: false,
+        recompute_checksums: false,
+        ..Default::default()
+    };
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info_with_options(&mut data, &app_info, &options).unwrap();
+
+    // id, then the stored size
+    assert_eq!(data[16..24], [7, 0, 0, 0, 0xd2, 0x04, 0, 0]);
+    // Keys are written as strings with an empty pool
+    assert!(data.windows(6).any(|window| window == b"\x01name\0"));
+    assert_eq!(data[data.len() - 4..], 0u32.to_le_bytes());
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    let parsed_app = &parsed.apps[&7];
+    assert_eq!(parsed_app.checksum_bin, app_info.apps[&7].checksum_bin);
+    assert_eq!(parsed_app.key_values, app_info.apps[&7].key_values);
+
+    let alt_end = vdfr::writer::WriterOptions {
+        alt_end_byte: true,
+        ..Default::default()
+    };
+    let mut data = Vec::new();
+    vdfr::writer::write_keyvalues_with_options(&mut data, &app_info.apps[&7].key_values, &alt_end)
+        .unwrap();
+    assert_eq!(data, b"\x01name\0A\0\x0b");
+}
+
+#[test]
+fn test_write_app_info_threads() {
+    for version in [vdfr::AppInfoVersion::V27, vdfr::AppInfoVersion::V29] {
+        let app_info = vdfr::AppInfo {
+            version,
+            universe: 1,
+            apps: (1..=10).map(|id| (id, golden_app(id))).collect(),
+            trailing_bytes: 0,
+        };
+        let mut sequential = Vec::new();
+        vdfr::writer::write_app_info(&mut sequential, &app_info).unwrap();
+
+        // More threads than apps, and runs of uneven length
+        for threads in [3, 4, 16] {
+            let options = vdfr::writer::WriterOptions {
+                threads,
+                ..Default::default()
+            };
+            let mut parallel = Vec::new();
+            vdfr::writer::write_app_info_with_options(&mut parallel, &app_info, &options).unwrap();
+            assert_eq!(parallel, sequential);
+        }
+    }
+}
+
+/// Writer counting the writes that reach it.
+#[derive(Default)]
+struct CountingWriter {
+    writes: usize,
+    data: Vec<u8>,
+}
+
+impl std::io::Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writes += 1;
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_buffered() {
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V27,
+        universe: 1,
+        apps: (1..=10).map(|id| (id, golden_app(id))).collect(),
+        trailing_bytes: 0,
+    };
+    let mut expected = Vec::new();
+    vdfr::writer::write_app_info(&mut expected, &app_info).unwrap();
+
+    // The small writes of every app are gathered into a single one
+    let mut writer = CountingWriter::default();
+    vdfr::writer::write_app_info(&mut writer, &app_info).unwrap();
+    assert_eq!(writer.writes, 1);
+    assert_eq!(writer.data, expected);
+
+    let mut writer = CountingWriter::default();
+    vdfr::writer::write_keyvalues(&mut writer, &app_info.apps[&1].key_values).unwrap();
+    assert_eq!(writer.writes, 1);
+    assert_eq!(writer.data, b"\x01name\0A\0\x08");
+}
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn test_write_original_order() {
+    let data = b"\x01b\0x\0\x00a\0\x01d\0y\0\x01c\0z\0\x08\x08";
+    let parsed = vdfr::parser::parse_keyvalues(data).unwrap();
+    let mut written = Vec::new();
+    vdfr::writer::write_keyvalues(&mut written, &parsed).unwrap();
+    assert_eq!(written, data);
+
+    let mut apps = vdfr::Map::new();
+    for id in [8, 7] {
+        let mut app = golden_app(id);
+        app.key_values
+            .insert("appid".into(), vdfr::Value::Int32Type(id as i32));
+        apps.insert(id, app);
+    }
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V29,
+        universe: 1,
+        apps,
+        trailing_bytes: 0,
+    };
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info(&mut data, &app_info).unwrap();
+
+    let parsed = vdfr::parser::parse_app_info(&data).unwrap();
+    assert_eq!(parsed.apps.keys().copied().collect::<Vec<_>>(), [8, 7]);
+    let keys: Vec<&str> = parsed.apps[&8]
+        .key_values
+        .keys()
+        .map(|key| &**key)
+        .collect();
+    assert_eq!(keys, ["name", "appid"]);
+
+    let mut rewritten = Vec::new();
+    vdfr::writer::write_app_info(&mut rewritten, &parsed).unwrap();
+    assert_eq!(rewritten, data);
+}
+
+#[test]
+fn test_compressed_write() {
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V29,
+        universe: 1,
+        apps: [(7, golden_app(7))].into(),
+        trailing_bytes: 0,
+    };
+    let mut plain = Vec::new();
+    vdfr::writer::write_app_info(&mut plain, &app_info).unwrap();
+
+    for compression in [
+        vdfr::compression::Compression::Gzip,
+        vdfr::compression::Compression::Zstd,
+    ] {
+        let mut writer = vdfr::compression::CompressedWriter::new(Vec::new(), compression).unwrap();
+        vdfr::writer::write_app_info(&mut writer, &app_info).unwrap();
+        let data = writer.finish().unwrap();
+
+        assert_eq!(vdfr::compression::Compression::detect(&data), compression);
+        assert_eq!(*vdfr::compression::decompress(&data).unwrap(), *plain);
+    }
+}
+
+#[test]
+fn test_unknown_type_app_resync() {
+    let mut unknown = golden_app(7);
+    unknown.key_values.insert(
+        "x".into(),
+        vdfr::Value::UnknownType {
+            type_id: 0x09,
+            raw: vec![0x08, 0x08, 0x00],
+        },
+    );
+    let app_info = vdfr::AppInfo {
+        version: vdfr::AppInfoVersion::V27,
+        universe: 1,
+        apps: [(7, unknown), (8, golden_app(8))].into(),
+        trailing_bytes: 0,
+    };
+    let options = vdfr::writer::WriterOptions {
+        recompute_sizes: true,
+        ..Default::default()
+    };
+    let mut data = Vec::new();
+    vdfr::writer::write_app_info_with_options(&mut data, &app_info, &options).unwrap();
+
+    let parse_options = vdfr::KeyValueOptions {
+        tolerate_unknown_types: true,
+        ..Default::default()
+    };
+    let parsed = vdfr::parser::parse_app_info_with_options(&data, &parse_options).unwrap();
+    assert_eq!(parsed.apps[&7].key_values, app_info.apps[&7].key_values);
+    assert_eq!(parsed.apps[&8].key_values, app_info.apps[&8].key_values);
+}